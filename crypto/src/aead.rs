@@ -0,0 +1,137 @@
+#![allow(dead_code)]
+
+//! ChaCha20-Poly1305 authenticated encryption (RFC 8439).
+
+use core::fmt;
+
+use crate::chacha20::{self, NONCE_LEN};
+use crate::ct::{constant_time_eq, zeroize};
+use crate::poly1305::{Poly1305, TAG_LEN};
+
+pub const KEY_LEN: usize = chacha20::KEY_LEN;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AeadError {
+    /// Wrong key, nonce or associated data, or the message was altered.
+    Unauthenticated,
+}
+
+/// ChaCha20-Poly1305 under one key. A nonce must never be reused with the
+/// same key.
+pub struct ChaCha20Poly1305 {
+    key: [u8; KEY_LEN],
+}
+
+impl ChaCha20Poly1305 {
+    pub fn new(key: [u8; KEY_LEN]) -> Self {
+        Self { key }
+    }
+
+    /// Encrypts `buf` in place and returns the tag covering it and `aad`.
+    pub fn encrypt_in_place(
+        &self,
+        nonce: &[u8; NONCE_LEN],
+        aad: &[u8],
+        buf: &mut [u8],
+    ) -> [u8; TAG_LEN] {
+        chacha20::apply_keystream(&self.key, nonce, 1, buf);
+        self.tag(nonce, aad, buf)
+    }
+
+    /// Checks `tag` and only then decrypts `buf` in place; on failure `buf`
+    /// is left as it was.
+    pub fn decrypt_in_place(
+        &self,
+        nonce: &[u8; NONCE_LEN],
+        aad: &[u8],
+        buf: &mut [u8],
+        tag: &[u8; TAG_LEN],
+    ) -> Result<(), AeadError> {
+        if !constant_time_eq(&self.tag(nonce, aad, buf), tag) {
+            return Err(AeadError::Unauthenticated);
+        }
+        chacha20::apply_keystream(&self.key, nonce, 1, buf);
+        Ok(())
+    }
+
+    fn tag(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], ciphertext: &[u8]) -> [u8; TAG_LEN] {
+        let mut block = chacha20::block(&self.key, 0, nonce);
+        let mut one_time = [0u8; 32];
+        one_time.copy_from_slice(&block[..32]);
+        let mut mac = Poly1305::new(&one_time);
+        zeroize(&mut block);
+        zeroize(&mut one_time);
+
+        let zeros = [0u8; 16];
+        mac.update(aad);
+        mac.update(&zeros[..(16 - aad.len() % 16) % 16]);
+        mac.update(ciphertext);
+        mac.update(&zeros[..(16 - ciphertext.len() % 16) % 16]);
+        mac.update(&(aad.len() as u64).to_le_bytes());
+        mac.update(&(ciphertext.len() as u64).to_le_bytes());
+        mac.finish()
+    }
+}
+
+impl Drop for ChaCha20Poly1305 {
+    fn drop(&mut self) {
+        zeroize(&mut self.key);
+    }
+}
+
+impl fmt::Debug for ChaCha20Poly1305 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ChaCha20Poly1305(<redacted>)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex;
+    use alloc::format;
+
+    #[test]
+    fn aead_known_answer_and_tampering() {
+        // RFC 8439 section 2.8.2.
+        let mut key = [0u8; KEY_LEN];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = 0x80 + i as u8;
+        }
+        let aead = ChaCha20Poly1305::new(key);
+        let nonce = [
+            0x07, 0, 0, 0, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47,
+        ];
+        let aad = [
+            0x50, 0x51, 0x52, 0x53, 0xc0, 0xc1, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7,
+        ];
+        let plaintext = *b"Ladies and Gentlemen of the class of '99: If I could offer you \
+                           only one tip for the future, sunscreen would be it.";
+        let mut buf = plaintext;
+        let tag = aead.encrypt_in_place(&nonce, &aad, &mut buf);
+        assert_eq!(
+            hex(&buf),
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6\
+             3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36\
+             92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+             3ff4def08e4b7a9de576d26586cec64b6116"
+        );
+        assert_eq!(hex(&tag), "1ae10b594f09e26a7e902ecbd0600691");
+
+        let ciphertext = buf;
+        assert_eq!(
+            aead.decrypt_in_place(&nonce, &aad[1..], &mut buf, &tag),
+            Err(AeadError::Unauthenticated)
+        );
+        buf[0] ^= 1;
+        assert_eq!(
+            aead.decrypt_in_place(&nonce, &aad, &mut buf, &tag),
+            Err(AeadError::Unauthenticated)
+        );
+        buf[0] ^= 1;
+        assert_eq!(buf, ciphertext);
+        assert_eq!(aead.decrypt_in_place(&nonce, &aad, &mut buf, &tag), Ok(()));
+        assert_eq!(buf, plaintext);
+        assert_eq!(format!("{:?}", aead), "ChaCha20Poly1305(<redacted>)");
+    }
+}
@@ -0,0 +1,38 @@
+#![allow(dead_code)]
+
+//! Constant-time comparison and wiping of secrets.
+
+/// Compares two byte strings in time that depends only on their lengths.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    // Keep the optimiser from short-circuiting the fold.
+    core::hint::black_box(diff) == 0
+}
+
+/// Overwrites `buf` with zeros in a way the optimiser will not elide.
+pub fn zeroize<T: Copy + Default>(buf: &mut [T]) {
+    for item in buf.iter_mut() {
+        // SAFETY: `item` is a valid, aligned reference into `buf`.
+        unsafe { core::ptr::write_volatile(item, T::default()) };
+    }
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_and_wipe() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secrets"));
+
+        let mut words = [0xdead_beef_u32; 4];
+        zeroize(&mut words);
+        assert_eq!(words, [0; 4]);
+    }
+}
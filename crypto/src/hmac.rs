@@ -0,0 +1,82 @@
+#![allow(dead_code)]
+
+//! HMAC-SHA-256 (RFC 2104).
+
+use crate::ct::zeroize;
+use crate::sha256::{Sha256, BLOCK_LEN, SHA256_LEN};
+
+/// Incremental HMAC-SHA-256. Cloning a keyed instance skips rehashing the
+/// key, which PBKDF2 relies on.
+#[derive(Clone)]
+pub struct HmacSha256 {
+    inner: Sha256,
+    outer: Sha256,
+}
+
+impl HmacSha256 {
+    pub fn new(key: &[u8]) -> Self {
+        let mut block = [0u8; BLOCK_LEN];
+        if key.len() > BLOCK_LEN {
+            block[..SHA256_LEN].copy_from_slice(&Sha256::digest(key));
+        } else {
+            block[..key.len()].copy_from_slice(key);
+        }
+        let mut pad = block.map(|b| b ^ 0x36);
+        let mut inner = Sha256::new();
+        inner.update(&pad);
+        pad = block.map(|b| b ^ 0x5c);
+        let mut outer = Sha256::new();
+        outer.update(&pad);
+        zeroize(&mut pad);
+        zeroize(&mut block);
+        Self { inner, outer }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finish(self) -> [u8; SHA256_LEN] {
+        let mut inner = self.inner.finish();
+        let mut outer = self.outer;
+        outer.update(&inner);
+        zeroize(&mut inner);
+        outer.finish()
+    }
+}
+
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; SHA256_LEN] {
+    let mut mac = HmacSha256::new(key);
+    mac.update(message);
+    mac.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex;
+
+    #[test]
+    fn hmac_known_answers() {
+        // RFC 4231 test case 2.
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // RFC 4231 test case 6: a key longer than one block is hashed first.
+        assert_eq!(
+            hex(&hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+        let mut split = HmacSha256::new(b"Jefe");
+        split.update(b"what do ya want ");
+        split.update(b"for nothing?");
+        assert_eq!(
+            split.finish(),
+            hmac_sha256(b"Jefe", b"what do ya want for nothing?")
+        );
+    }
+}
@@ -0,0 +1,110 @@
+#![allow(dead_code)]
+
+//! Key derivation: HKDF (RFC 5869) and PBKDF2 (RFC 8018), both over
+//! HMAC-SHA-256.
+
+use crate::ct::zeroize;
+use crate::hmac::{hmac_sha256, HmacSha256};
+use crate::sha256::SHA256_LEN;
+
+/// HKDF-Extract: condenses `ikm` into a pseudorandom key.
+pub fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> [u8; SHA256_LEN] {
+    hmac_sha256(salt, ikm)
+}
+
+/// HKDF-Expand: fills `out` with key material bound to `info`. `out` may
+/// be at most 255 hash lengths long.
+pub fn hkdf_expand(prk: &[u8; SHA256_LEN], info: &[u8], out: &mut [u8]) {
+    assert!(out.len() <= 255 * SHA256_LEN, "HKDF output too long");
+    let keyed = HmacSha256::new(prk);
+    let mut block = [0u8; SHA256_LEN];
+    for (counter, chunk) in (1..=255u8).zip(out.chunks_mut(SHA256_LEN)) {
+        let mut mac = keyed.clone();
+        if counter > 1 {
+            mac.update(&block);
+        }
+        mac.update(info);
+        mac.update(&[counter]);
+        block = mac.finish();
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+    zeroize(&mut block);
+}
+
+/// PBKDF2 with HMAC-SHA-256, for keys derived from passphrases.
+pub fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32, out: &mut [u8]) {
+    let keyed = HmacSha256::new(password);
+    for (index, chunk) in (1u32..).zip(out.chunks_mut(SHA256_LEN)) {
+        let mut mac = keyed.clone();
+        mac.update(salt);
+        mac.update(&index.to_be_bytes());
+        let mut u = mac.finish();
+        let mut t = u;
+        for _ in 1..iterations.max(1) {
+            let mut mac = keyed.clone();
+            mac.update(&u);
+            u = mac.finish();
+            for (t, u) in t.iter_mut().zip(&u) {
+                *t ^= u;
+            }
+        }
+        chunk.copy_from_slice(&t[..chunk.len()]);
+        zeroize(&mut u);
+        zeroize(&mut t);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex;
+
+    #[test]
+    fn hkdf_known_answers() {
+        // RFC 5869 test case 1.
+        let prk = hkdf_extract(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12], &[0x0b; 22]);
+        assert_eq!(
+            hex(&prk),
+            "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5"
+        );
+        let mut okm = [0u8; 42];
+        hkdf_expand(
+            &prk,
+            &[0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9],
+            &mut okm,
+        );
+        assert_eq!(
+            hex(&okm),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+        );
+
+        // The longest output uses every counter value up to 255.
+        let mut okm = [0u8; 255 * SHA256_LEN];
+        hkdf_expand(&[0x0b; SHA256_LEN], b"max", &mut okm);
+        assert_eq!(hex(&okm[..16]), "4902b69c430db8852fe1ffc13fc0527e");
+        assert_eq!(
+            hex(&okm[okm.len() - 16..]),
+            "c9f6e40729c4a043a658b76897ee6d06"
+        );
+    }
+
+    #[test]
+    fn pbkdf2_known_answers() {
+        let mut key = [0u8; 32];
+        pbkdf2_sha256(b"password", b"salt", 1, &mut key);
+        assert_eq!(
+            hex(&key),
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+        );
+        pbkdf2_sha256(b"password", b"salt", 2, &mut key);
+        assert_eq!(
+            hex(&key),
+            "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"
+        );
+        pbkdf2_sha256(b"password", b"salt", 4096, &mut key);
+        assert_eq!(
+            hex(&key),
+            "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+        );
+    }
+}
@@ -0,0 +1,25 @@
+#![no_std]
+
+//! Cryptographic primitives for kernel-side crates. Pure Rust, no
+//! allocation, checked against the standard test vectors.
+
+#[cfg(test)]
+extern crate alloc;
+
+pub mod aead;
+pub mod chacha20;
+pub mod ct;
+pub mod hmac;
+pub mod kdf;
+pub mod poly1305;
+pub mod sha256;
+
+#[cfg(test)]
+fn hex(bytes: &[u8]) -> alloc::string::String {
+    use core::fmt::Write;
+    let mut out = alloc::string::String::new();
+    for byte in bytes {
+        write!(out, "{:02x}", byte).unwrap();
+    }
+    out
+}
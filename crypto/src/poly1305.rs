@@ -0,0 +1,218 @@
+#![allow(dead_code)]
+
+//! Poly1305 one-time authenticator (RFC 8439), in 26-bit limbs so every
+//! product fits in a `u64`.
+
+use crate::ct::zeroize;
+
+pub const KEY_LEN: usize = 32;
+pub const TAG_LEN: usize = 16;
+const BLOCK_LEN: usize = 16;
+const LIMB_MASK: u32 = 0x3ff_ffff;
+
+fn le32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Incremental Poly1305. A key must authenticate only one message.
+pub struct Poly1305 {
+    r: [u32; 5],
+    pad: [u32; 4],
+    h: [u32; 5],
+    buffer: [u8; BLOCK_LEN],
+    buffered: usize,
+}
+
+impl Poly1305 {
+    pub fn new(key: &[u8; KEY_LEN]) -> Self {
+        // Clamp r as the RFC requires while splitting it into limbs.
+        let r = [
+            le32(&key[0..]) & 0x3ff_ffff,
+            (le32(&key[3..]) >> 2) & 0x3ff_ff03,
+            (le32(&key[6..]) >> 4) & 0x3ff_c0ff,
+            (le32(&key[9..]) >> 6) & 0x3f0_3fff,
+            (le32(&key[12..]) >> 8) & 0x00f_ffff,
+        ];
+        let pad = [
+            le32(&key[16..]),
+            le32(&key[20..]),
+            le32(&key[24..]),
+            le32(&key[28..]),
+        ];
+        Self {
+            r,
+            pad,
+            h: [0; 5],
+            buffer: [0; BLOCK_LEN],
+            buffered: 0,
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        if self.buffered > 0 {
+            let take = (BLOCK_LEN - self.buffered).min(data.len());
+            self.buffer[self.buffered..self.buffered + take].copy_from_slice(&data[..take]);
+            self.buffered += take;
+            data = &data[take..];
+            if self.buffered < BLOCK_LEN {
+                return;
+            }
+            let block = self.buffer;
+            self.block(&block, 1 << 24);
+            self.buffered = 0;
+        }
+        let mut blocks = data.chunks_exact(BLOCK_LEN);
+        for block in &mut blocks {
+            self.block(block, 1 << 24);
+        }
+        let rest = blocks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffered = rest.len();
+    }
+
+    pub fn finish(mut self) -> [u8; TAG_LEN] {
+        if self.buffered > 0 {
+            // A short final block is padded with a one byte, so its high
+            // bit is already in place.
+            let mut block = [0u8; BLOCK_LEN];
+            block[..self.buffered].copy_from_slice(&self.buffer[..self.buffered]);
+            block[self.buffered] = 1;
+            self.block(&block, 0);
+            zeroize(&mut block);
+        }
+
+        let mut h = self.h;
+        let mut carry;
+        for i in 1..5 {
+            carry = h[i - 1] >> 26;
+            h[i - 1] &= LIMB_MASK;
+            h[i] += carry;
+        }
+        carry = h[4] >> 26;
+        h[4] &= LIMB_MASK;
+        h[0] += carry * 5;
+        carry = h[0] >> 26;
+        h[0] &= LIMB_MASK;
+        h[1] += carry;
+
+        // g = h + 5 - 2^130; keep it instead of h when it did not go negative.
+        let mut g = [0u32; 5];
+        carry = 5;
+        for i in 0..4 {
+            g[i] = h[i] + carry;
+            carry = g[i] >> 26;
+            g[i] &= LIMB_MASK;
+        }
+        g[4] = h[4].wrapping_add(carry).wrapping_sub(1 << 26);
+        let use_g = (g[4] >> 31).wrapping_sub(1);
+        for (h, g) in h.iter_mut().zip(&g) {
+            *h = (*h & !use_g) | (g & use_g);
+        }
+
+        let words = [
+            h[0] | (h[1] << 26),
+            (h[1] >> 6) | (h[2] << 20),
+            (h[2] >> 12) | (h[3] << 14),
+            (h[3] >> 18) | (h[4] << 8),
+        ];
+        let mut tag = [0u8; TAG_LEN];
+        let mut sum = 0u64;
+        for ((chunk, word), pad) in tag.chunks_exact_mut(4).zip(words).zip(self.pad) {
+            sum = word as u64 + pad as u64 + (sum >> 32);
+            chunk.copy_from_slice(&(sum as u32).to_le_bytes());
+        }
+        zeroize(&mut h);
+        zeroize(&mut g);
+        tag
+    }
+
+    /// Adds one 16-byte block and multiplies by r. `high_bit` is 2^128 in
+    /// limb 4, or zero for an already padded final block.
+    fn block(&mut self, block: &[u8], high_bit: u32) {
+        let [r0, r1, r2, r3, r4] = self.r.map(u64::from);
+        let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+
+        let h = &mut self.h;
+        h[0] += le32(&block[0..]) & LIMB_MASK;
+        h[1] += (le32(&block[3..]) >> 2) & LIMB_MASK;
+        h[2] += (le32(&block[6..]) >> 4) & LIMB_MASK;
+        h[3] += (le32(&block[9..]) >> 6) & LIMB_MASK;
+        h[4] += (le32(&block[12..]) >> 8) | high_bit;
+        let [h0, h1, h2, h3, h4] = h.map(u64::from);
+
+        let mut d = [
+            h0 * r0 + h1 * s4 + h2 * s3 + h3 * s2 + h4 * s1,
+            h0 * r1 + h1 * r0 + h2 * s4 + h3 * s3 + h4 * s2,
+            h0 * r2 + h1 * r1 + h2 * r0 + h3 * s4 + h4 * s3,
+            h0 * r3 + h1 * r2 + h2 * r1 + h3 * r0 + h4 * s4,
+            h0 * r4 + h1 * r3 + h2 * r2 + h3 * r1 + h4 * r0,
+        ];
+        for i in 1..5 {
+            d[i] += d[i - 1] >> 26;
+        }
+        for (h, d) in h.iter_mut().zip(&d) {
+            *h = (*d as u32) & LIMB_MASK;
+        }
+        h[0] += (d[4] >> 26) as u32 * 5;
+        h[1] += h[0] >> 26;
+        h[0] &= LIMB_MASK;
+    }
+}
+
+impl Drop for Poly1305 {
+    fn drop(&mut self) {
+        zeroize(&mut self.r);
+        zeroize(&mut self.pad);
+        zeroize(&mut self.h);
+        zeroize(&mut self.buffer);
+    }
+}
+
+/// Tag for `message` under the one-time `key`.
+pub fn poly1305(key: &[u8; KEY_LEN], message: &[u8]) -> [u8; TAG_LEN] {
+    let mut mac = Poly1305::new(key);
+    mac.update(message);
+    mac.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex;
+
+    #[test]
+    fn poly1305_known_answers() {
+        // RFC 8439 section 2.5.2.
+        let mut key = [0u8; KEY_LEN];
+        key.copy_from_slice(&[
+            0x85, 0xd6, 0xbe, 0x78, 0x57, 0x55, 0x6d, 0x33, 0x7f, 0x44, 0x52, 0xfe, 0x42, 0xd5,
+            0x06, 0xa8, 0x01, 0x03, 0x80, 0x8a, 0xfb, 0x0d, 0xb2, 0xfd, 0x4a, 0xbf, 0xf6, 0xaf,
+            0x41, 0x49, 0xf5, 0x1b,
+        ]);
+        let message = b"Cryptographic Forum Research Group";
+        assert_eq!(
+            hex(&poly1305(&key, message)),
+            "a8061dc1305136c6c22b8baf0c0127a9"
+        );
+        let mut split = Poly1305::new(&key);
+        for chunk in message.chunks(5) {
+            split.update(chunk);
+        }
+        assert_eq!(split.finish(), poly1305(&key, message));
+
+        // RFC 8439 appendix A.3 vector 11: h wraps past 2^130 - 5.
+        let mut key = [0u8; KEY_LEN];
+        key[0] = 1;
+        let mut message = [0u8; 48];
+        message[..16].fill(0xff);
+        message[16..32].copy_from_slice(&[
+            0xfb, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe, 0xfe,
+            0xfe, 0xfe,
+        ]);
+        message[32..].fill(0x01);
+        assert_eq!(
+            hex(&poly1305(&key, &message)),
+            "00000000000000000000000000000000"
+        );
+    }
+}
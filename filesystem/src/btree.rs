@@ -0,0 +1,601 @@
+#![allow(dead_code)]
+
+use alloc::vec::Vec;
+
+use storage::block::BlockDevice;
+
+use crate::pager::{checksum, read_u64, PageId, Pager, PagerConfig, PagerError, NO_PAGE};
+
+const NODE_HEADER: usize = 16;
+const KIND_LEAF: u8 = 1;
+const KIND_BRANCH: u8 = 2;
+
+/// Copy-on-write B+tree mapping byte keys to byte values.
+///
+/// Every mutation rewrites the path from the touched leaf to the root into
+/// fresh pages, so the previously committed tree stays intact on disk until
+/// `commit` swaps the root. Leaves carry no sibling links; range scans walk
+/// down from the root instead.
+pub struct BTree<D: BlockDevice> {
+    pager: Pager<D>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeError {
+    Pager(PagerError),
+    EntryTooLarge,
+}
+
+impl From<PagerError> for TreeError {
+    fn from(err: PagerError) -> Self {
+        Self::Pager(err)
+    }
+}
+
+enum Node {
+    Leaf(Vec<(Vec<u8>, Vec<u8>)>),
+    Branch {
+        keys: Vec<Vec<u8>>,
+        children: Vec<PageId>,
+    },
+}
+
+enum Insertion {
+    Replaced(PageId),
+    Split(PageId, Vec<u8>, PageId),
+}
+
+enum Removal {
+    Absent,
+    Replaced(PageId),
+    Emptied,
+}
+
+impl<D: BlockDevice> BTree<D> {
+    pub fn format(device: D, config: PagerConfig) -> Result<Self, TreeError> {
+        Ok(Self {
+            pager: Pager::format(device, config)?,
+        })
+    }
+
+    /// Opens an existing tree and rebuilds the page allocation map by
+    /// walking every reachable node.
+    pub fn open(device: D, config: PagerConfig) -> Result<Self, TreeError> {
+        let mut tree = Self {
+            pager: Pager::open(device, config)?,
+        };
+        let mut pending = Vec::new();
+        if tree.pager.root() != NO_PAGE {
+            pending.push(tree.pager.root());
+        }
+        while let Some(page) = pending.pop() {
+            tree.pager.mark_used(page)?;
+            if let Node::Branch { children, .. } = tree.load(page)? {
+                pending.extend(children);
+            }
+        }
+        Ok(tree)
+    }
+
+    pub fn into_device(self) -> D {
+        self.pager.into_device()
+    }
+
+    pub fn pager(&self) -> &Pager<D> {
+        &self.pager
+    }
+
+    /// Largest `key.len() + value.len()` accepted by `insert`. Capping
+    /// entries at a quarter page guarantees that any split yields two
+    /// halves that each fit in a page.
+    pub fn max_entry_len(&self) -> usize {
+        (self.pager.page_size() - NODE_HEADER) / 4 - 6
+    }
+
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, TreeError> {
+        let mut page = self.pager.root();
+        while page != NO_PAGE {
+            match self.load(page)? {
+                Node::Leaf(entries) => {
+                    return Ok(entries
+                        .binary_search_by(|(k, _)| k.as_slice().cmp(key))
+                        .ok()
+                        .map(|idx| entries[idx].1.clone()));
+                }
+                Node::Branch { keys, children } => page = children[child_slot(&keys, key)],
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<(), TreeError> {
+        if key.len() + value.len() > self.max_entry_len() {
+            return Err(TreeError::EntryTooLarge);
+        }
+        let root = self.pager.root();
+        if root == NO_PAGE {
+            let leaf = Node::Leaf(alloc::vec![(key.to_vec(), value.to_vec())]);
+            let page = self.store(None, &leaf)?;
+            self.pager.set_root(page);
+            return Ok(());
+        }
+        let new_root = match self.insert_at(root, key, value)? {
+            Insertion::Replaced(page) => page,
+            Insertion::Split(left, separator, right) => self.store(
+                None,
+                &Node::Branch {
+                    keys: alloc::vec![separator],
+                    children: alloc::vec![left, right],
+                },
+            )?,
+        };
+        self.pager.set_root(new_root);
+        Ok(())
+    }
+
+    /// Removes `key`, returning whether it was present.
+    pub fn remove(&mut self, key: &[u8]) -> Result<bool, TreeError> {
+        let root = self.pager.root();
+        if root == NO_PAGE {
+            return Ok(false);
+        }
+        match self.remove_at(root, key)? {
+            Removal::Absent => Ok(false),
+            Removal::Emptied => {
+                self.pager.set_root(NO_PAGE);
+                Ok(true)
+            }
+            Removal::Replaced(page) => {
+                // Collapse single-child branch roots so the tree height shrinks.
+                let mut page = page;
+                while let Node::Branch { keys, children } = self.load(page)? {
+                    if !keys.is_empty() {
+                        break;
+                    }
+                    self.pager.retire(page);
+                    page = children[0];
+                }
+                self.pager.set_root(page);
+                Ok(true)
+            }
+        }
+    }
+
+    /// Visits entries in key order starting at the first key `>= start`
+    /// until `visit` returns `false`.
+    pub fn scan(
+        &self,
+        start: &[u8],
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> Result<(), TreeError> {
+        let root = self.pager.root();
+        if root != NO_PAGE {
+            self.scan_at(root, Some(start), visit)?;
+        }
+        Ok(())
+    }
+
+    pub fn commit(&mut self) -> Result<(), TreeError> {
+        self.pager.commit().map_err(TreeError::Pager)
+    }
+
+    pub fn rollback(&mut self) {
+        self.pager.rollback();
+    }
+
+    fn scan_at(
+        &self,
+        page: PageId,
+        start: Option<&[u8]>,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> Result<bool, TreeError> {
+        match self.load(page)? {
+            Node::Leaf(entries) => {
+                let from = match start {
+                    Some(start) => entries.partition_point(|(k, _)| k.as_slice() < start),
+                    None => 0,
+                };
+                for (key, value) in &entries[from..] {
+                    if !visit(key, value) {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Node::Branch { keys, children } => {
+                let first = start.map_or(0, |start| child_slot(&keys, start));
+                for (offset, child) in children[first..].iter().enumerate() {
+                    let bound = if offset == 0 { start } else { None };
+                    if !self.scan_at(*child, bound, visit)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    fn insert_at(
+        &mut self,
+        page: PageId,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Insertion, TreeError> {
+        let node = match self.load(page)? {
+            Node::Leaf(mut entries) => {
+                match entries.binary_search_by(|(k, _)| k.as_slice().cmp(key)) {
+                    Ok(idx) => entries[idx].1 = value.to_vec(),
+                    Err(idx) => entries.insert(idx, (key.to_vec(), value.to_vec())),
+                }
+                Node::Leaf(entries)
+            }
+            Node::Branch {
+                mut keys,
+                mut children,
+            } => {
+                let slot = child_slot(&keys, key);
+                match self.insert_at(children[slot], key, value)? {
+                    Insertion::Replaced(child) => children[slot] = child,
+                    Insertion::Split(left, separator, right) => {
+                        children[slot] = left;
+                        children.insert(slot + 1, right);
+                        keys.insert(slot, separator);
+                    }
+                }
+                Node::Branch { keys, children }
+            }
+        };
+
+        if node.encoded_len() <= self.pager.page_size() {
+            return Ok(Insertion::Replaced(self.store(Some(page), &node)?));
+        }
+        let (left, separator, right) = node.split();
+        let left = self.store(Some(page), &left)?;
+        let right = self.store(None, &right)?;
+        Ok(Insertion::Split(left, separator, right))
+    }
+
+    fn remove_at(&mut self, page: PageId, key: &[u8]) -> Result<Removal, TreeError> {
+        match self.load(page)? {
+            Node::Leaf(mut entries) => {
+                let idx = match entries.binary_search_by(|(k, _)| k.as_slice().cmp(key)) {
+                    Ok(idx) => idx,
+                    Err(_) => return Ok(Removal::Absent),
+                };
+                entries.remove(idx);
+                if entries.is_empty() {
+                    self.pager.retire(page);
+                    return Ok(Removal::Emptied);
+                }
+                Ok(Removal::Replaced(
+                    self.store(Some(page), &Node::Leaf(entries))?,
+                ))
+            }
+            Node::Branch {
+                mut keys,
+                mut children,
+            } => {
+                let slot = child_slot(&keys, key);
+                match self.remove_at(children[slot], key)? {
+                    Removal::Absent => return Ok(Removal::Absent),
+                    Removal::Emptied => {
+                        children.remove(slot);
+                        if !keys.is_empty() {
+                            keys.remove(slot.saturating_sub(1));
+                        }
+                        if children.is_empty() {
+                            self.pager.retire(page);
+                            return Ok(Removal::Emptied);
+                        }
+                    }
+                    Removal::Replaced(child) => {
+                        children[slot] = child;
+                        self.rebalance(&mut keys, &mut children, slot)?;
+                    }
+                }
+                let node = Node::Branch { keys, children };
+                Ok(Removal::Replaced(self.store(Some(page), &node)?))
+            }
+        }
+    }
+
+    /// Merges an underfull child with a neighbour when the result fits in
+    /// a single page. Nodes are never redistributed, which keeps deletes
+    /// cheap at the cost of some slack in sparse regions.
+    fn rebalance(
+        &mut self,
+        keys: &mut Vec<Vec<u8>>,
+        children: &mut Vec<PageId>,
+        slot: usize,
+    ) -> Result<(), TreeError> {
+        if children.len() < 2 {
+            return Ok(());
+        }
+        let page_size = self.pager.page_size();
+        if self.load(children[slot])?.encoded_len() >= page_size / 4 {
+            return Ok(());
+        }
+        let left_slot = if slot + 1 < children.len() {
+            slot
+        } else {
+            slot - 1
+        };
+        let right_slot = left_slot + 1;
+        let left = self.load(children[left_slot])?;
+        let right = self.load(children[right_slot])?;
+        let separator = &keys[left_slot];
+        let merged = match (left, right) {
+            (Node::Leaf(mut left), Node::Leaf(right)) => {
+                left.extend(right);
+                Node::Leaf(left)
+            }
+            (
+                Node::Branch {
+                    keys: mut left_keys,
+                    children: mut left_children,
+                },
+                Node::Branch {
+                    keys: right_keys,
+                    children: right_children,
+                },
+            ) => {
+                left_keys.push(separator.clone());
+                left_keys.extend(right_keys);
+                left_children.extend(right_children);
+                Node::Branch {
+                    keys: left_keys,
+                    children: left_children,
+                }
+            }
+            _ => return Err(TreeError::Pager(PagerError::Corrupt)),
+        };
+        if merged.encoded_len() > page_size {
+            return Ok(());
+        }
+        let right_page = children.remove(right_slot);
+        keys.remove(left_slot);
+        self.pager.retire(right_page);
+        children[left_slot] = self.store(Some(children[left_slot]), &merged)?;
+        Ok(())
+    }
+
+    fn load(&self, page: PageId) -> Result<Node, TreeError> {
+        let data = self.pager.read(page)?;
+        Node::decode(&data).ok_or(TreeError::Pager(PagerError::Corrupt))
+    }
+
+    fn store(&mut self, previous: Option<PageId>, node: &Node) -> Result<PageId, TreeError> {
+        Ok(self.pager.write(previous, &node.encode())?)
+    }
+}
+
+/// Index of the child whose key range contains `key`.
+fn child_slot(keys: &[Vec<u8>], key: &[u8]) -> usize {
+    keys.partition_point(|k| k.as_slice() <= key)
+}
+
+impl Node {
+    fn encoded_len(&self) -> usize {
+        match self {
+            Self::Leaf(entries) => {
+                NODE_HEADER
+                    + entries
+                        .iter()
+                        .map(|(k, v)| 6 + k.len() + v.len())
+                        .sum::<usize>()
+            }
+            Self::Branch { keys, .. } => {
+                NODE_HEADER + 8 + keys.iter().map(|k| 10 + k.len()).sum::<usize>()
+            }
+        }
+    }
+
+    /// Splits an overfull node near its byte midpoint.
+    fn split(self) -> (Node, Vec<u8>, Node) {
+        let half = self.encoded_len() / 2;
+        match self {
+            Self::Leaf(mut entries) => {
+                let mut used = NODE_HEADER;
+                let mut at = 0;
+                while at < entries.len() - 1 && used < half {
+                    used += 6 + entries[at].0.len() + entries[at].1.len();
+                    at += 1;
+                }
+                let right = entries.split_off(at.max(1));
+                let separator = right[0].0.clone();
+                (Self::Leaf(entries), separator, Self::Leaf(right))
+            }
+            Self::Branch {
+                mut keys,
+                mut children,
+            } => {
+                let mut used = NODE_HEADER + 8;
+                let mut at = 0;
+                while at < keys.len().saturating_sub(2) && used < half {
+                    used += 10 + keys[at].len();
+                    at += 1;
+                }
+                let at = at.max(1);
+                let right_keys = keys.split_off(at + 1);
+                let separator = keys.pop().unwrap_or_default();
+                let right_children = children.split_off(at + 1);
+                (
+                    Self::Branch { keys, children },
+                    separator,
+                    Self::Branch {
+                        keys: right_keys,
+                        children: right_children,
+                    },
+                )
+            }
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.encoded_len());
+        out.resize(NODE_HEADER, 0);
+        match self {
+            Self::Leaf(entries) => {
+                out[0] = KIND_LEAF;
+                out[2..4].copy_from_slice(&(entries.len() as u16).to_le_bytes());
+                for (key, value) in entries {
+                    out.extend_from_slice(&(key.len() as u16).to_le_bytes());
+                    out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                    out.extend_from_slice(key);
+                    out.extend_from_slice(value);
+                }
+            }
+            Self::Branch { keys, children } => {
+                out[0] = KIND_BRANCH;
+                out[2..4].copy_from_slice(&(keys.len() as u16).to_le_bytes());
+                out.extend_from_slice(&children[0].to_le_bytes());
+                for (key, child) in keys.iter().zip(&children[1..]) {
+                    out.extend_from_slice(&(key.len() as u16).to_le_bytes());
+                    out.extend_from_slice(key);
+                    out.extend_from_slice(&child.to_le_bytes());
+                }
+            }
+        }
+        let body_len = (out.len() - NODE_HEADER) as u32;
+        out[4..8].copy_from_slice(&body_len.to_le_bytes());
+        let sum = checksum(&out[NODE_HEADER..]);
+        out[8..16].copy_from_slice(&sum.to_le_bytes());
+        out
+    }
+
+    fn decode(data: &[u8]) -> Option<Self> {
+        let header = data.get(..NODE_HEADER)?;
+        let count = u16::from_le_bytes([header[2], header[3]]) as usize;
+        let body_len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let body = data.get(NODE_HEADER..NODE_HEADER + body_len)?;
+        if checksum(body) != read_u64(&header[8..]) {
+            return None;
+        }
+        let mut cursor = Cursor { data: body, pos: 0 };
+        match header[0] {
+            KIND_LEAF => {
+                let mut entries = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let key_len = cursor.u16()? as usize;
+                    let value_len = cursor.u32()? as usize;
+                    let key = cursor.bytes(key_len)?.to_vec();
+                    let value = cursor.bytes(value_len)?.to_vec();
+                    entries.push((key, value));
+                }
+                Some(Self::Leaf(entries))
+            }
+            KIND_BRANCH => {
+                let mut keys = Vec::with_capacity(count);
+                let mut children = Vec::with_capacity(count + 1);
+                children.push(cursor.u64()?);
+                for _ in 0..count {
+                    let key_len = cursor.u16()? as usize;
+                    keys.push(cursor.bytes(key_len)?.to_vec());
+                    children.push(cursor.u64()?);
+                }
+                Some(Self::Branch { keys, children })
+            }
+            _ => None,
+        }
+    }
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let out = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(out)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.bytes(8).map(read_u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use alloc::vec;
+    use storage::block::MemoryBlockDevice;
+
+    fn config() -> PagerConfig {
+        PagerConfig {
+            page_size: 1024,
+            ..PagerConfig::new(512, 512)
+        }
+    }
+
+    fn key(n: u32) -> Vec<u8> {
+        format!("key-{:05}", n).into_bytes()
+    }
+
+    #[test]
+    fn splits_and_scans_in_order() {
+        let mut disk = vec![0u8; 512 * 1024];
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let mut tree = BTree::format(device, config()).unwrap();
+        // Insert in a scrambled order to exercise splits at every position.
+        for i in 0..1000u32 {
+            let n = (i * 7919) % 1000;
+            tree.insert(&key(n), &n.to_le_bytes()).unwrap();
+        }
+        tree.commit().unwrap();
+
+        let mut seen = Vec::new();
+        tree.scan(&key(250), &mut |k, _| {
+            seen.push(k.to_vec());
+            seen.len() < 10
+        })
+        .unwrap();
+        assert_eq!(seen.first(), Some(&key(250)));
+        assert_eq!(seen.last(), Some(&key(259)));
+        assert_eq!(
+            tree.get(&key(999)).unwrap(),
+            Some(999u32.to_le_bytes().to_vec())
+        );
+    }
+
+    #[test]
+    fn removals_merge_and_survive_reopen() {
+        let mut disk = vec![0u8; 512 * 1024];
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let mut tree = BTree::format(device, config()).unwrap();
+        for n in 0..600u32 {
+            tree.insert(&key(n), b"value").unwrap();
+        }
+        tree.commit().unwrap();
+        let full = tree.pager().free_pages();
+        for n in (0..600u32).filter(|n| n % 10 != 0) {
+            assert!(tree.remove(&key(n)).unwrap());
+        }
+        assert!(!tree.remove(&key(1)).unwrap());
+        tree.commit().unwrap();
+        assert!(tree.pager().free_pages() > full);
+
+        let reopened = BTree::open(tree.into_device(), config()).unwrap();
+        let mut count = 0;
+        reopened
+            .scan(b"", &mut |k, _| {
+                assert_eq!(k, key(count * 10).as_slice());
+                count += 1;
+                true
+            })
+            .unwrap();
+        assert_eq!(count, 60);
+    }
+}
@@ -3,50 +3,397 @@
 use alloc::borrow::ToOwned;
 use alloc::collections::BTreeMap;
 use alloc::string::String;
+use alloc::vec::Vec;
 
+use storage::block::BlockDevice;
 use storage::object::ObjectMetadata;
 
+use crate::btree::{BTree, TreeError};
+use crate::pager::PagerConfig;
+use crate::record::{RecordReader, RecordWriter};
+use crate::validation::{
+    validate_bucket_name, validate_key, BucketNameError, ControlCharPolicy, KeyError,
+};
+
 /// Minimal bucket descriptor.
 pub struct BucketInfo<'a> {
     pub name: &'a str,
     pub object_count: u64,
+    pub bytes_used: u64,
+    pub quota: BucketQuota,
+    pub metadata: &'a BucketMetadata,
+}
+
+/// Owned snapshot of everything the catalog knows about one bucket.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BucketDescription {
+    pub name: String,
+    pub usage: BucketUsage,
+    pub quota: BucketQuota,
+    pub metadata: BucketMetadata,
+}
+
+/// Descriptive bucket attributes supplied at creation time.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BucketMetadata {
+    /// Creation timestamp in the caller's clock units.
+    pub created_at: u64,
+    /// API key ID of the principal that created the bucket.
+    pub owner: String,
+    /// Free-form placement label, e.g. `"us-east-1"`.
+    pub region: String,
+    pub tags: Vec<(String, String)>,
+}
+
+impl BucketMetadata {
+    /// S3 allows at most 50 tags per bucket.
+    pub const MAX_TAGS: usize = 50;
+    pub const MAX_TAG_KEY_LEN: usize = 128;
+    pub const MAX_TAG_VALUE_LEN: usize = 256;
+    const MAX_LABEL_LEN: usize = 128;
+
+    pub fn validate(&self) -> Result<(), CatalogError> {
+        if self.tags.len() > Self::MAX_TAGS
+            || self.owner.len() > Self::MAX_LABEL_LEN
+            || self.region.len() > Self::MAX_LABEL_LEN
+        {
+            return Err(CatalogError::InvalidMetadata);
+        }
+        for (idx, (key, value)) in self.tags.iter().enumerate() {
+            if key.is_empty()
+                || key.len() > Self::MAX_TAG_KEY_LEN
+                || value.len() > Self::MAX_TAG_VALUE_LEN
+                || self.tags[..idx].iter().any(|(other, _)| other == key)
+            {
+                return Err(CatalogError::InvalidMetadata);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Space consumed by a bucket's objects.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BucketUsage {
+    pub bytes: u64,
+    pub objects: u64,
+}
+
+impl BucketUsage {
+    /// Usage after storing an object of `size` bytes over one that
+    /// previously occupied `previous` bytes, if any.
+    pub fn with_object(self, previous: Option<u64>, size: u64) -> Self {
+        match previous {
+            Some(old) => Self {
+                bytes: self.bytes.saturating_sub(old).saturating_add(size),
+                objects: self.objects,
+            },
+            None => Self {
+                bytes: self.bytes.saturating_add(size),
+                objects: self.objects + 1,
+            },
+        }
+    }
+
+    /// Usage after dropping an object of `size` bytes.
+    pub fn without_object(self, size: u64) -> Self {
+        Self {
+            bytes: self.bytes.saturating_sub(size),
+            objects: self.objects.saturating_sub(1),
+        }
+    }
+}
+
+/// Byte and object-count ceilings; `None` means unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quota {
+    pub max_bytes: Option<u64>,
+    pub max_objects: Option<u64>,
+}
+
+impl Quota {
+    pub fn exceeded_by(&self, usage: BucketUsage) -> bool {
+        self.max_bytes.is_some_and(|max| usage.bytes > max)
+            || self.max_objects.is_some_and(|max| usage.objects > max)
+    }
+}
+
+/// Per-bucket limits. Writes that would cross `hard` fail with
+/// `CatalogError::QuotaExceeded`; crossing `soft` is allowed and only
+/// reported so callers can warn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BucketQuota {
+    pub soft: Quota,
+    pub hard: Quota,
+}
+
+/// S3 storage classes. The catalog records the class an object was written
+/// with; placement does not yet differ between classes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StorageClass {
+    #[default]
+    Standard,
+    ReducedRedundancy,
+    StandardIa,
+    OnezoneIa,
+    IntelligentTiering,
+    Glacier,
+    DeepArchive,
+}
+
+impl StorageClass {
+    const ALL: [StorageClass; 7] = [
+        StorageClass::Standard,
+        StorageClass::ReducedRedundancy,
+        StorageClass::StandardIa,
+        StorageClass::OnezoneIa,
+        StorageClass::IntelligentTiering,
+        StorageClass::Glacier,
+        StorageClass::DeepArchive,
+    ];
+
+    /// Name used in the `x-amz-storage-class` header.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StorageClass::Standard => "STANDARD",
+            StorageClass::ReducedRedundancy => "REDUCED_REDUNDANCY",
+            StorageClass::StandardIa => "STANDARD_IA",
+            StorageClass::OnezoneIa => "ONEZONE_IA",
+            StorageClass::IntelligentTiering => "INTELLIGENT_TIERING",
+            StorageClass::Glacier => "GLACIER",
+            StorageClass::DeepArchive => "DEEP_ARCHIVE",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|class| class.as_str() == name)
+    }
+
+    fn code(self) -> u8 {
+        self as u8
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.get(code as usize).copied()
+    }
+}
+
+/// Descriptive object attributes recorded next to `ObjectMetadata`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ObjectAttributes {
+    pub content_type: String,
+    /// Timestamp of the last write in the caller's clock units.
+    pub last_modified: u64,
+    pub etag: String,
+    pub storage_class: StorageClass,
+    /// `x-amz-meta-*` pairs with the prefix stripped.
+    pub user_metadata: Vec<(String, String)>,
+    pub tags: Vec<(String, String)>,
+}
+
+impl ObjectAttributes {
+    /// S3 caps user-defined metadata at 2 KiB of names plus values.
+    pub const MAX_USER_METADATA_LEN: usize = 2048;
+    /// S3 allows at most 10 tags per object.
+    pub const MAX_TAGS: usize = 10;
+    const MAX_LABEL_LEN: usize = 256;
+
+    /// Checks an object tag set against S3's count, length and uniqueness
+    /// rules.
+    pub fn validate_tags(tags: &[(String, String)]) -> Result<(), CatalogError> {
+        if tags.len() > Self::MAX_TAGS {
+            return Err(CatalogError::InvalidTag);
+        }
+        for (idx, (key, value)) in tags.iter().enumerate() {
+            if key.is_empty()
+                || key.len() > BucketMetadata::MAX_TAG_KEY_LEN
+                || value.len() > BucketMetadata::MAX_TAG_VALUE_LEN
+                || tags[..idx].iter().any(|(other, _)| other == key)
+            {
+                return Err(CatalogError::InvalidTag);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), CatalogError> {
+        if self.content_type.len() > Self::MAX_LABEL_LEN || self.etag.len() > Self::MAX_LABEL_LEN {
+            return Err(CatalogError::InvalidMetadata);
+        }
+        let mut total = 0;
+        for (idx, (name, value)) in self.user_metadata.iter().enumerate() {
+            total += name.len() + value.len();
+            if name.is_empty()
+                || self.user_metadata[..idx]
+                    .iter()
+                    .any(|(other, _)| other == name)
+            {
+                return Err(CatalogError::InvalidMetadata);
+            }
+        }
+        if total > Self::MAX_USER_METADATA_LEN {
+            return Err(CatalogError::InvalidMetadata);
+        }
+        Self::validate_tags(&self.tags)
+    }
+}
+
+/// Everything the catalog records about one object.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ObjectDescription {
+    pub meta: ObjectMetadata,
+    pub attributes: ObjectAttributes,
 }
 
 pub trait Catalog {
-    fn create_bucket(&mut self, name: &str) -> Result<(), CatalogError>;
+    fn create_bucket(&mut self, name: &str) -> Result<(), CatalogError> {
+        self.create_bucket_with(name, BucketMetadata::default())
+    }
+    fn create_bucket_with(
+        &mut self,
+        name: &str,
+        metadata: BucketMetadata,
+    ) -> Result<(), CatalogError>;
     fn delete_bucket(&mut self, name: &str) -> Result<(), CatalogError>;
+    /// Atomically moves a bucket, its objects and its attributes to `to`.
+    fn rename_bucket(&mut self, from: &str, to: &str) -> Result<(), CatalogError>;
     fn list_buckets(&self, sink: &mut dyn FnMut(BucketInfo<'_>));
+    fn describe_bucket(&self, name: &str) -> Result<BucketDescription, CatalogError>;
+    fn set_bucket_metadata(
+        &mut self,
+        name: &str,
+        metadata: BucketMetadata,
+    ) -> Result<(), CatalogError>;
+    /// Visits a bucket's objects in key order.
+    fn list_objects(
+        &self,
+        bucket: &str,
+        sink: &mut dyn FnMut(&str, &ObjectDescription),
+    ) -> Result<(), CatalogError>;
     fn put_object(
         &mut self,
         bucket: &str,
         key: &str,
         meta: ObjectMetadata,
+    ) -> Result<(), CatalogError> {
+        self.put_object_with(bucket, key, meta, ObjectAttributes::default())
+    }
+    /// Stores or replaces an object's entry, attributes included.
+    fn put_object_with(
+        &mut self,
+        bucket: &str,
+        key: &str,
+        meta: ObjectMetadata,
+        attributes: ObjectAttributes,
     ) -> Result<(), CatalogError>;
     fn remove_object(&mut self, bucket: &str, key: &str) -> Result<(), CatalogError>;
-    fn object_metadata(&self, bucket: &str, key: &str) -> Result<ObjectMetadata, CatalogError>;
+    fn object_metadata(&self, bucket: &str, key: &str) -> Result<ObjectDescription, CatalogError>;
+    fn usage(&self, bucket: &str) -> Result<BucketUsage, CatalogError>;
+    fn quota(&self, bucket: &str) -> Result<BucketQuota, CatalogError>;
+    fn set_quota(&mut self, bucket: &str, quota: BucketQuota) -> Result<(), CatalogError>;
+
+    /// Replaces an existing object's tag set.
+    fn put_object_tagging(
+        &mut self,
+        bucket: &str,
+        key: &str,
+        tags: Vec<(String, String)>,
+    ) -> Result<(), CatalogError> {
+        ObjectAttributes::validate_tags(&tags)?;
+        let mut object = self.object_metadata(bucket, key)?;
+        object.attributes.tags = tags;
+        self.put_object_with(bucket, key, object.meta, object.attributes)
+    }
+
+    fn object_tagging(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Vec<(String, String)>, CatalogError> {
+        Ok(self.object_metadata(bucket, key)?.attributes.tags)
+    }
+
+    fn delete_object_tagging(&mut self, bucket: &str, key: &str) -> Result<(), CatalogError> {
+        self.put_object_tagging(bucket, key, Vec::new())
+    }
+
+    /// How control characters in object keys are treated.
+    fn key_policy(&self) -> ControlCharPolicy {
+        ControlCharPolicy::default()
+    }
+
+    /// Applies the S3 key rules under this catalog's `key_policy`.
+    fn check_key(&self, key: &str) -> Result<(), CatalogError> {
+        validate_key(key, self.key_policy()).map_err(CatalogError::InvalidKey)
+    }
+
+    /// Checks whether storing `size` bytes under `key` would stay within the
+    /// bucket's hard quota, so callers can refuse a write before touching
+    /// object data.
+    fn check_quota(&self, bucket: &str, key: &str, size: u64) -> Result<(), CatalogError> {
+        self.check_key(key)?;
+        let usage = self.usage(bucket)?;
+        let previous = match self.object_metadata(bucket, key) {
+            Ok(object) => Some(object.meta.size),
+            Err(CatalogError::NotFound) => None,
+            Err(err) => return Err(err),
+        };
+        if self
+            .quota(bucket)?
+            .hard
+            .exceeded_by(usage.with_object(previous, size))
+        {
+            return Err(CatalogError::QuotaExceeded);
+        }
+        Ok(())
+    }
+
+    /// Checks everything `put_object_with` would refuse for this write,
+    /// so callers can reject it before touching object data.
+    fn check_put(
+        &self,
+        bucket: &str,
+        key: &str,
+        size: u64,
+        attributes: &ObjectAttributes,
+    ) -> Result<(), CatalogError> {
+        attributes.validate()?;
+        self.check_quota(bucket, key, size)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CatalogError {
     NotFound,
     AlreadyExists,
-    InvalidName,
+    InvalidBucketName(BucketNameError),
+    InvalidKey(KeyError),
+    InvalidMetadata,
+    /// More than ten tags, an empty or oversized tag, or a repeated key.
+    InvalidTag,
+    QuotaExceeded,
     Backend,
 }
 
 #[derive(Default)]
 pub struct InMemoryCatalog {
     buckets: BTreeMap<String, Bucket>,
+    key_policy: ControlCharPolicy,
 }
 
+#[derive(Default)]
 struct Bucket {
-    objects: BTreeMap<String, ObjectMetadata>,
+    objects: BTreeMap<String, ObjectDescription>,
+    bytes: u64,
+    quota: BucketQuota,
+    metadata: BucketMetadata,
 }
 
-impl Default for Bucket {
-    fn default() -> Self {
-        Self {
-            objects: BTreeMap::new(),
+impl Bucket {
+    fn usage(&self) -> BucketUsage {
+        BucketUsage {
+            bytes: self.bytes,
+            objects: self.objects.len() as u64,
         }
     }
 }
@@ -56,6 +403,11 @@ impl InMemoryCatalog {
         Self::default()
     }
 
+    pub fn with_key_policy(mut self, policy: ControlCharPolicy) -> Self {
+        self.key_policy = policy;
+        self
+    }
+
     fn bucket_mut(&mut self, name: &str) -> Result<&mut Bucket, CatalogError> {
         self.buckets.get_mut(name).ok_or(CatalogError::NotFound)
     }
@@ -66,14 +418,27 @@ impl InMemoryCatalog {
 }
 
 impl Catalog for InMemoryCatalog {
-    fn create_bucket(&mut self, name: &str) -> Result<(), CatalogError> {
-        if name.is_empty() {
-            return Err(CatalogError::InvalidName);
-        }
+    fn key_policy(&self) -> ControlCharPolicy {
+        self.key_policy
+    }
+
+    fn create_bucket_with(
+        &mut self,
+        name: &str,
+        metadata: BucketMetadata,
+    ) -> Result<(), CatalogError> {
+        validate_bucket_name(name).map_err(CatalogError::InvalidBucketName)?;
+        metadata.validate()?;
         if self.buckets.contains_key(name) {
             return Err(CatalogError::AlreadyExists);
         }
-        self.buckets.insert(name.to_owned(), Bucket::default());
+        self.buckets.insert(
+            name.to_owned(),
+            Bucket {
+                metadata,
+                ..Bucket::default()
+            },
+        );
         Ok(())
     }
 
@@ -84,51 +449,747 @@ impl Catalog for InMemoryCatalog {
             .ok_or(CatalogError::NotFound)
     }
 
+    fn rename_bucket(&mut self, from: &str, to: &str) -> Result<(), CatalogError> {
+        validate_bucket_name(to).map_err(CatalogError::InvalidBucketName)?;
+        if self.buckets.contains_key(to) {
+            return Err(CatalogError::AlreadyExists);
+        }
+        let bucket = self.buckets.remove(from).ok_or(CatalogError::NotFound)?;
+        self.buckets.insert(to.to_owned(), bucket);
+        Ok(())
+    }
+
     fn list_buckets(&self, sink: &mut dyn FnMut(BucketInfo<'_>)) {
         for (name, bucket) in &self.buckets {
             sink(BucketInfo {
                 name,
                 object_count: bucket.objects.len() as u64,
+                bytes_used: bucket.bytes,
+                quota: bucket.quota,
+                metadata: &bucket.metadata,
             });
         }
     }
 
-    fn put_object(
+    fn describe_bucket(&self, name: &str) -> Result<BucketDescription, CatalogError> {
+        let bucket = self.bucket(name)?;
+        Ok(BucketDescription {
+            name: name.to_owned(),
+            usage: bucket.usage(),
+            quota: bucket.quota,
+            metadata: bucket.metadata.clone(),
+        })
+    }
+
+    fn set_bucket_metadata(
+        &mut self,
+        name: &str,
+        metadata: BucketMetadata,
+    ) -> Result<(), CatalogError> {
+        metadata.validate()?;
+        self.bucket_mut(name)?.metadata = metadata;
+        Ok(())
+    }
+
+    fn list_objects(
+        &self,
+        bucket: &str,
+        sink: &mut dyn FnMut(&str, &ObjectDescription),
+    ) -> Result<(), CatalogError> {
+        for (key, object) in &self.bucket(bucket)?.objects {
+            sink(key, object);
+        }
+        Ok(())
+    }
+
+    fn put_object_with(
         &mut self,
         bucket: &str,
         key: &str,
         meta: ObjectMetadata,
+        attributes: ObjectAttributes,
     ) -> Result<(), CatalogError> {
-        if key.is_empty() {
-            return Err(CatalogError::InvalidName);
-        }
+        self.check_key(key)?;
+        attributes.validate()?;
         let bucket = self.bucket_mut(bucket)?;
-        bucket.objects.insert(key.to_owned(), meta);
+        let previous = bucket.objects.get(key).map(|old| old.meta.size);
+        let usage = bucket.usage().with_object(previous, meta.size);
+        if bucket.quota.hard.exceeded_by(usage) {
+            return Err(CatalogError::QuotaExceeded);
+        }
+        bucket
+            .objects
+            .insert(key.to_owned(), ObjectDescription { meta, attributes });
+        bucket.bytes = usage.bytes;
         Ok(())
     }
 
     fn remove_object(&mut self, bucket: &str, key: &str) -> Result<(), CatalogError> {
         let bucket = self.bucket_mut(bucket)?;
-        bucket
-            .objects
-            .remove(key)
-            .map(|_| ())
-            .ok_or(CatalogError::NotFound)
+        let removed = bucket.objects.remove(key).ok_or(CatalogError::NotFound)?;
+        bucket.bytes = bucket.bytes.saturating_sub(removed.meta.size);
+        Ok(())
     }
 
-    fn object_metadata(&self, bucket: &str, key: &str) -> Result<ObjectMetadata, CatalogError> {
+    fn object_metadata(&self, bucket: &str, key: &str) -> Result<ObjectDescription, CatalogError> {
         let bucket = self.bucket(bucket)?;
         bucket
             .objects
             .get(key)
-            .copied()
+            .cloned()
             .ok_or(CatalogError::NotFound)
     }
+
+    fn usage(&self, bucket: &str) -> Result<BucketUsage, CatalogError> {
+        Ok(self.bucket(bucket)?.usage())
+    }
+
+    fn quota(&self, bucket: &str) -> Result<BucketQuota, CatalogError> {
+        Ok(self.bucket(bucket)?.quota)
+    }
+
+    fn set_quota(&mut self, bucket: &str, quota: BucketQuota) -> Result<(), CatalogError> {
+        self.bucket_mut(bucket)?.quota = quota;
+        Ok(())
+    }
+}
+
+/// Catalog persisted in a copy-on-write B+tree on a block device.
+///
+/// Buckets and objects share one ordered keyspace: bucket records live
+/// under `b'B' + name` and objects under `b'O' + bucket + 0 + key`, so a
+/// bucket's objects are contiguous and listable with a single range scan.
+/// An object whose attributes do not fit in one tree entry keeps only its
+/// metadata there, and its full record in overflow chunks under
+/// `b'V' + bucket + 0 + key + chunk index`. A bucket record that does not
+/// fit leaves an empty row and goes to chunks under
+/// `b'W' + name + 0 + chunk index`.
+/// Every mutating call is one transaction that either commits a new root or
+/// rolls back entirely.
+pub struct BTreeCatalog<D: BlockDevice> {
+    tree: BTree<D>,
+    key_policy: ControlCharPolicy,
+}
+
+const BUCKET_TAG: u8 = b'B';
+const OBJECT_TAG: u8 = b'O';
+const OVERFLOW_TAG: u8 = b'V';
+const BUCKET_OVERFLOW_TAG: u8 = b'W';
+/// Encoded `ObjectMetadata`: id, size and checksum.
+const OBJECT_META_LEN: usize = 16 + 8 + 8;
+
+/// Raw key/value pair read out of the tree.
+type Row = (Vec<u8>, Vec<u8>);
+
+impl<D: BlockDevice> BTreeCatalog<D> {
+    /// Creates an empty catalog, discarding anything previously on `device`.
+    pub fn format(device: D, config: PagerConfig) -> Result<Self, CatalogError> {
+        let tree = BTree::format(device, config).map_err(backend)?;
+        Ok(Self {
+            tree,
+            key_policy: ControlCharPolicy::default(),
+        })
+    }
+
+    pub fn open(device: D, config: PagerConfig) -> Result<Self, CatalogError> {
+        let tree = BTree::open(device, config).map_err(backend)?;
+        Ok(Self {
+            tree,
+            key_policy: ControlCharPolicy::default(),
+        })
+    }
+
+    pub fn with_key_policy(mut self, policy: ControlCharPolicy) -> Self {
+        self.key_policy = policy;
+        self
+    }
+
+    pub fn into_device(self) -> D {
+        self.tree.into_device()
+    }
+
+    fn bucket_key(name: &str) -> Vec<u8> {
+        let mut key = Vec::with_capacity(name.len() + 1);
+        key.push(BUCKET_TAG);
+        key.extend_from_slice(name.as_bytes());
+        key
+    }
+
+    fn object_key(bucket: &str, key: &str) -> Vec<u8> {
+        let mut out = Self::object_prefix(bucket);
+        out.extend_from_slice(key.as_bytes());
+        out
+    }
+
+    fn object_prefix(bucket: &str) -> Vec<u8> {
+        let mut out = Vec::with_capacity(bucket.len() + 2);
+        out.push(OBJECT_TAG);
+        out.extend_from_slice(bucket.as_bytes());
+        out.push(0);
+        out
+    }
+
+    fn overflow_prefix(bucket: &str) -> Vec<u8> {
+        let mut out = Self::object_prefix(bucket);
+        out[0] = OVERFLOW_TAG;
+        out
+    }
+
+    /// Key of overflow chunk `index` for the object stored under
+    /// `object_key`.
+    fn overflow_key(object_key: &[u8], index: u16) -> Vec<u8> {
+        let mut out = Vec::with_capacity(object_key.len() + 2);
+        out.push(OVERFLOW_TAG);
+        out.extend_from_slice(&object_key[1..]);
+        out.extend_from_slice(&index.to_be_bytes());
+        out
+    }
+
+    /// Key of overflow chunk `index` for the record of bucket `name`.
+    fn bucket_overflow_key(name: &str, index: u16) -> Vec<u8> {
+        let mut out = Vec::with_capacity(name.len() + 4);
+        out.push(BUCKET_OVERFLOW_TAG);
+        out.extend_from_slice(name.as_bytes());
+        out.push(0);
+        out.extend_from_slice(&index.to_be_bytes());
+        out
+    }
+
+    /// Stores an encoded object record, spilling it into overflow chunks
+    /// when it does not fit next to its key. Drops any chunks left by the
+    /// previous record.
+    fn write_object(&mut self, object_key: &[u8], encoded: &[u8]) -> Result<(), CatalogError> {
+        self.remove_overflow(object_key)?;
+        let max = self.tree.max_entry_len();
+        if object_key.len() + encoded.len() <= max {
+            return self.tree.insert(object_key, encoded).map_err(backend);
+        }
+        if !self.fits(object_key) {
+            return Err(CatalogError::InvalidMetadata);
+        }
+        self.write_chunks(&|index| Self::overflow_key(object_key, index), encoded)?;
+        self.tree
+            .insert(object_key, &encoded[..OBJECT_META_LEN])
+            .map_err(backend)
+    }
+
+    /// Splits `encoded` across the chunk keys `chunk_key` yields, each
+    /// filled up to the entry limit.
+    fn write_chunks(
+        &mut self,
+        chunk_key: &dyn Fn(u16) -> Vec<u8>,
+        encoded: &[u8],
+    ) -> Result<(), CatalogError> {
+        let chunk_len = self.tree.max_entry_len().saturating_sub(chunk_key(0).len());
+        if chunk_len == 0 || encoded.len().div_ceil(chunk_len) > u16::MAX as usize {
+            return Err(CatalogError::InvalidMetadata);
+        }
+        for (index, chunk) in (0..=u16::MAX).zip(encoded.chunks(chunk_len)) {
+            self.tree
+                .insert(&chunk_key(index), chunk)
+                .map_err(backend)?;
+        }
+        Ok(())
+    }
+
+    /// Concatenates the chunks under `chunk_key`, stopping at the first
+    /// missing index.
+    fn read_chunks(&self, chunk_key: &dyn Fn(u16) -> Vec<u8>) -> Result<Vec<u8>, CatalogError> {
+        let mut record = Vec::new();
+        for index in 0..=u16::MAX {
+            match self.tree.get(&chunk_key(index)).map_err(backend)? {
+                Some(chunk) => record.extend_from_slice(&chunk),
+                None => break,
+            }
+        }
+        Ok(record)
+    }
+
+    fn remove_chunks(&mut self, chunk_key: &dyn Fn(u16) -> Vec<u8>) -> Result<(), CatalogError> {
+        for index in 0..=u16::MAX {
+            if !self.tree.remove(&chunk_key(index)).map_err(backend)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the page size leaves room for `object_key` with its
+    /// metadata, and for overflow chunks under it.
+    fn fits(&self, object_key: &[u8]) -> bool {
+        let max = self.tree.max_entry_len();
+        object_key.len() + OBJECT_META_LEN <= max && object_key.len() + 2 < max
+    }
+
+    /// Decodes the row stored under `object_key`, reassembling its
+    /// overflow chunks if it has any.
+    fn read_object(
+        &self,
+        object_key: &[u8],
+        row: &[u8],
+    ) -> Result<ObjectDescription, CatalogError> {
+        if row.len() != OBJECT_META_LEN {
+            return decode_object(row);
+        }
+        let record = self.read_chunks(&|index| Self::overflow_key(object_key, index))?;
+        if record.is_empty() {
+            return decode_object(row);
+        }
+        let object = decode_object(&record)?;
+        if object.meta != decode_object(row)?.meta {
+            return Err(CatalogError::Backend);
+        }
+        Ok(object)
+    }
+
+    fn remove_overflow(&mut self, object_key: &[u8]) -> Result<(), CatalogError> {
+        self.remove_chunks(&|index| Self::overflow_key(object_key, index))
+    }
+
+    fn remove_rows(&mut self, prefix: &[u8]) -> Result<(), CatalogError> {
+        loop {
+            let batch = self.object_batch(prefix, 256)?;
+            if batch.is_empty() {
+                return Ok(());
+            }
+            for (key, _) in batch {
+                self.tree.remove(&key).map_err(backend)?;
+            }
+        }
+    }
+
+    /// Moves every row under `from` to the same suffix under `to`.
+    fn move_rows(&mut self, from: &[u8], to: &[u8]) -> Result<(), CatalogError> {
+        loop {
+            let batch = self.object_batch(from, 256)?;
+            if batch.is_empty() {
+                return Ok(());
+            }
+            for (key, value) in batch {
+                let mut moved = to.to_vec();
+                moved.extend_from_slice(&key[from.len()..]);
+                self.tree.insert(&moved, &value).map_err(backend)?;
+                self.tree.remove(&key).map_err(backend)?;
+            }
+        }
+    }
+
+    fn bucket_record(&self, bucket: &str) -> Result<BucketRecord, CatalogError> {
+        let row = self
+            .tree
+            .get(&Self::bucket_key(bucket))
+            .map_err(backend)?
+            .ok_or(CatalogError::NotFound)?;
+        self.read_bucket(bucket, &row)
+    }
+
+    /// Decodes the row of bucket `name`; an empty row means the record is
+    /// in overflow chunks.
+    fn read_bucket(&self, name: &str, row: &[u8]) -> Result<BucketRecord, CatalogError> {
+        if !row.is_empty() {
+            return BucketRecord::decode(row);
+        }
+        BucketRecord::decode(&self.read_chunks(&|index| Self::bucket_overflow_key(name, index))?)
+    }
+
+    /// Stores the record of `bucket`, spilling it into overflow chunks
+    /// when it does not fit in one entry.
+    fn set_bucket_record(
+        &mut self,
+        bucket: &str,
+        record: &BucketRecord,
+    ) -> Result<(), CatalogError> {
+        self.remove_bucket_overflow(bucket)?;
+        let key = Self::bucket_key(bucket);
+        let encoded = record.encode();
+        if key.len() + encoded.len() <= self.tree.max_entry_len() {
+            return self.tree.insert(&key, &encoded).map_err(backend);
+        }
+        self.write_chunks(&|index| Self::bucket_overflow_key(bucket, index), &encoded)?;
+        self.tree.insert(&key, &[]).map_err(backend)
+    }
+
+    fn remove_bucket_overflow(&mut self, bucket: &str) -> Result<(), CatalogError> {
+        self.remove_chunks(&|index| Self::bucket_overflow_key(bucket, index))
+    }
+
+    /// Collects up to `limit` rows under `prefix`.
+    fn object_batch(&self, prefix: &[u8], limit: usize) -> Result<Vec<Row>, CatalogError> {
+        let mut batch = Vec::new();
+        self.tree
+            .scan(prefix, &mut |key, value| {
+                if !key.starts_with(prefix) {
+                    return false;
+                }
+                batch.push((key.to_vec(), value.to_vec()));
+                batch.len() < limit
+            })
+            .map_err(backend)?;
+        Ok(batch)
+    }
+
+    /// Runs `op` as a single transaction, committing on success and
+    /// discarding every page it wrote on failure.
+    fn transaction<T>(
+        &mut self,
+        op: impl FnOnce(&mut Self) -> Result<T, CatalogError>,
+    ) -> Result<T, CatalogError> {
+        match op(self).and_then(|value| {
+            self.tree.commit().map_err(backend)?;
+            Ok(value)
+        }) {
+            Ok(value) => Ok(value),
+            Err(err) => {
+                self.tree.rollback();
+                Err(err)
+            }
+        }
+    }
+}
+
+fn backend(err: TreeError) -> CatalogError {
+    match err {
+        // Only an object key too long for the page size can get here;
+        // its attributes would have gone to overflow chunks.
+        TreeError::EntryTooLarge => CatalogError::InvalidMetadata,
+        TreeError::Pager(_) => CatalogError::Backend,
+    }
+}
+
+/// Bucket row stored under `b'B' + name`.
+#[derive(Default)]
+struct BucketRecord {
+    usage: BucketUsage,
+    quota: BucketQuota,
+    metadata: BucketMetadata,
+}
+
+impl BucketRecord {
+    fn encode(&self) -> Vec<u8> {
+        RecordWriter::new()
+            .u64(self.usage.objects)
+            .u64(self.usage.bytes)
+            .opt_u64(self.quota.soft.max_bytes)
+            .opt_u64(self.quota.soft.max_objects)
+            .opt_u64(self.quota.hard.max_bytes)
+            .opt_u64(self.quota.hard.max_objects)
+            .u64(self.metadata.created_at)
+            .str(&self.metadata.owner)
+            .str(&self.metadata.region)
+            .pairs(&self.metadata.tags)
+            .finish()
+    }
+
+    fn decode(record: &[u8]) -> Result<Self, CatalogError> {
+        let mut reader = RecordReader::new(record);
+        let mut decode = || {
+            Some(Self {
+                usage: BucketUsage {
+                    objects: reader.u64()?,
+                    bytes: reader.u64()?,
+                },
+                quota: BucketQuota {
+                    soft: Quota {
+                        max_bytes: reader.opt_u64()?,
+                        max_objects: reader.opt_u64()?,
+                    },
+                    hard: Quota {
+                        max_bytes: reader.opt_u64()?,
+                        max_objects: reader.opt_u64()?,
+                    },
+                },
+                metadata: BucketMetadata {
+                    created_at: reader.u64()?,
+                    owner: reader.str()?,
+                    region: reader.str()?,
+                    tags: reader.pairs()?,
+                },
+            })
+        };
+        decode().ok_or(CatalogError::Backend)
+    }
+}
+
+/// Object row stored under `b'O' + bucket + 0 + key`. The fixed
+/// `ObjectMetadata` fields come first and attributes follow; decoding stops
+/// at the end of the row, so attributes absent from older rows read as
+/// defaults and fields appended later are ignored by older readers.
+fn encode_object(object: &ObjectDescription) -> Vec<u8> {
+    let attributes = &object.attributes;
+    RecordWriter::new()
+        .u128(object.meta.id)
+        .u64(object.meta.size)
+        .u64(object.meta.checksum)
+        .str(&attributes.content_type)
+        .u64(attributes.last_modified)
+        .str(&attributes.etag)
+        .u8(attributes.storage_class.code())
+        .pairs(&attributes.user_metadata)
+        .pairs(&attributes.tags)
+        .finish()
+}
+
+fn decode_object(record: &[u8]) -> Result<ObjectDescription, CatalogError> {
+    let mut reader = RecordReader::new(record);
+    let mut decode = || {
+        let meta = ObjectMetadata {
+            id: reader.u128()?,
+            size: reader.u64()?,
+            checksum: reader.u64()?,
+        };
+        if reader.is_empty() {
+            return Some(ObjectDescription {
+                meta,
+                attributes: ObjectAttributes::default(),
+            });
+        }
+        let attributes = ObjectAttributes {
+            content_type: reader.str()?,
+            last_modified: reader.u64()?,
+            etag: reader.str()?,
+            storage_class: StorageClass::from_code(reader.u8()?)?,
+            user_metadata: reader.pairs()?,
+            tags: if reader.is_empty() {
+                Vec::new()
+            } else {
+                reader.pairs()?
+            },
+        };
+        Some(ObjectDescription { meta, attributes })
+    };
+    decode().ok_or(CatalogError::Backend)
+}
+
+impl<D: BlockDevice> Catalog for BTreeCatalog<D> {
+    fn key_policy(&self) -> ControlCharPolicy {
+        self.key_policy
+    }
+
+    fn create_bucket_with(
+        &mut self,
+        name: &str,
+        metadata: BucketMetadata,
+    ) -> Result<(), CatalogError> {
+        validate_bucket_name(name).map_err(CatalogError::InvalidBucketName)?;
+        metadata.validate()?;
+        self.transaction(|catalog| {
+            let key = Self::bucket_key(name);
+            if catalog.tree.get(&key).map_err(backend)?.is_some() {
+                return Err(CatalogError::AlreadyExists);
+            }
+            let record = BucketRecord {
+                metadata,
+                ..BucketRecord::default()
+            };
+            catalog.set_bucket_record(name, &record)
+        })
+    }
+
+    fn delete_bucket(&mut self, name: &str) -> Result<(), CatalogError> {
+        self.transaction(|catalog| {
+            if !catalog
+                .tree
+                .remove(&Self::bucket_key(name))
+                .map_err(backend)?
+            {
+                return Err(CatalogError::NotFound);
+            }
+            catalog.remove_bucket_overflow(name)?;
+            catalog.remove_rows(&Self::object_prefix(name))?;
+            catalog.remove_rows(&Self::overflow_prefix(name))
+        })
+    }
+
+    fn rename_bucket(&mut self, from: &str, to: &str) -> Result<(), CatalogError> {
+        validate_bucket_name(to).map_err(CatalogError::InvalidBucketName)?;
+        self.transaction(|catalog| {
+            let record = catalog.bucket_record(from)?;
+            if catalog
+                .tree
+                .get(&Self::bucket_key(to))
+                .map_err(backend)?
+                .is_some()
+            {
+                return Err(CatalogError::AlreadyExists);
+            }
+            catalog
+                .tree
+                .remove(&Self::bucket_key(from))
+                .map_err(backend)?;
+            catalog.remove_bucket_overflow(from)?;
+            catalog.set_bucket_record(to, &record)?;
+            catalog.move_rows(&Self::object_prefix(from), &Self::object_prefix(to))?;
+            catalog.move_rows(&Self::overflow_prefix(from), &Self::overflow_prefix(to))
+        })
+    }
+
+    fn list_buckets(&self, sink: &mut dyn FnMut(BucketInfo<'_>)) {
+        let _ = self.tree.scan(&[BUCKET_TAG], &mut |key, value| {
+            if key.first() != Some(&BUCKET_TAG) {
+                return false;
+            }
+            let Ok(name) = core::str::from_utf8(&key[1..]) else {
+                return true;
+            };
+            let Ok(record) = self.read_bucket(name, value) else {
+                return true;
+            };
+            sink(BucketInfo {
+                name,
+                object_count: record.usage.objects,
+                bytes_used: record.usage.bytes,
+                quota: record.quota,
+                metadata: &record.metadata,
+            });
+            true
+        });
+    }
+
+    fn describe_bucket(&self, name: &str) -> Result<BucketDescription, CatalogError> {
+        let record = self.bucket_record(name)?;
+        Ok(BucketDescription {
+            name: name.to_owned(),
+            usage: record.usage,
+            quota: record.quota,
+            metadata: record.metadata,
+        })
+    }
+
+    fn set_bucket_metadata(
+        &mut self,
+        name: &str,
+        metadata: BucketMetadata,
+    ) -> Result<(), CatalogError> {
+        metadata.validate()?;
+        self.transaction(|catalog| {
+            let mut record = catalog.bucket_record(name)?;
+            record.metadata = metadata;
+            catalog.set_bucket_record(name, &record)
+        })
+    }
+
+    fn list_objects(
+        &self,
+        bucket: &str,
+        sink: &mut dyn FnMut(&str, &ObjectDescription),
+    ) -> Result<(), CatalogError> {
+        self.bucket_record(bucket)?;
+        let prefix = Self::object_prefix(bucket);
+        let mut result = Ok(());
+        self.tree
+            .scan(&prefix, &mut |key, value| {
+                if !key.starts_with(&prefix) {
+                    return false;
+                }
+                match (
+                    core::str::from_utf8(&key[prefix.len()..]),
+                    self.read_object(key, value),
+                ) {
+                    (Ok(name), Ok(object)) => {
+                        sink(name, &object);
+                        true
+                    }
+                    _ => {
+                        result = Err(CatalogError::Backend);
+                        false
+                    }
+                }
+            })
+            .map_err(backend)?;
+        result
+    }
+
+    fn check_put(
+        &self,
+        bucket: &str,
+        key: &str,
+        size: u64,
+        attributes: &ObjectAttributes,
+    ) -> Result<(), CatalogError> {
+        attributes.validate()?;
+        self.check_quota(bucket, key, size)?;
+        if !self.fits(&Self::object_key(bucket, key)) {
+            return Err(CatalogError::InvalidMetadata);
+        }
+        Ok(())
+    }
+
+    fn put_object_with(
+        &mut self,
+        bucket: &str,
+        key: &str,
+        meta: ObjectMetadata,
+        attributes: ObjectAttributes,
+    ) -> Result<(), CatalogError> {
+        self.check_key(key)?;
+        attributes.validate()?;
+        let object_key = Self::object_key(bucket, key);
+        let encoded = encode_object(&ObjectDescription { meta, attributes });
+        self.transaction(|catalog| {
+            let mut record = catalog.bucket_record(bucket)?;
+            let previous = match catalog.tree.get(&object_key).map_err(backend)? {
+                Some(old) => Some(decode_object(&old)?.meta.size),
+                None => None,
+            };
+            record.usage = record.usage.with_object(previous, meta.size);
+            if record.quota.hard.exceeded_by(record.usage) {
+                return Err(CatalogError::QuotaExceeded);
+            }
+            catalog.write_object(&object_key, &encoded)?;
+            catalog.set_bucket_record(bucket, &record)
+        })
+    }
+
+    fn remove_object(&mut self, bucket: &str, key: &str) -> Result<(), CatalogError> {
+        self.transaction(|catalog| {
+            let mut record = catalog.bucket_record(bucket)?;
+            let object_key = Self::object_key(bucket, key);
+            let old = catalog
+                .tree
+                .get(&object_key)
+                .map_err(backend)?
+                .ok_or(CatalogError::NotFound)?;
+            catalog.tree.remove(&object_key).map_err(backend)?;
+            catalog.remove_overflow(&object_key)?;
+            record.usage = record.usage.without_object(decode_object(&old)?.meta.size);
+            catalog.set_bucket_record(bucket, &record)
+        })
+    }
+
+    fn object_metadata(&self, bucket: &str, key: &str) -> Result<ObjectDescription, CatalogError> {
+        self.bucket_record(bucket)?;
+        let object_key = Self::object_key(bucket, key);
+        let record = self
+            .tree
+            .get(&object_key)
+            .map_err(backend)?
+            .ok_or(CatalogError::NotFound)?;
+        self.read_object(&object_key, &record)
+    }
+
+    fn usage(&self, bucket: &str) -> Result<BucketUsage, CatalogError> {
+        Ok(self.bucket_record(bucket)?.usage)
+    }
+
+    fn quota(&self, bucket: &str) -> Result<BucketQuota, CatalogError> {
+        Ok(self.bucket_record(bucket)?.quota)
+    }
+
+    fn set_quota(&mut self, bucket: &str, quota: BucketQuota) -> Result<(), CatalogError> {
+        self.transaction(|catalog| {
+            let mut record = catalog.bucket_record(bucket)?;
+            record.quota = quota;
+            catalog.set_bucket_record(bucket, &record)
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use storage::block::MemoryBlockDevice;
 
     #[test]
     fn create_and_list_bucket() {
@@ -142,16 +1203,403 @@ mod tests {
 
     #[test]
     fn put_and_fetch_object() {
-        let mut catalog = InMemoryCatalog::new();
-        catalog.create_bucket("docs").unwrap();
+        let mut disk = vec![0u8; 64 * 8192];
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let mut btree = BTreeCatalog::format(device, PagerConfig::new(512, 64)).unwrap();
+        let mut memory = InMemoryCatalog::new();
+        let catalogs: [&mut dyn Catalog; 2] = [&mut memory, &mut btree];
         let meta = ObjectMetadata {
             id: 7,
             size: 128,
             checksum: 0,
         };
-        catalog.put_object("docs", "file.txt", meta).unwrap();
+        let attributes = ObjectAttributes {
+            content_type: "text/plain".to_string(),
+            last_modified: 99,
+            etag: "\"0123abcd\"".to_string(),
+            storage_class: StorageClass::StandardIa,
+            user_metadata: vec![("owner".to_string(), "ops".to_string())],
+            tags: vec![("tier".to_string(), "cold".to_string())],
+        };
+        for catalog in catalogs {
+            catalog.create_bucket("docs").unwrap();
+            catalog.put_object("docs", "plain.txt", meta).unwrap();
+            catalog
+                .put_object_with("docs", "file.txt", meta, attributes.clone())
+                .unwrap();
+
+            let stored = catalog.object_metadata("docs", "file.txt").unwrap();
+            assert_eq!(stored.meta.size, 128);
+            assert_eq!(stored.attributes, attributes);
+            let plain = catalog.object_metadata("docs", "plain.txt").unwrap();
+            assert_eq!(plain.attributes, ObjectAttributes::default());
+
+            let oversized = ObjectAttributes {
+                user_metadata: vec![("big".to_string(), "x".repeat(2048))],
+                ..ObjectAttributes::default()
+            };
+            assert_eq!(
+                catalog.put_object_with("docs", "big.txt", meta, oversized),
+                Err(CatalogError::InvalidMetadata)
+            );
+        }
+    }
+
+    #[test]
+    fn object_tagging_round_trips() {
+        let mut disk = vec![0u8; 64 * 8192];
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let mut btree = BTreeCatalog::format(device, PagerConfig::new(512, 64)).unwrap();
+        let mut memory = InMemoryCatalog::new();
+        let catalogs: [&mut dyn Catalog; 2] = [&mut memory, &mut btree];
+        let meta = ObjectMetadata {
+            id: 1,
+            size: 5,
+            checksum: 0,
+        };
+        let tags = vec![
+            ("tier".to_string(), "hot".to_string()),
+            ("team".to_string(), "media".to_string()),
+        ];
+        for catalog in catalogs {
+            catalog.create_bucket("docs").unwrap();
+            catalog.put_object("docs", "a.txt", meta).unwrap();
+            catalog
+                .put_object_tagging("docs", "a.txt", tags.clone())
+                .unwrap();
+            assert_eq!(catalog.object_tagging("docs", "a.txt").unwrap(), tags);
+            assert_eq!(catalog.usage("docs").unwrap().bytes, 5);
+
+            let too_many = (0..11)
+                .map(|n| (alloc::format!("k{}", n), "v".to_string()))
+                .collect();
+            assert_eq!(
+                catalog.put_object_tagging("docs", "a.txt", too_many),
+                Err(CatalogError::InvalidTag)
+            );
+            assert_eq!(
+                catalog.put_object_tagging("docs", "missing.txt", tags.clone()),
+                Err(CatalogError::NotFound)
+            );
+
+            // Ten tags at the longest allowed key and value.
+            let full: Vec<_> = (0..ObjectAttributes::MAX_TAGS)
+                .map(|n| {
+                    let key = alloc::format!("{}{}", n, "k".repeat(127));
+                    (key, "v".repeat(BucketMetadata::MAX_TAG_VALUE_LEN))
+                })
+                .collect();
+            assert_eq!(full[0].0.len(), BucketMetadata::MAX_TAG_KEY_LEN);
+            catalog
+                .put_object_tagging("docs", "a.txt", full.clone())
+                .unwrap();
+            assert_eq!(catalog.object_tagging("docs", "a.txt").unwrap(), full);
+
+            catalog.delete_object_tagging("docs", "a.txt").unwrap();
+            assert!(catalog.object_tagging("docs", "a.txt").unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn hard_quota_rejects_and_usage_tracks() {
+        let mut catalog = InMemoryCatalog::new();
+        catalog.create_bucket("docs").unwrap();
+        catalog
+            .set_quota(
+                "docs",
+                BucketQuota {
+                    soft: Quota {
+                        max_bytes: Some(100),
+                        max_objects: None,
+                    },
+                    hard: Quota {
+                        max_bytes: Some(150),
+                        max_objects: Some(2),
+                    },
+                },
+            )
+            .unwrap();
+        let meta = |size| ObjectMetadata {
+            id: 1,
+            size,
+            checksum: 0,
+        };
+        catalog.put_object("docs", "a", meta(120)).unwrap();
+        let quota = catalog.quota("docs").unwrap();
+        assert!(quota.soft.exceeded_by(catalog.usage("docs").unwrap()));
+        assert_eq!(
+            catalog.check_quota("docs", "b", 40),
+            Err(CatalogError::QuotaExceeded)
+        );
+        assert_eq!(
+            catalog.put_object("docs", "b", meta(40)),
+            Err(CatalogError::QuotaExceeded)
+        );
+        // Replacing an object only counts the size difference.
+        catalog.put_object("docs", "a", meta(150)).unwrap();
+        catalog.remove_object("docs", "a").unwrap();
+        assert_eq!(catalog.usage("docs").unwrap(), BucketUsage::default());
+    }
+
+    #[test]
+    fn btree_catalog_survives_reopen() {
+        let mut disk = vec![0u8; 256 * 8192];
+        let config = PagerConfig::new(512, 256);
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let mut catalog = BTreeCatalog::format(device, config).unwrap();
+        catalog.create_bucket("logs").unwrap();
+        catalog.create_bucket("photos").unwrap();
+        for n in 0..500u64 {
+            let meta = ObjectMetadata {
+                id: n as u128,
+                size: n * 3,
+                checksum: 0,
+            };
+            catalog
+                .put_object("photos", &alloc::format!("img/{:04}.jpg", n), meta)
+                .unwrap();
+        }
+        catalog.remove_object("photos", "img/0007.jpg").unwrap();
+        catalog
+            .set_quota(
+                "logs",
+                BucketQuota {
+                    hard: Quota {
+                        max_bytes: None,
+                        max_objects: Some(0),
+                    },
+                    ..BucketQuota::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            catalog.create_bucket("logs"),
+            Err(CatalogError::AlreadyExists)
+        );
+
+        let catalog = BTreeCatalog::open(catalog.into_device(), config).unwrap();
+        let mut buckets = Vec::new();
+        catalog.list_buckets(&mut |info| buckets.push((info.name.to_string(), info.object_count)));
+        assert_eq!(
+            buckets,
+            vec![("logs".to_string(), 0), ("photos".to_string(), 499)]
+        );
+        assert_eq!(
+            catalog
+                .object_metadata("photos", "img/0420.jpg")
+                .unwrap()
+                .meta
+                .size,
+            1260
+        );
+        assert_eq!(
+            catalog.object_metadata("photos", "img/0007.jpg"),
+            Err(CatalogError::NotFound)
+        );
+        assert_eq!(
+            catalog.usage("photos").unwrap().bytes,
+            (0..500).sum::<u64>() * 3 - 21
+        );
+        assert_eq!(catalog.quota("logs").unwrap().hard.max_objects, Some(0));
+    }
+
+    #[test]
+    fn rename_moves_objects_and_metadata() {
+        let mut disk = vec![0u8; 64 * 8192];
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let mut btree = BTreeCatalog::format(device, PagerConfig::new(512, 64)).unwrap();
+        let mut memory = InMemoryCatalog::new();
+        let catalogs: [&mut dyn Catalog; 2] = [&mut memory, &mut btree];
+        let metadata = BucketMetadata {
+            created_at: 42,
+            owner: "key-1".to_string(),
+            region: "local".to_string(),
+            tags: vec![("team".to_string(), "media".to_string())],
+        };
+        for catalog in catalogs {
+            catalog.create_bucket_with("old", metadata.clone()).unwrap();
+            catalog.create_bucket("taken").unwrap();
+            let meta = ObjectMetadata {
+                id: 1,
+                size: 10,
+                checksum: 0,
+            };
+            catalog.put_object("old", "a/b.txt", meta).unwrap();
+            catalog.put_object("old", "c.txt", meta).unwrap();
+
+            assert_eq!(
+                catalog.rename_bucket("old", "taken"),
+                Err(CatalogError::AlreadyExists)
+            );
+            catalog.rename_bucket("old", "new").unwrap();
+            assert_eq!(catalog.describe_bucket("old"), Err(CatalogError::NotFound));
+            let described = catalog.describe_bucket("new").unwrap();
+            assert_eq!(described.metadata, metadata);
+            assert_eq!(described.usage.objects, 2);
+            let mut keys = Vec::new();
+            catalog
+                .list_objects("new", &mut |key, _| keys.push(key.to_string()))
+                .unwrap();
+            assert_eq!(keys, ["a/b.txt", "c.txt"]);
+
+            // A full tag set at the longest allowed keys and values.
+            let full = BucketMetadata {
+                owner: "o".repeat(128),
+                region: "r".repeat(128),
+                tags: (0..BucketMetadata::MAX_TAGS)
+                    .map(|n| {
+                        let key = alloc::format!("{:02}{}", n, "k".repeat(126));
+                        (key, "v".repeat(BucketMetadata::MAX_TAG_VALUE_LEN))
+                    })
+                    .collect(),
+                ..metadata.clone()
+            };
+            assert_eq!(full.tags[0].0.len(), BucketMetadata::MAX_TAG_KEY_LEN);
+            catalog.create_bucket_with("full", full.clone()).unwrap();
+            catalog.put_object("full", "x", meta).unwrap();
+            catalog.rename_bucket("full", "fuller").unwrap();
+            let described = catalog.describe_bucket("fuller").unwrap();
+            assert_eq!(described.metadata, full);
+            assert_eq!(described.usage.objects, 1);
+            let mut listed = Vec::new();
+            catalog.list_buckets(&mut |info| {
+                listed.push((info.name.to_string(), info.metadata.clone()))
+            });
+            assert!(listed.contains(&("fuller".to_string(), full.clone())));
+            catalog.delete_bucket("fuller").unwrap();
+        }
+
+        // Nothing of the overflowing bucket is left behind.
+        let mut stray = 0;
+        btree
+            .tree
+            .scan(&[BUCKET_OVERFLOW_TAG], &mut |key, _| {
+                stray += usize::from(key[0] == BUCKET_OVERFLOW_TAG);
+                true
+            })
+            .unwrap();
+        assert_eq!(stray, 0);
+    }
+
+    #[test]
+    fn catalogs_enforce_naming_rules() {
+        let mut disk = vec![0u8; 64 * 8192];
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let mut btree = BTreeCatalog::format(device, PagerConfig::new(512, 64))
+            .unwrap()
+            .with_key_policy(ControlCharPolicy::Allow);
+        let mut memory = InMemoryCatalog::new().with_key_policy(ControlCharPolicy::Allow);
+        let catalogs: [&mut dyn Catalog; 2] = [&mut memory, &mut btree];
+        let meta = ObjectMetadata {
+            id: 1,
+            size: 1,
+            checksum: 0,
+        };
+        for catalog in catalogs {
+            assert_eq!(
+                catalog.create_bucket("10.0.0.1"),
+                Err(CatalogError::InvalidBucketName(BucketNameError::IpAddress))
+            );
+            catalog.create_bucket("docs").unwrap();
+            assert_eq!(
+                catalog.rename_bucket("docs", "Docs"),
+                Err(CatalogError::InvalidBucketName(BucketNameError::Character))
+            );
+            assert_eq!(
+                catalog.put_object("docs", &"k".repeat(1025), meta),
+                Err(CatalogError::InvalidKey(KeyError::TooLong))
+            );
+            catalog.put_object("docs", "tab\there", meta).unwrap();
+            assert_eq!(catalog.usage("docs").unwrap().objects, 1);
+        }
+        assert_eq!(
+            InMemoryCatalog::new().check_quota("docs", "tab\there", 1),
+            Err(CatalogError::InvalidKey(KeyError::ControlCharacter))
+        );
+    }
+
+    #[test]
+    fn btree_catalog_delete_bucket_drops_objects() {
+        let mut disk = vec![0u8; 64 * 8192];
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let mut catalog = BTreeCatalog::format(device, PagerConfig::new(512, 64)).unwrap();
+        catalog.create_bucket("docs").unwrap();
+        let meta = ObjectMetadata {
+            id: 1,
+            size: 4,
+            checksum: 0,
+        };
+        catalog.put_object("docs", "a.txt", meta).unwrap();
+        catalog.delete_bucket("docs").unwrap();
+        catalog.create_bucket("docs").unwrap();
+        assert_eq!(
+            catalog.object_metadata("docs", "a.txt"),
+            Err(CatalogError::NotFound)
+        );
+        assert_eq!(
+            catalog.put_object("missing", "a.txt", meta),
+            Err(CatalogError::NotFound)
+        );
+    }
+
+    #[test]
+    fn btree_catalog_overflows_largest_valid_attributes() {
+        use crate::validation::{MAX_BUCKET_NAME_LEN, MAX_KEY_LEN};
+
+        let mut disk = vec![0u8; 64 * 8192];
+        let config = PagerConfig::new(512, 64);
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let mut catalog = BTreeCatalog::format(device, config).unwrap();
+        let bucket = "b".repeat(MAX_BUCKET_NAME_LEN);
+        let key = "k".repeat(MAX_KEY_LEN);
+        catalog.create_bucket(&bucket).unwrap();
+        let half = ObjectAttributes::MAX_USER_METADATA_LEN / 2;
+        let attributes = ObjectAttributes {
+            content_type: "t".repeat(256),
+            etag: "e".repeat(256),
+            user_metadata: vec![("m".repeat(half), "v".repeat(half))],
+            ..ObjectAttributes::default()
+        };
+        let meta = ObjectMetadata {
+            id: 9,
+            size: 4,
+            checksum: 0,
+        };
+        catalog
+            .put_object_with(&bucket, &key, meta, attributes.clone())
+            .unwrap();
+        let mut oversized = attributes.clone();
+        oversized.user_metadata[0].1.push('v');
+        assert_eq!(
+            catalog.put_object_with(&bucket, &key, meta, oversized),
+            Err(CatalogError::InvalidMetadata)
+        );
+
+        catalog.rename_bucket(&bucket, "moved").unwrap();
+        let mut catalog = BTreeCatalog::open(catalog.into_device(), config).unwrap();
+        let expected = ObjectDescription { meta, attributes };
+        assert_eq!(catalog.object_metadata("moved", &key), Ok(expected.clone()));
+        let mut listed = Vec::new();
+        catalog
+            .list_objects("moved", &mut |_, object| listed.push(object.clone()))
+            .unwrap();
+        assert_eq!(listed, [expected]);
 
-        let stored = catalog.object_metadata("docs", "file.txt").unwrap();
-        assert_eq!(stored.size, 128);
+        // Shrinking the record drops its overflow chunks.
+        catalog.put_object("moved", &key, meta).unwrap();
+        assert_eq!(
+            catalog.object_metadata("moved", &key).unwrap().attributes,
+            ObjectAttributes::default()
+        );
+        catalog.remove_object("moved", &key).unwrap();
+        let mut rows = 0;
+        catalog
+            .tree
+            .scan(&[], &mut |_, _| {
+                rows += 1;
+                true
+            })
+            .unwrap();
+        assert_eq!(rows, 1, "only the bucket record is left");
     }
 }
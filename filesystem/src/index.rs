@@ -1,24 +1,51 @@
 #![allow(dead_code)]
 
+use alloc::borrow::ToOwned;
 use alloc::collections::BTreeMap;
-use alloc::string::String;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
+use core::ops::Bound;
 
 use storage::object::ObjectMetadata;
 
+use crate::catalog::ObjectAttributes;
+use crate::search::{Search, SearchQuery, SearchResponse, SecondaryIndex};
+
 /// Listing parameters for S3-style object listings.
 pub struct ListRequest<'a> {
     pub bucket: &'a str,
     pub prefix: Option<&'a str>,
     pub delimiter: Option<char>,
+    /// Opaque token from a previous `ListResponse::next_token`. Takes
+    /// precedence over `start_after`.
     pub continuation: Option<&'a str>,
+    /// Only keys coming strictly after this one in listing order are
+    /// returned, i.e. smaller keys when listing descending.
+    pub start_after: Option<&'a str>,
+    pub direction: ListDirection,
+    /// Upper bound on `key_count`; `0` means unlimited.
     pub max_keys: usize,
+    /// Only objects carrying this exact tag key and value are returned, and
+    /// only prefixes containing such an object are rolled up.
+    pub tag: Option<(&'a str, &'a str)>,
+}
+
+/// Order keys and common prefixes are returned in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListDirection {
+    #[default]
+    Ascending,
+    /// Largest key first, e.g. newest first for time-prefixed keys.
+    Descending,
 }
 
 pub struct ListResponse<'a> {
     pub objects: Vec<ListObject<'a>>,
     pub next_token: Option<String>,
     pub common_prefixes: Vec<String>,
+    /// Objects plus common prefixes returned, as reported in S3's `KeyCount`.
+    pub key_count: usize,
+    pub is_truncated: bool,
 }
 
 pub struct ListObject<'a> {
@@ -28,35 +55,173 @@ pub struct ListObject<'a> {
 
 pub trait Index {
     fn list(&self, request: &ListRequest<'_>) -> Result<ListResponse<'_>, IndexError>;
+    /// Visits the name of every bucket holding at least one entry.
+    fn buckets(&self, visit: &mut dyn FnMut(&str));
 }
 
 pub trait MutableIndex: Index {
+    /// Adds or updates an entry. Attributes already recorded for the key
+    /// are kept.
     fn insert(&mut self, bucket: &str, key: &str, meta: ObjectMetadata);
+    /// Adds or replaces an entry together with its attributes.
+    fn insert_with(
+        &mut self,
+        bucket: &str,
+        key: &str,
+        meta: ObjectMetadata,
+        attributes: &ObjectAttributes,
+    );
     fn remove(&mut self, bucket: &str, key: &str);
+    /// Replaces the tags recorded for an existing entry.
+    fn set_tags(&mut self, bucket: &str, key: &str, tags: &[(String, String)]);
     fn purge_bucket(&mut self, bucket: &str);
+    fn rename_bucket(&mut self, from: &str, to: &str);
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IndexError {
     NotFound,
+    InvalidToken,
     Backend,
 }
 
+/// Position a listing resumes from, carried opaquely in continuation tokens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Resume {
+    /// Resume strictly after this key.
+    After(String),
+    /// Resume after every key under this common prefix.
+    Beyond(String),
+}
+
+impl Resume {
+    pub(crate) fn encode(&self) -> String {
+        let (tag, key) = match self {
+            Self::After(key) => (b'k', key),
+            Self::Beyond(key) => (b'p', key),
+        };
+        let mut token = String::with_capacity(2 + key.len() * 2);
+        for byte in core::iter::once(tag).chain(key.bytes()) {
+            token.push(char::from(HEX[(byte >> 4) as usize]));
+            token.push(char::from(HEX[(byte & 0xF) as usize]));
+        }
+        token
+    }
+
+    pub(crate) fn decode(token: &str) -> Result<Self, IndexError> {
+        let digits = token.as_bytes();
+        if digits.len() < 2 || !digits.len().is_multiple_of(2) {
+            return Err(IndexError::InvalidToken);
+        }
+        let mut bytes = Vec::with_capacity(digits.len() / 2);
+        for pair in digits.chunks(2) {
+            let hi = hex_value(pair[0]).ok_or(IndexError::InvalidToken)?;
+            let lo = hex_value(pair[1]).ok_or(IndexError::InvalidToken)?;
+            bytes.push(hi << 4 | lo);
+        }
+        let tag = bytes.remove(0);
+        let key = String::from_utf8(bytes).map_err(|_| IndexError::InvalidToken)?;
+        match tag {
+            b'k' => Ok(Self::After(key)),
+            b'p' => Ok(Self::Beyond(key)),
+            _ => Err(IndexError::InvalidToken),
+        }
+    }
+
+    fn lower_bound(&self) -> Option<Bound<String>> {
+        match self {
+            Self::After(key) => Some(Bound::Excluded(key.clone())),
+            Self::Beyond(prefix) => prefix_successor(prefix).map(Bound::Included),
+        }
+    }
+
+    /// Exclusive upper bound when listing descending. Every key under a
+    /// common prefix sorts at or above the prefix itself, so both variants
+    /// resume strictly below their key.
+    fn upper_bound(&self) -> &str {
+        match self {
+            Self::After(key) | Self::Beyond(key) => key,
+        }
+    }
+}
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+fn hex_value(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        _ => None,
+    }
+}
+
+/// Ordered in-memory index.
+///
+/// Each bucket keeps its keys in a `BTreeMap`, so inserts and removals are
+/// logarithmic and listings seek straight to the first candidate key
+/// instead of scanning the bucket from the start. Secondary indexes over
+/// object attributes are updated alongside for `Search`.
 pub struct InMemoryIndex {
-    buckets: BTreeMap<String, Vec<String>>,
-    metadata: BTreeMap<(String, String), ObjectMetadata>,
+    buckets: BTreeMap<String, BucketEntries>,
+}
+
+#[derive(Default)]
+struct BucketEntries {
+    objects: BTreeMap<String, Entry>,
+    secondary: SecondaryIndex,
+}
+
+impl BucketEntries {
+    /// Replaces `key`'s entry with `update(previous)`, keeping the
+    /// secondary indexes in step.
+    fn upsert(&mut self, key: &str, update: impl FnOnce(Option<Entry>) -> Entry) {
+        let previous = self.objects.remove(key);
+        if let Some(old) = &previous {
+            self.secondary.remove(key, old);
+        }
+        let entry = update(previous);
+        self.secondary.add(key, &entry);
+        self.objects.insert(key.to_owned(), entry);
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(old) = self.objects.remove(key) {
+            self.secondary.remove(key, &old);
+        }
+    }
+}
+
+pub(crate) struct Entry {
+    pub(crate) meta: ObjectMetadata,
+    pub(crate) attributes: ObjectAttributes,
+}
+
+impl Entry {
+    pub(crate) fn has_tag(&self, (key, value): (&str, &str)) -> bool {
+        self.attributes
+            .tags
+            .iter()
+            .any(|(k, v)| k == key && v == value)
+    }
 }
 
 impl InMemoryIndex {
     pub fn new() -> Self {
         Self {
             buckets: BTreeMap::new(),
-            metadata: BTreeMap::new(),
         }
     }
 
-    fn entries_for(&self, bucket: &str) -> Option<&Vec<String>> {
-        self.buckets.get(bucket)
+    fn entries_for(&self, bucket: &str) -> Option<&BTreeMap<String, Entry>> {
+        self.buckets.get(bucket).map(|entries| &entries.objects)
+    }
+
+    fn bucket_mut(&mut self, bucket: &str) -> &mut BucketEntries {
+        if !self.buckets.contains_key(bucket) {
+            self.buckets
+                .insert(bucket.to_owned(), BucketEntries::default());
+        }
+        self.buckets.get_mut(bucket).expect("bucket inserted above")
     }
 }
 
@@ -66,6 +231,23 @@ impl Default for InMemoryIndex {
     }
 }
 
+/// Smallest string greater than every string starting with `prefix`, or
+/// `None` when no such bound exists.
+fn prefix_successor(prefix: &str) -> Option<String> {
+    let mut chars: Vec<char> = prefix.chars().collect();
+    while let Some(last) = chars.pop() {
+        let mut next = last as u32 + 1;
+        if (0xD800..0xE000).contains(&next) {
+            next = 0xE000;
+        }
+        if let Some(c) = char::from_u32(next) {
+            chars.push(c);
+            return Some(chars.into_iter().collect());
+        }
+    }
+    None
+}
+
 impl Index for InMemoryIndex {
     fn list(&self, request: &ListRequest<'_>) -> Result<ListResponse<'_>, IndexError> {
         let entries = self
@@ -78,90 +260,242 @@ impl Index for InMemoryIndex {
             request.max_keys
         };
 
-        let start = request
-            .continuation
-            .and_then(|token| entries.iter().position(|k| k == token))
-            .map(|idx| idx + 1)
-            .unwrap_or(0);
-
-        let mut objects = Vec::new();
-        let mut prefixes = Vec::new();
-        let mut next_token = None;
-
-        for key in entries.iter().skip(start) {
-            if !key.starts_with(prefix) {
-                continue;
-            }
+        // The continuation token wins over start-after, and either only
+        // matters when it falls inside the prefix's key range.
+        let resume = match (request.continuation, request.start_after) {
+            (Some(token), _) => Some(Resume::decode(token)?),
+            (None, Some(after)) => Some(Resume::After(after.to_owned())),
+            (None, None) => None,
+        };
+        let mut page = Page {
+            objects: Vec::new(),
+            prefixes: Vec::new(),
+            last: None,
+            is_truncated: false,
+            max_keys,
+        };
 
-            if let Some(delimiter) = request.delimiter {
-                if let Some(pos) = key[prefix.len()..].find(delimiter) {
-                    let cp = &key[..prefix.len() + pos + 1];
-                    if !prefixes.iter().any(|p| p == cp) {
-                        prefixes.push(cp.to_string());
+        match request.direction {
+            ListDirection::Ascending => {
+                let mut lower = Bound::Included(prefix.to_owned());
+                if let Some(resume) = resume {
+                    match resume.lower_bound() {
+                        Some(bound) if bound_key(&bound) >= prefix => lower = bound,
+                        Some(_) => {}
+                        None => return Ok(ListResponse::empty()),
                     }
-                    continue;
+                }
+                'seek: loop {
+                    let range = entries.range::<String, _>((lower.clone(), Bound::Unbounded));
+                    for (key, entry) in range {
+                        if !key.starts_with(prefix) {
+                            break 'seek;
+                        }
+                        match page.offer(request, prefix, key, entry) {
+                            Step::Next => {}
+                            Step::Stop => break 'seek,
+                            // Skip every remaining key under this common prefix.
+                            Step::Skip(cp) => match prefix_successor(&cp) {
+                                Some(next) => {
+                                    lower = Bound::Included(next);
+                                    continue 'seek;
+                                }
+                                None => break 'seek,
+                            },
+                        }
+                    }
+                    break;
                 }
             }
-
-            let meta = self
-                .metadata
-                .get(&(request.bucket.to_owned(), key.clone()))
-                .copied()
-                .unwrap_or(ObjectMetadata {
-                    id: 0,
-                    size: 0,
-                    checksum: 0,
-                });
-
-            objects.push(ListObject {
-                key,
-                size: meta.size,
-            });
-
-            if objects.len() == max_keys {
-                let idx = entries
-                    .iter()
-                    .position(|k| k == key)
-                    .unwrap_or(entries.len());
-                if idx + 1 < entries.len() {
-                    next_token = Some(entries[idx].clone());
+            ListDirection::Descending => {
+                let mut upper = match prefix_successor(prefix) {
+                    Some(next) => Bound::Excluded(next),
+                    None => Bound::Unbounded,
+                };
+                if let Some(resume) = resume {
+                    let bound = resume.upper_bound();
+                    if bound < prefix {
+                        return Ok(ListResponse::empty());
+                    }
+                    if matches!(upper, Bound::Unbounded) || bound < bound_key(&upper) {
+                        upper = Bound::Excluded(bound.to_owned());
+                    }
+                }
+                let lower = Bound::Included(prefix.to_owned());
+                'seek: loop {
+                    let range = entries.range::<String, _>((lower.clone(), upper.clone()));
+                    for (key, entry) in range.rev() {
+                        match page.offer(request, prefix, key, entry) {
+                            Step::Next => {}
+                            Step::Stop => break 'seek,
+                            // Keys under the common prefix all sort at or
+                            // above it, so resume just below it.
+                            Step::Skip(cp) => {
+                                upper = Bound::Excluded(cp);
+                                continue 'seek;
+                            }
+                        }
+                    }
+                    break;
                 }
-                break;
             }
         }
 
+        let next_token = if page.is_truncated {
+            page.last.map(|resume| resume.encode())
+        } else {
+            None
+        };
         Ok(ListResponse {
-            objects,
+            key_count: page.objects.len() + page.prefixes.len(),
+            objects: page.objects,
             next_token,
-            common_prefixes: prefixes,
+            common_prefixes: page.prefixes,
+            is_truncated: page.is_truncated,
         })
     }
+
+    fn buckets(&self, visit: &mut dyn FnMut(&str)) {
+        for (name, entries) in &self.buckets {
+            if !entries.objects.is_empty() {
+                visit(name);
+            }
+        }
+    }
+}
+
+/// What a listing does after offering a key to its page.
+enum Step {
+    Next,
+    /// The key rolled up into this common prefix; skip the rest of it.
+    Skip(String),
+    /// The page is full.
+    Stop,
+}
+
+/// Listing page under construction, shared by both directions.
+struct Page<'a> {
+    objects: Vec<ListObject<'a>>,
+    prefixes: Vec<String>,
+    last: Option<Resume>,
+    is_truncated: bool,
+    max_keys: usize,
+}
+
+impl<'a> Page<'a> {
+    /// Offers the next key in listing order.
+    fn offer(
+        &mut self,
+        request: &ListRequest<'_>,
+        prefix: &str,
+        key: &'a String,
+        entry: &Entry,
+    ) -> Step {
+        if request.tag.is_some_and(|tag| !entry.has_tag(tag)) {
+            return Step::Next;
+        }
+
+        // Both keys and common prefixes count toward max-keys, so the page
+        // is full once their total reaches the limit and another candidate
+        // exists.
+        if self.objects.len() + self.prefixes.len() == self.max_keys {
+            self.is_truncated = true;
+            return Step::Stop;
+        }
+
+        if let Some(delimiter) = request.delimiter {
+            if let Some(pos) = key[prefix.len()..].find(delimiter) {
+                let cp = key[..prefix.len() + pos + delimiter.len_utf8()].to_string();
+                self.prefixes.push(cp.clone());
+                self.last = Some(Resume::Beyond(cp.clone()));
+                return Step::Skip(cp);
+            }
+        }
+
+        self.objects.push(ListObject {
+            key,
+            size: entry.meta.size,
+        });
+        self.last = Some(Resume::After(key.clone()));
+        Step::Next
+    }
+}
+
+fn bound_key(bound: &Bound<String>) -> &str {
+    match bound {
+        Bound::Included(key) | Bound::Excluded(key) => key,
+        Bound::Unbounded => "",
+    }
+}
+
+impl ListResponse<'_> {
+    fn empty() -> Self {
+        Self {
+            objects: Vec::new(),
+            next_token: None,
+            common_prefixes: Vec::new(),
+            key_count: 0,
+            is_truncated: false,
+        }
+    }
+}
+
+impl Search for InMemoryIndex {
+    fn search(&self, query: &SearchQuery<'_>) -> Result<SearchResponse<'_>, IndexError> {
+        let entries = self.buckets.get(query.bucket).ok_or(IndexError::NotFound)?;
+        entries.secondary.search(&entries.objects, query)
+    }
 }
 
 impl MutableIndex for InMemoryIndex {
     fn insert(&mut self, bucket: &str, key: &str, meta: ObjectMetadata) {
-        let entries = self
-            .buckets
-            .entry(bucket.to_owned())
-            .or_insert_with(Vec::new);
-        if !entries.iter().any(|k| k == key) {
-            entries.push(key.to_owned());
-            entries.sort();
+        self.bucket_mut(bucket).upsert(key, |previous| Entry {
+            meta,
+            attributes: previous.map(|old| old.attributes).unwrap_or_default(),
+        });
+    }
+
+    fn insert_with(
+        &mut self,
+        bucket: &str,
+        key: &str,
+        meta: ObjectMetadata,
+        attributes: &ObjectAttributes,
+    ) {
+        self.bucket_mut(bucket).upsert(key, |_| Entry {
+            meta,
+            attributes: attributes.clone(),
+        });
+    }
+
+    fn set_tags(&mut self, bucket: &str, key: &str, tags: &[(String, String)]) {
+        let Some(entries) = self.buckets.get_mut(bucket) else {
+            return;
+        };
+        if !entries.objects.contains_key(key) {
+            return;
         }
-        self.metadata
-            .insert((bucket.to_owned(), key.to_owned()), meta);
+        entries.upsert(key, |previous| {
+            let mut entry = previous.expect("entry checked above");
+            entry.attributes.tags = tags.to_vec();
+            entry
+        });
     }
 
     fn remove(&mut self, bucket: &str, key: &str) {
         if let Some(entries) = self.buckets.get_mut(bucket) {
-            entries.retain(|k| k != key);
+            entries.remove(key);
         }
-        self.metadata.remove(&(bucket.to_owned(), key.to_owned()));
     }
 
     fn purge_bucket(&mut self, bucket: &str) {
         self.buckets.remove(bucket);
-        self.metadata.retain(|(b, _), _| b != bucket);
+    }
+
+    fn rename_bucket(&mut self, from: &str, to: &str) {
+        if let Some(entries) = self.buckets.remove(from) {
+            self.buckets.insert(to.to_owned(), entries);
+        }
     }
 }
 
@@ -197,11 +531,151 @@ mod tests {
                 prefix: Some("2023/"),
                 delimiter: Some('/'),
                 continuation: None,
+                start_after: None,
+                direction: ListDirection::Ascending,
                 max_keys: 100,
+                tag: None,
             })
             .unwrap();
 
         assert!(response.objects.len() <= 1);
         assert_eq!(response.common_prefixes.len(), 2);
     }
+
+    fn sample_index(keys: &[&str]) -> InMemoryIndex {
+        let mut index = InMemoryIndex::new();
+        for key in keys {
+            index.insert(
+                "bucket",
+                key,
+                ObjectMetadata {
+                    id: 0,
+                    size: key.len() as u64,
+                    checksum: 0,
+                },
+            );
+        }
+        index
+    }
+
+    fn request<'a>(
+        delimiter: Option<char>,
+        continuation: Option<&'a str>,
+        start_after: Option<&'a str>,
+        max_keys: usize,
+    ) -> ListRequest<'a> {
+        ListRequest {
+            bucket: "bucket",
+            prefix: None,
+            delimiter,
+            continuation,
+            start_after,
+            direction: ListDirection::Ascending,
+            max_keys,
+            tag: None,
+        }
+    }
+
+    #[test]
+    fn prefixes_count_toward_max_keys() {
+        let index = sample_index(&["a", "b/1", "b/2", "c/1", "d"]);
+        let first = index.list(&request(Some('/'), None, None, 2)).unwrap();
+        let keys: Vec<&str> = first.objects.iter().map(|o| o.key).collect();
+        assert_eq!(keys, ["a"]);
+        assert_eq!(first.common_prefixes, ["b/"]);
+        assert_eq!(first.key_count, 2);
+        assert!(first.is_truncated);
+
+        let rest = index
+            .list(&request(Some('/'), first.next_token.as_deref(), None, 2))
+            .unwrap();
+        let keys: Vec<&str> = rest.objects.iter().map(|o| o.key).collect();
+        assert_eq!(keys, ["d"]);
+        assert_eq!(rest.common_prefixes, ["c/"]);
+        assert!(!rest.is_truncated);
+        assert!(rest.next_token.is_none());
+    }
+
+    #[test]
+    fn token_survives_deleted_key_and_start_after() {
+        let mut index = sample_index(&["a", "b", "c", "d"]);
+        let first = index.list(&request(None, None, None, 2)).unwrap();
+        let token = first.next_token.unwrap();
+        assert_ne!(token, "b");
+        index.remove("bucket", "b");
+
+        let rest = index.list(&request(None, Some(&token), None, 10)).unwrap();
+        let keys: Vec<&str> = rest.objects.iter().map(|o| o.key).collect();
+        assert_eq!(keys, ["c", "d"]);
+
+        let after = index.list(&request(None, None, Some("bb"), 10)).unwrap();
+        let keys: Vec<&str> = after.objects.iter().map(|o| o.key).collect();
+        assert_eq!(keys, ["c", "d"]);
+
+        assert!(matches!(
+            index.list(&request(None, Some("not-a-token"), None, 10)),
+            Err(IndexError::InvalidToken)
+        ));
+    }
+
+    #[test]
+    fn tag_filter_skips_untagged_keys_and_prefixes() {
+        let mut index = sample_index(&["a", "b/1", "b/2", "c/1", "d", "e"]);
+        let hot = [("tier".to_string(), "hot".to_string())];
+        for key in ["b/2", "d", "e"] {
+            index.set_tags("bucket", key, &hot);
+        }
+        // Re-inserting keeps the tags; clearing them drops the key.
+        index.insert(
+            "bucket",
+            "e",
+            ObjectMetadata {
+                id: 0,
+                size: 9,
+                checksum: 0,
+            },
+        );
+        index.set_tags("bucket", "d", &[]);
+
+        let mut req = request(Some('/'), None, None, 1);
+        req.tag = Some(("tier", "hot"));
+        let first = index.list(&req).unwrap();
+        assert_eq!(first.common_prefixes, ["b/"]);
+        assert!(first.objects.is_empty());
+        assert!(first.is_truncated);
+
+        req.continuation = first.next_token.as_deref();
+        req.max_keys = 10;
+        let rest = index.list(&req).unwrap();
+        let keys: Vec<(&str, u64)> = rest.objects.iter().map(|o| (o.key, o.size)).collect();
+        assert_eq!(keys, [("e", 9)]);
+        assert!(rest.common_prefixes.is_empty());
+    }
+
+    #[test]
+    fn descending_listing_rolls_up_and_resumes() {
+        let index = sample_index(&["2023/a", "2023/b", "2024/c", "2024/d", "2025-x", "2025/e"]);
+        let mut req = request(Some('/'), None, None, 2);
+        req.direction = ListDirection::Descending;
+        let first = index.list(&req).unwrap();
+        let keys: Vec<&str> = first.objects.iter().map(|o| o.key).collect();
+        assert_eq!(keys, ["2025-x"]);
+        assert_eq!(first.common_prefixes, ["2025/"]);
+        assert!(first.is_truncated);
+
+        req.continuation = first.next_token.as_deref();
+        req.max_keys = 10;
+        let rest = index.list(&req).unwrap();
+        assert!(rest.objects.is_empty());
+        assert_eq!(rest.common_prefixes, ["2024/", "2023/"]);
+        assert!(!rest.is_truncated);
+
+        let mut req = request(None, None, Some("2024/d"), 2);
+        req.direction = ListDirection::Descending;
+        req.prefix = Some("2024/");
+        let page = index.list(&req).unwrap();
+        let keys: Vec<&str> = page.objects.iter().map(|o| o.key).collect();
+        assert_eq!(keys, ["2024/c"]);
+        assert!(!page.is_truncated);
+    }
 }
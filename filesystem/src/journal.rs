@@ -1,9 +1,18 @@
 #![allow(dead_code)]
 
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
 use alloc::string::String;
 use alloc::vec::Vec;
 
+use storage::block::BlockDevice;
+
+use crate::btree::BTree;
 use crate::catalog::CatalogError;
+use crate::pager::PagerConfig;
+
+/// Position of an entry in the journal; the first entry is 0.
+pub type Sequence = u64;
 
 /// Represents a log entry describing a catalog mutation.
 pub struct JournalEntry<'a> {
@@ -21,9 +30,21 @@ pub enum Operation {
 }
 
 pub trait Journal {
-    fn append(&mut self, entry: JournalEntry<'_>) -> Result<(), JournalError>;
-    fn replay(&self, callback: &mut dyn FnMut(JournalEntry<'_>)) -> Result<(), JournalError>;
+    /// Appends an entry and returns the sequence number it was assigned.
+    fn append(&mut self, entry: JournalEntry<'_>) -> Result<Sequence, JournalError>;
+    /// Visits every entry numbered `from` or later, in order.
+    fn read_from(
+        &self,
+        from: Sequence,
+        callback: &mut dyn FnMut(Sequence, JournalEntry<'_>),
+    ) -> Result<(), JournalError>;
+    /// Sequence number the next append will be assigned.
+    fn next_sequence(&self) -> Sequence;
     fn len(&self) -> usize;
+
+    fn replay(&self, callback: &mut dyn FnMut(JournalEntry<'_>)) -> Result<(), JournalError> {
+        self.read_from(0, &mut |_, entry| callback(entry))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -31,6 +52,9 @@ pub enum JournalError {
     Storage,
     Catalog(CatalogError),
     InvalidEntry,
+    /// A subscription's cursor is past the journal's next sequence, e.g. a
+    /// saved cursor resumed against a journal that restarted from 0.
+    CursorAhead,
 }
 
 pub struct InMemoryJournal {
@@ -59,7 +83,7 @@ impl Default for InMemoryJournal {
 }
 
 impl Journal for InMemoryJournal {
-    fn append(&mut self, entry: JournalEntry<'_>) -> Result<(), JournalError> {
+    fn append(&mut self, entry: JournalEntry<'_>) -> Result<Sequence, JournalError> {
         if entry.bucket.is_empty() {
             return Err(JournalError::InvalidEntry);
         }
@@ -75,26 +99,185 @@ impl Journal for InMemoryJournal {
             key: entry.key.map(ToOwned::to_owned),
             operation: entry.operation,
         });
-        Ok(())
+        Ok(self.entries.len() as Sequence - 1)
     }
 
-    fn replay(&self, callback: &mut dyn FnMut(JournalEntry<'_>)) -> Result<(), JournalError> {
-        for entry in &self.entries {
-            let key = entry.key.as_deref();
-            callback(JournalEntry {
-                bucket: &entry.bucket,
-                key,
-                operation: entry.operation,
-            });
+    fn read_from(
+        &self,
+        from: Sequence,
+        callback: &mut dyn FnMut(Sequence, JournalEntry<'_>),
+    ) -> Result<(), JournalError> {
+        let start = usize::try_from(from).unwrap_or(usize::MAX);
+        for (offset, entry) in self.entries.iter().enumerate().skip(start) {
+            callback(
+                offset as Sequence,
+                JournalEntry {
+                    bucket: &entry.bucket,
+                    key: entry.key.as_deref(),
+                    operation: entry.operation,
+                },
+            );
         }
         Ok(())
     }
 
+    fn next_sequence(&self) -> Sequence {
+        self.entries.len() as Sequence
+    }
+
     fn len(&self) -> usize {
         self.entries.len()
     }
 }
 
+/// Durable storage for subscription cursors, keyed by subscriber name.
+pub trait CursorStore {
+    fn load(&self, name: &str) -> Result<Option<Sequence>, JournalError>;
+    fn save(&mut self, name: &str, cursor: Sequence) -> Result<(), JournalError>;
+}
+
+#[derive(Default)]
+pub struct InMemoryCursorStore {
+    cursors: BTreeMap<String, Sequence>,
+}
+
+impl InMemoryCursorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CursorStore for InMemoryCursorStore {
+    fn load(&self, name: &str) -> Result<Option<Sequence>, JournalError> {
+        Ok(self.cursors.get(name).copied())
+    }
+
+    fn save(&mut self, name: &str, cursor: Sequence) -> Result<(), JournalError> {
+        self.cursors.insert(name.to_owned(), cursor);
+        Ok(())
+    }
+}
+
+/// Cursor store kept in a B+tree on a block device; each save commits.
+pub struct BTreeCursorStore<D: BlockDevice> {
+    tree: BTree<D>,
+}
+
+impl<D: BlockDevice> BTreeCursorStore<D> {
+    pub fn format(device: D, config: PagerConfig) -> Result<Self, JournalError> {
+        let tree = BTree::format(device, config).map_err(|_| JournalError::Storage)?;
+        Ok(Self { tree })
+    }
+
+    pub fn open(device: D, config: PagerConfig) -> Result<Self, JournalError> {
+        let tree = BTree::open(device, config).map_err(|_| JournalError::Storage)?;
+        Ok(Self { tree })
+    }
+
+    pub fn into_device(self) -> D {
+        self.tree.into_device()
+    }
+}
+
+impl<D: BlockDevice> CursorStore for BTreeCursorStore<D> {
+    fn load(&self, name: &str) -> Result<Option<Sequence>, JournalError> {
+        let value = self
+            .tree
+            .get(name.as_bytes())
+            .map_err(|_| JournalError::Storage)?;
+        match value {
+            Some(bytes) => bytes
+                .try_into()
+                .map(|raw| Some(Sequence::from_le_bytes(raw)))
+                .map_err(|_| JournalError::Storage),
+            None => Ok(None),
+        }
+    }
+
+    fn save(&mut self, name: &str, cursor: Sequence) -> Result<(), JournalError> {
+        let result = self
+            .tree
+            .insert(name.as_bytes(), &cursor.to_le_bytes())
+            .and_then(|()| self.tree.commit());
+        if result.is_err() {
+            self.tree.rollback();
+            return Err(JournalError::Storage);
+        }
+        Ok(())
+    }
+}
+
+/// A named consumer tailing a journal.
+///
+/// `poll` hands over every entry appended since the previous poll and
+/// advances the in-memory cursor; `commit` makes that position durable.
+/// After a restart the subscription resumes from the last committed
+/// cursor, so entries polled but not committed are delivered again.
+pub struct Subscription {
+    name: String,
+    cursor: Sequence,
+}
+
+impl Subscription {
+    /// Resumes `name` from its saved cursor, or from the start of the
+    /// journal if it has never committed.
+    pub fn open(name: &str, store: &dyn CursorStore) -> Result<Self, JournalError> {
+        Ok(Self {
+            name: name.to_owned(),
+            cursor: store.load(name)?.unwrap_or(0),
+        })
+    }
+
+    /// Starts `name` at `cursor`, e.g. `journal.next_sequence()` to receive
+    /// only entries appended from now on.
+    pub fn starting_at(name: &str, cursor: Sequence) -> Self {
+        Self {
+            name: name.to_owned(),
+            cursor,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sequence number of the next entry this subscription will receive.
+    pub fn cursor(&self) -> Sequence {
+        self.cursor
+    }
+
+    /// Entries appended since the last poll.
+    pub fn pending<J: Journal + ?Sized>(&self, journal: &J) -> u64 {
+        journal.next_sequence().saturating_sub(self.cursor)
+    }
+
+    /// Delivers every new entry in order and returns how many were
+    /// delivered. Fails with `CursorAhead`, delivering nothing, if the
+    /// journal has fewer entries than the cursor has already seen.
+    pub fn poll<J: Journal + ?Sized>(
+        &mut self,
+        journal: &J,
+        deliver: &mut dyn FnMut(Sequence, JournalEntry<'_>),
+    ) -> Result<usize, JournalError> {
+        if self.cursor > journal.next_sequence() {
+            return Err(JournalError::CursorAhead);
+        }
+        let mut delivered = 0;
+        let mut cursor = self.cursor;
+        journal.read_from(self.cursor, &mut |sequence, entry| {
+            deliver(sequence, entry);
+            cursor = sequence + 1;
+            delivered += 1;
+        })?;
+        self.cursor = cursor;
+        Ok(delivered)
+    }
+
+    pub fn commit(&self, store: &mut dyn CursorStore) -> Result<(), JournalError> {
+        store.save(&self.name, self.cursor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,6 +308,71 @@ mod tests {
         assert_eq!(ops[0], Operation::PutObject);
     }
 
+    #[test]
+    fn subscriptions_tail_and_resume_from_committed_cursor() {
+        use alloc::vec;
+        use storage::block::MemoryBlockDevice;
+
+        let put = |journal: &mut InMemoryJournal, key| {
+            journal
+                .append(JournalEntry {
+                    bucket: "docs",
+                    key: Some(key),
+                    operation: Operation::PutObject,
+                })
+                .unwrap()
+        };
+        let mut journal = InMemoryJournal::new();
+        assert_eq!(put(&mut journal, "a"), 0);
+        let mut disk = vec![0u8; 16 * 8192];
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let config = PagerConfig::new(512, 16);
+        let mut store = BTreeCursorStore::format(device, config).unwrap();
+
+        let mut feed = Subscription::open("indexer", &store).unwrap();
+        let mut late = Subscription::starting_at("cache", journal.next_sequence());
+        let mut seen = Vec::new();
+        feed.poll(&journal, &mut |seq, entry| {
+            seen.push((seq, entry.key.unwrap().to_owned()))
+        })
+        .unwrap();
+        feed.commit(&mut store).unwrap();
+        put(&mut journal, "b");
+        put(&mut journal, "c");
+        assert_eq!(feed.pending(&journal), 2);
+        feed.poll(&journal, &mut |seq, entry| {
+            seen.push((seq, entry.key.unwrap().to_owned()))
+        })
+        .unwrap();
+        assert_eq!(
+            seen,
+            [
+                (0, "a".to_owned()),
+                (1, "b".to_owned()),
+                (2, "c".to_owned())
+            ]
+        );
+        assert_eq!(late.poll(&journal, &mut |_, _| {}).unwrap(), 2);
+
+        // Only the first poll was committed, so a restart redelivers b and c.
+        let mut store = BTreeCursorStore::open(store.into_device(), config).unwrap();
+        let mut resumed = Subscription::open("indexer", &store).unwrap();
+        assert_eq!(resumed.cursor(), 1);
+        assert_eq!(resumed.poll(&journal, &mut |_, _| {}).unwrap(), 2);
+        assert_eq!(resumed.poll(&journal, &mut |_, _| {}).unwrap(), 0);
+        resumed.commit(&mut store).unwrap();
+
+        // An in-memory journal restarts from 0, behind the saved cursor.
+        let mut restarted = InMemoryJournal::new();
+        put(&mut restarted, "d");
+        let mut stale = Subscription::open("indexer", &store).unwrap();
+        assert_eq!(
+            stale.poll(&restarted, &mut |_, _| panic!("delivered past a restart")),
+            Err(JournalError::CursorAhead)
+        );
+        assert_eq!(stale.cursor(), 3);
+    }
+
     #[test]
     fn reject_invalid_entries() {
         let mut journal = InMemoryJournal::new();
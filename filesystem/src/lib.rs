@@ -2,6 +2,12 @@
 
 extern crate alloc;
 
+pub mod btree;
 pub mod catalog;
 pub mod index;
 pub mod journal;
+pub mod lifecycle;
+pub mod pager;
+pub mod record;
+pub mod search;
+pub mod validation;
@@ -0,0 +1,474 @@
+#![allow(dead_code)]
+
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use runtime::timers::{TimerId, TimerQueue, TimerService};
+use storage::object::{ObjectError, ObjectStore};
+
+use crate::catalog::{Catalog, CatalogError, ObjectDescription};
+
+/// One bucket lifecycle rule. A rule applies to objects whose key starts
+/// with `prefix` and, when `tag` is set, that carry that exact tag. Ages are
+/// in timer ticks; callers working in days convert before building rules.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LifecycleRule {
+    pub id: String,
+    pub enabled: bool,
+    pub prefix: String,
+    pub tag: Option<(String, String)>,
+    /// Expire current objects this many ticks after their last write.
+    pub expire_after: Option<u64>,
+    /// Abort multipart uploads still incomplete this many ticks after they
+    /// were initiated.
+    pub abort_incomplete_after: Option<u64>,
+    /// Remove noncurrent versions this many ticks after they were superseded.
+    pub noncurrent_expire_after: Option<u64>,
+}
+
+impl LifecycleRule {
+    fn matches_key(&self, key: &str) -> bool {
+        key.starts_with(self.prefix.as_str())
+    }
+
+    fn matches(&self, key: &str, object: &ObjectDescription) -> bool {
+        self.matches_key(key)
+            && self.tag.as_ref().is_none_or(|(name, value)| {
+                object
+                    .attributes
+                    .tags
+                    .iter()
+                    .any(|(k, v)| k == name && v == value)
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecycleError {
+    /// Empty or repeated rule ID, or more than `Lifecycle::MAX_RULES` rules.
+    InvalidRule,
+    /// A rule with no action configured.
+    NoAction,
+    /// Abort-incomplete-upload actions cannot be filtered by tag, matching S3.
+    TagFilterOnAbort,
+}
+
+/// Something a lifecycle pass removed, in the order it was applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LifecycleAction {
+    Expired {
+        bucket: String,
+        key: String,
+        rule: String,
+    },
+    AbortedUpload {
+        bucket: String,
+        key: String,
+        upload_id: String,
+        rule: String,
+    },
+    ExpiredVersion {
+        bucket: String,
+        key: String,
+        version_id: String,
+        rule: String,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct LifecycleReport {
+    pub actions: Vec<LifecycleAction>,
+    /// Removals that matched a rule but failed to apply.
+    pub failures: usize,
+}
+
+/// State governed by lifecycle rules that lives outside the catalog. Every
+/// method defaults to "nothing to do", so implementors only provide what
+/// they track.
+pub trait LifecycleHooks {
+    /// Visits `(upload id, key, initiated at)` for each incomplete upload.
+    fn incomplete_uploads(&self, _bucket: &str, _visit: &mut dyn FnMut(&str, &str, u64)) {}
+    fn abort_upload(&mut self, _bucket: &str, _key: &str, _upload_id: &str) -> bool {
+        false
+    }
+    /// Visits `(key, version id, noncurrent since)` for each noncurrent
+    /// object version.
+    fn noncurrent_versions(&self, _bucket: &str, _visit: &mut dyn FnMut(&str, &str, u64)) {}
+    fn remove_version(&mut self, _bucket: &str, _key: &str, _version_id: &str) -> bool {
+        false
+    }
+}
+
+/// Hooks for deployments with neither multipart uploads nor versioning.
+impl LifecycleHooks for () {}
+
+/// Per-bucket lifecycle configuration plus the periodic timer that drives
+/// evaluation.
+pub struct Lifecycle<T: TimerService = TimerQueue> {
+    rules: BTreeMap<String, Vec<LifecycleRule>>,
+    timers: T,
+    interval: u64,
+    timer: Option<TimerId>,
+}
+
+impl Lifecycle<TimerQueue> {
+    pub fn new(interval: u64) -> Self {
+        Self::with_timers(TimerQueue::new(), interval)
+    }
+}
+
+impl Default for Lifecycle<TimerQueue> {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_INTERVAL)
+    }
+}
+
+impl<T: TimerService> Lifecycle<T> {
+    /// S3 caps a bucket's lifecycle configuration at 1000 rules.
+    pub const MAX_RULES: usize = 1000;
+    pub const DEFAULT_INTERVAL: u64 = 3600;
+
+    pub fn with_timers(timers: T, interval: u64) -> Self {
+        Self {
+            rules: BTreeMap::new(),
+            timers,
+            interval: interval.max(1),
+            timer: None,
+        }
+    }
+
+    /// Replaces a bucket's rules; an empty list removes its configuration.
+    pub fn set_rules(
+        &mut self,
+        bucket: &str,
+        rules: Vec<LifecycleRule>,
+    ) -> Result<(), LifecycleError> {
+        if rules.len() > Self::MAX_RULES {
+            return Err(LifecycleError::InvalidRule);
+        }
+        for (idx, rule) in rules.iter().enumerate() {
+            if rule.id.is_empty() || rules[..idx].iter().any(|other| other.id == rule.id) {
+                return Err(LifecycleError::InvalidRule);
+            }
+            if rule.expire_after.is_none()
+                && rule.abort_incomplete_after.is_none()
+                && rule.noncurrent_expire_after.is_none()
+            {
+                return Err(LifecycleError::NoAction);
+            }
+            if rule.tag.is_some() && rule.abort_incomplete_after.is_some() {
+                return Err(LifecycleError::TagFilterOnAbort);
+            }
+        }
+        if rules.is_empty() {
+            self.rules.remove(bucket);
+        } else {
+            self.rules.insert(bucket.to_owned(), rules);
+        }
+        Ok(())
+    }
+
+    pub fn rules(&self, bucket: &str) -> &[LifecycleRule] {
+        self.rules.get(bucket).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn remove_bucket(&mut self, bucket: &str) {
+        self.rules.remove(bucket);
+    }
+
+    pub fn rename_bucket(&mut self, from: &str, to: &str) {
+        if let Some(rules) = self.rules.remove(from) {
+            self.rules.insert(to.to_owned(), rules);
+        }
+    }
+
+    /// Polls the evaluation timer, arming it on first use. Returns true once
+    /// per elapsed interval, re-arming the timer each time it fires.
+    pub fn due(&mut self, now: u64) -> bool {
+        let Some(armed) = self.timer else {
+            self.timer = Some(self.timers.schedule(now.saturating_add(self.interval)));
+            return false;
+        };
+        let mut fired = false;
+        self.timers.poll(now, &mut |id| fired |= id == armed);
+        if fired {
+            self.timer = Some(self.timers.schedule(now.saturating_add(self.interval)));
+        }
+        fired
+    }
+
+    /// Applies every enabled rule as of `now`. Expired objects are removed
+    /// from the catalog and their data, stored under `storage_key(bucket,
+    /// key)`, from `store`. Uploads and versions go through `hooks`.
+    pub fn evaluate<C, O>(
+        &self,
+        now: u64,
+        catalog: &mut C,
+        store: &mut O,
+        storage_key: fn(&str, &str) -> String,
+        hooks: &mut dyn LifecycleHooks,
+    ) -> LifecycleReport
+    where
+        C: Catalog,
+        O: ObjectStore,
+    {
+        let mut report = LifecycleReport::default();
+        for (bucket, rules) in &self.rules {
+            let rules: Vec<&LifecycleRule> = rules.iter().filter(|rule| rule.enabled).collect();
+            if rules.is_empty() {
+                continue;
+            }
+            expire_objects(
+                now,
+                bucket,
+                &rules,
+                catalog,
+                store,
+                storage_key,
+                &mut report,
+            );
+            abort_uploads(now, bucket, &rules, hooks, &mut report);
+            expire_versions(now, bucket, &rules, hooks, &mut report);
+        }
+        report
+    }
+}
+
+fn elapsed(now: u64, since: u64, limit: Option<u64>) -> bool {
+    limit.is_some_and(|limit| now.saturating_sub(since) >= limit)
+}
+
+fn expire_objects<C: Catalog, O: ObjectStore>(
+    now: u64,
+    bucket: &str,
+    rules: &[&LifecycleRule],
+    catalog: &mut C,
+    store: &mut O,
+    storage_key: fn(&str, &str) -> String,
+    report: &mut LifecycleReport,
+) {
+    let mut expired = Vec::new();
+    let listed = catalog.list_objects(bucket, &mut |key, object| {
+        let since = object.attributes.last_modified;
+        if let Some(rule) = rules
+            .iter()
+            .find(|rule| rule.matches(key, object) && elapsed(now, since, rule.expire_after))
+        {
+            expired.push((key.to_owned(), rule.id.clone()));
+        }
+    });
+    if listed == Err(CatalogError::NotFound) {
+        return;
+    }
+    for (key, rule) in expired {
+        if catalog.remove_object(bucket, &key).is_err() {
+            report.failures += 1;
+            continue;
+        }
+        match store.delete(&storage_key(bucket, &key)) {
+            Ok(()) | Err(ObjectError::NotFound) => {}
+            Err(_) => report.failures += 1,
+        }
+        report.actions.push(LifecycleAction::Expired {
+            bucket: bucket.to_owned(),
+            key,
+            rule,
+        });
+    }
+}
+
+fn abort_uploads(
+    now: u64,
+    bucket: &str,
+    rules: &[&LifecycleRule],
+    hooks: &mut dyn LifecycleHooks,
+    report: &mut LifecycleReport,
+) {
+    let mut stale = Vec::new();
+    hooks.incomplete_uploads(bucket, &mut |upload_id, key, initiated| {
+        if let Some(rule) = rules.iter().find(|rule| {
+            rule.matches_key(key) && elapsed(now, initiated, rule.abort_incomplete_after)
+        }) {
+            stale.push((upload_id.to_owned(), key.to_owned(), rule.id.clone()));
+        }
+    });
+    for (upload_id, key, rule) in stale {
+        if !hooks.abort_upload(bucket, &key, &upload_id) {
+            report.failures += 1;
+            continue;
+        }
+        report.actions.push(LifecycleAction::AbortedUpload {
+            bucket: bucket.to_owned(),
+            key,
+            upload_id,
+            rule,
+        });
+    }
+}
+
+fn expire_versions(
+    now: u64,
+    bucket: &str,
+    rules: &[&LifecycleRule],
+    hooks: &mut dyn LifecycleHooks,
+    report: &mut LifecycleReport,
+) {
+    let mut stale = Vec::new();
+    hooks.noncurrent_versions(bucket, &mut |key, version_id, since| {
+        if let Some(rule) = rules
+            .iter()
+            .find(|rule| rule.matches_key(key) && elapsed(now, since, rule.noncurrent_expire_after))
+        {
+            stale.push((key.to_owned(), version_id.to_owned(), rule.id.clone()));
+        }
+    });
+    for (key, version_id, rule) in stale {
+        if !hooks.remove_version(bucket, &key, &version_id) {
+            report.failures += 1;
+            continue;
+        }
+        report.actions.push(LifecycleAction::ExpiredVersion {
+            bucket: bucket.to_owned(),
+            key,
+            version_id,
+            rule,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::{InMemoryCatalog, ObjectAttributes};
+    use alloc::format;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use storage::object::InMemoryObjectStore;
+
+    fn storage_key(bucket: &str, key: &str) -> String {
+        format!("{}/{}", bucket, key)
+    }
+
+    #[derive(Default)]
+    struct Uploads {
+        open: Vec<(String, String, u64)>,
+    }
+
+    impl LifecycleHooks for Uploads {
+        fn incomplete_uploads(&self, _bucket: &str, visit: &mut dyn FnMut(&str, &str, u64)) {
+            for (id, key, initiated) in &self.open {
+                visit(id, key, *initiated);
+            }
+        }
+
+        fn abort_upload(&mut self, _bucket: &str, _key: &str, upload_id: &str) -> bool {
+            let before = self.open.len();
+            self.open.retain(|(id, _, _)| id != upload_id);
+            self.open.len() < before
+        }
+    }
+
+    #[test]
+    fn expires_matching_objects_and_aborts_stale_uploads() {
+        let mut catalog = InMemoryCatalog::new();
+        let mut store = InMemoryObjectStore::new();
+        catalog.create_bucket("logs").unwrap();
+        for (key, written, tags) in [
+            ("tmp/old", 10, vec![]),
+            ("tmp/new", 90, vec![]),
+            ("keep/old", 10, vec![]),
+            (
+                "keep/scratch",
+                10,
+                vec![("scratch".to_string(), "yes".to_string())],
+            ),
+        ] {
+            let meta = store.put(&storage_key("logs", key), b"data").unwrap();
+            let attributes = ObjectAttributes {
+                last_modified: written,
+                tags,
+                ..ObjectAttributes::default()
+            };
+            catalog
+                .put_object_with("logs", key, meta, attributes)
+                .unwrap();
+        }
+        let mut uploads = Uploads {
+            open: vec![
+                ("u1".to_string(), "big.bin".to_string(), 0),
+                ("u2".to_string(), "fresh.bin".to_string(), 95),
+            ],
+        };
+
+        let mut lifecycle = Lifecycle::new(50);
+        let tmp = LifecycleRule {
+            id: "tmp".to_string(),
+            enabled: true,
+            prefix: "tmp/".to_string(),
+            expire_after: Some(50),
+            abort_incomplete_after: None,
+            ..LifecycleRule::default()
+        };
+        let scratch = LifecycleRule {
+            id: "scratch".to_string(),
+            enabled: true,
+            tag: Some(("scratch".to_string(), "yes".to_string())),
+            expire_after: Some(50),
+            ..LifecycleRule::default()
+        };
+        let uploads_rule = LifecycleRule {
+            id: "mpu".to_string(),
+            enabled: true,
+            abort_incomplete_after: Some(30),
+            ..LifecycleRule::default()
+        };
+        assert_eq!(
+            lifecycle.set_rules("logs", vec![tmp.clone(), tmp.clone()]),
+            Err(LifecycleError::InvalidRule)
+        );
+        lifecycle
+            .set_rules("logs", vec![tmp, scratch, uploads_rule])
+            .unwrap();
+
+        // The first poll arms the timer; evaluation runs once it elapses.
+        assert!(!lifecycle.due(50));
+        assert!(!lifecycle.due(99));
+        assert!(lifecycle.due(100));
+        let report = lifecycle.evaluate(100, &mut catalog, &mut store, storage_key, &mut uploads);
+        assert_eq!(
+            report.actions,
+            [
+                LifecycleAction::Expired {
+                    bucket: "logs".to_string(),
+                    key: "keep/scratch".to_string(),
+                    rule: "scratch".to_string(),
+                },
+                LifecycleAction::Expired {
+                    bucket: "logs".to_string(),
+                    key: "tmp/old".to_string(),
+                    rule: "tmp".to_string(),
+                },
+                LifecycleAction::AbortedUpload {
+                    bucket: "logs".to_string(),
+                    key: "big.bin".to_string(),
+                    upload_id: "u1".to_string(),
+                    rule: "mpu".to_string(),
+                },
+            ]
+        );
+        assert_eq!(report.failures, 0);
+        let mut left = Vec::new();
+        catalog
+            .list_objects("logs", &mut |key, _| left.push(key.to_string()))
+            .unwrap();
+        assert_eq!(left, ["keep/old", "tmp/new"]);
+        let mut stored = Vec::new();
+        store.list(&mut |key, _| stored.push(key.to_string()));
+        assert_eq!(stored, ["logs/keep/old", "logs/tmp/new"]);
+        assert_eq!(uploads.open.len(), 1);
+        assert!(!lifecycle.due(120));
+        assert!(lifecycle.due(150));
+    }
+}
@@ -0,0 +1,483 @@
+#![allow(dead_code)]
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use storage::block::{BlockDevice, BlockError};
+
+/// Page number within the pager's address space. Pages 0 and 1 hold the
+/// superblock slots, so `0` doubles as the "no page" sentinel for roots.
+pub type PageId = u64;
+
+pub const NO_PAGE: PageId = 0;
+const SUPERBLOCK_SLOTS: PageId = 2;
+const SUPERBLOCK_MAGIC: &[u8; 8] = b"RCBTREE1";
+const SUPERBLOCK_LEN: usize = 44;
+
+/// Geometry of the paged region on the underlying device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PagerConfig {
+    /// Block size of the device in bytes.
+    pub block_size: usize,
+    /// Size of a page in bytes; must be a multiple of `block_size`.
+    pub page_size: usize,
+    /// Total pages available, including the two superblock slots.
+    pub page_count: u64,
+    /// Number of pages kept in the read cache.
+    pub cache_pages: usize,
+    /// First block of the paged region on the device.
+    pub base_lba: u64,
+}
+
+impl PagerConfig {
+    pub const DEFAULT_PAGE_SIZE: usize = 8192;
+    pub const DEFAULT_CACHE_PAGES: usize = 64;
+
+    pub const fn new(block_size: usize, page_count: u64) -> Self {
+        Self {
+            block_size,
+            page_size: Self::DEFAULT_PAGE_SIZE,
+            page_count,
+            cache_pages: Self::DEFAULT_CACHE_PAGES,
+            base_lba: 0,
+        }
+    }
+
+    fn validate(&self) -> Result<(), PagerError> {
+        if self.block_size == 0
+            || self.page_size < 512
+            || !self.page_size.is_multiple_of(self.block_size)
+            || self.page_count <= SUPERBLOCK_SLOTS
+        {
+            return Err(PagerError::Geometry);
+        }
+        Ok(())
+    }
+
+    fn blocks_per_page(&self) -> u64 {
+        (self.page_size / self.block_size) as u64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PagerError {
+    Device(BlockError),
+    Corrupt,
+    Full,
+    Geometry,
+}
+
+impl From<BlockError> for PagerError {
+    fn from(err: BlockError) -> Self {
+        Self::Device(err)
+    }
+}
+
+/// Copy-on-write page manager.
+///
+/// Pages written during a transaction are never visible to the committed
+/// state until `commit` atomically publishes a new root through the
+/// double-buffered superblock. Pages replaced during the transaction are
+/// only returned to the free pool once that commit has landed.
+pub struct Pager<D: BlockDevice> {
+    device: RefCell<D>,
+    config: PagerConfig,
+    cache: RefCell<PageCache>,
+    used: Bitmap,
+    fresh: BTreeSet<PageId>,
+    retired: Vec<PageId>,
+    generation: u64,
+    committed_root: PageId,
+    root: PageId,
+}
+
+impl<D: BlockDevice> Pager<D> {
+    /// Initialises an empty paged region, overwriting both superblock slots.
+    pub fn format(device: D, config: PagerConfig) -> Result<Self, PagerError> {
+        config.validate()?;
+        let mut pager = Self::with_device(device, config);
+        pager.write_superblock(0, 0, NO_PAGE)?;
+        pager.write_superblock(1, 0, NO_PAGE)?;
+        pager.device.get_mut().flush()?;
+        Ok(pager)
+    }
+
+    /// Loads the most recent valid superblock. Callers must mark every page
+    /// reachable from `root()` with `mark_used` before allocating.
+    pub fn open(device: D, config: PagerConfig) -> Result<Self, PagerError> {
+        config.validate()?;
+        let mut pager = Self::with_device(device, config);
+        let mut best: Option<(u64, PageId)> = None;
+        for slot in 0..SUPERBLOCK_SLOTS {
+            if let Some((generation, root)) = pager.read_superblock(slot)? {
+                if best.is_none_or(|(g, _)| generation > g) {
+                    best = Some((generation, root));
+                }
+            }
+        }
+        let (generation, root) = best.ok_or(PagerError::Corrupt)?;
+        if root != NO_PAGE && (root < SUPERBLOCK_SLOTS || root >= config.page_count) {
+            return Err(PagerError::Corrupt);
+        }
+        pager.generation = generation;
+        pager.committed_root = root;
+        pager.root = root;
+        Ok(pager)
+    }
+
+    fn with_device(device: D, config: PagerConfig) -> Self {
+        let mut used = Bitmap::new(config.page_count);
+        for slot in 0..SUPERBLOCK_SLOTS {
+            used.set(slot);
+        }
+        Self {
+            device: RefCell::new(device),
+            config,
+            cache: RefCell::new(PageCache::new(config.cache_pages)),
+            used,
+            fresh: BTreeSet::new(),
+            retired: Vec::new(),
+            generation: 0,
+            committed_root: NO_PAGE,
+            root: NO_PAGE,
+        }
+    }
+
+    pub fn into_device(self) -> D {
+        self.device.into_inner()
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.config.page_size
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn root(&self) -> PageId {
+        self.root
+    }
+
+    pub fn set_root(&mut self, root: PageId) {
+        self.root = root;
+    }
+
+    /// Number of pages not currently referenced by either the committed
+    /// tree or the open transaction.
+    pub fn free_pages(&self) -> u64 {
+        self.config.page_count - self.used.count()
+    }
+
+    /// Records a page as referenced by the committed tree. Used while
+    /// rebuilding the allocation map after `open`.
+    pub fn mark_used(&mut self, page: PageId) -> Result<(), PagerError> {
+        if page < SUPERBLOCK_SLOTS || page >= self.config.page_count || self.used.get(page) {
+            return Err(PagerError::Corrupt);
+        }
+        self.used.set(page);
+        Ok(())
+    }
+
+    pub fn read(&self, page: PageId) -> Result<Rc<[u8]>, PagerError> {
+        if page < SUPERBLOCK_SLOTS || page >= self.config.page_count {
+            return Err(PagerError::Corrupt);
+        }
+        if let Some(data) = self.cache.borrow_mut().get(page) {
+            return Ok(data);
+        }
+        let mut buffer = vec![0u8; self.config.page_size];
+        self.device.borrow_mut().read(self.lba(page), &mut buffer)?;
+        let data: Rc<[u8]> = Rc::from(buffer);
+        self.cache.borrow_mut().insert(page, data.clone());
+        Ok(data)
+    }
+
+    /// Writes `data` as the new contents of `previous`. Pages allocated in
+    /// the current transaction are rewritten in place; committed pages are
+    /// copied to a fresh page and retired.
+    pub fn write(&mut self, previous: Option<PageId>, data: &[u8]) -> Result<PageId, PagerError> {
+        if data.len() > self.config.page_size {
+            return Err(PagerError::Geometry);
+        }
+        let page = match previous {
+            Some(page) if self.fresh.contains(&page) => page,
+            Some(page) => {
+                let fresh = self.allocate()?;
+                self.retire(page);
+                fresh
+            }
+            None => self.allocate()?,
+        };
+        let mut buffer = vec![0u8; self.config.page_size];
+        buffer[..data.len()].copy_from_slice(data);
+        let lba = self.lba(page);
+        self.device.get_mut().write(lba, &buffer)?;
+        self.cache.get_mut().insert(page, Rc::from(buffer));
+        Ok(page)
+    }
+
+    /// Drops a page from the tree. Fresh pages are reclaimed immediately.
+    pub fn retire(&mut self, page: PageId) {
+        if self.fresh.remove(&page) {
+            self.used.clear(page);
+            self.cache.get_mut().remove(page);
+        } else {
+            self.retired.push(page);
+        }
+    }
+
+    /// Publishes the current root in the alternate superblock slot.
+    pub fn commit(&mut self) -> Result<(), PagerError> {
+        if self.root == self.committed_root && self.fresh.is_empty() && self.retired.is_empty() {
+            return Ok(());
+        }
+        self.device.get_mut().flush()?;
+        let generation = self.generation + 1;
+        self.write_superblock(generation % SUPERBLOCK_SLOTS, generation, self.root)?;
+        self.device.get_mut().flush()?;
+        self.generation = generation;
+        self.committed_root = self.root;
+        self.fresh.clear();
+        for page in core::mem::take(&mut self.retired) {
+            self.used.clear(page);
+            self.cache.get_mut().remove(page);
+        }
+        Ok(())
+    }
+
+    /// Discards every page written since the last commit.
+    pub fn rollback(&mut self) {
+        for page in core::mem::take(&mut self.fresh) {
+            self.used.clear(page);
+            self.cache.get_mut().remove(page);
+        }
+        self.retired.clear();
+        self.root = self.committed_root;
+    }
+
+    fn allocate(&mut self) -> Result<PageId, PagerError> {
+        let page = self
+            .used
+            .first_clear(SUPERBLOCK_SLOTS)
+            .ok_or(PagerError::Full)?;
+        self.used.set(page);
+        self.fresh.insert(page);
+        Ok(page)
+    }
+
+    fn lba(&self, page: PageId) -> u64 {
+        self.config.base_lba + page * self.config.blocks_per_page()
+    }
+
+    fn write_superblock(
+        &mut self,
+        slot: PageId,
+        generation: u64,
+        root: PageId,
+    ) -> Result<(), PagerError> {
+        let mut buffer = vec![0u8; self.config.block_size.max(SUPERBLOCK_LEN + 8)];
+        buffer[0..8].copy_from_slice(SUPERBLOCK_MAGIC);
+        buffer[8..16].copy_from_slice(&generation.to_le_bytes());
+        buffer[16..24].copy_from_slice(&root.to_le_bytes());
+        buffer[24..28].copy_from_slice(&(self.config.page_size as u32).to_le_bytes());
+        buffer[28..36].copy_from_slice(&self.config.page_count.to_le_bytes());
+        let sum = checksum(&buffer[..SUPERBLOCK_LEN]);
+        buffer[SUPERBLOCK_LEN..SUPERBLOCK_LEN + 8].copy_from_slice(&sum.to_le_bytes());
+        let lba = self.lba(slot);
+        self.device.get_mut().write(lba, &buffer)?;
+        Ok(())
+    }
+
+    fn read_superblock(&mut self, slot: PageId) -> Result<Option<(u64, PageId)>, PagerError> {
+        let mut buffer = vec![0u8; self.config.block_size.max(SUPERBLOCK_LEN + 8)];
+        let lba = self.lba(slot);
+        self.device.get_mut().read(lba, &mut buffer)?;
+        if &buffer[0..8] != SUPERBLOCK_MAGIC {
+            return Ok(None);
+        }
+        let stored = read_u64(&buffer[SUPERBLOCK_LEN..]);
+        if stored != checksum(&buffer[..SUPERBLOCK_LEN]) {
+            return Ok(None);
+        }
+        let page_size = u32::from_le_bytes([buffer[24], buffer[25], buffer[26], buffer[27]]);
+        if page_size as usize != self.config.page_size
+            || read_u64(&buffer[28..]) != self.config.page_count
+        {
+            return Err(PagerError::Geometry);
+        }
+        Ok(Some((read_u64(&buffer[8..]), read_u64(&buffer[16..]))))
+    }
+}
+
+/// FNV-1a over `bytes`; cheap torn-write detection for on-disk records.
+pub fn checksum(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+pub(crate) fn read_u64(bytes: &[u8]) -> u64 {
+    let mut raw = [0u8; 8];
+    raw.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(raw)
+}
+
+/// Least-recently-used cache of raw page images.
+struct PageCache {
+    capacity: usize,
+    clock: u64,
+    pages: BTreeMap<PageId, (Rc<[u8]>, u64)>,
+    recency: BTreeMap<u64, PageId>,
+}
+
+impl PageCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            clock: 0,
+            pages: BTreeMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, page: PageId) -> Option<Rc<[u8]>> {
+        self.clock += 1;
+        let clock = self.clock;
+        let (data, stamp) = self.pages.get_mut(&page)?;
+        self.recency.remove(stamp);
+        *stamp = clock;
+        self.recency.insert(clock, page);
+        Some(data.clone())
+    }
+
+    fn insert(&mut self, page: PageId, data: Rc<[u8]>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.remove(page);
+        while self.pages.len() >= self.capacity {
+            match self.recency.pop_first() {
+                Some((_, victim)) => {
+                    self.pages.remove(&victim);
+                }
+                None => break,
+            }
+        }
+        self.clock += 1;
+        self.pages.insert(page, (data, self.clock));
+        self.recency.insert(self.clock, page);
+    }
+
+    fn remove(&mut self, page: PageId) {
+        if let Some((_, stamp)) = self.pages.remove(&page) {
+            self.recency.remove(&stamp);
+        }
+    }
+}
+
+/// Page allocation map.
+struct Bitmap {
+    words: Vec<u64>,
+    len: u64,
+    set: u64,
+}
+
+impl Bitmap {
+    fn new(len: u64) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64) as usize],
+            len,
+            set: 0,
+        }
+    }
+
+    fn get(&self, bit: u64) -> bool {
+        self.words[(bit / 64) as usize] & (1 << (bit % 64)) != 0
+    }
+
+    fn set(&mut self, bit: u64) {
+        if !self.get(bit) {
+            self.words[(bit / 64) as usize] |= 1 << (bit % 64);
+            self.set += 1;
+        }
+    }
+
+    fn clear(&mut self, bit: u64) {
+        if self.get(bit) {
+            self.words[(bit / 64) as usize] &= !(1 << (bit % 64));
+            self.set -= 1;
+        }
+    }
+
+    fn count(&self) -> u64 {
+        self.set
+    }
+
+    fn first_clear(&self, from: u64) -> Option<u64> {
+        let mut word_idx = (from / 64) as usize;
+        let mut mask = !0u64 << (from % 64);
+        while word_idx < self.words.len() {
+            let free = !self.words[word_idx] & mask;
+            if free != 0 {
+                let bit = word_idx as u64 * 64 + free.trailing_zeros() as u64;
+                return (bit < self.len).then_some(bit);
+            }
+            word_idx += 1;
+            mask = !0;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use storage::block::MemoryBlockDevice;
+
+    fn config() -> PagerConfig {
+        PagerConfig {
+            page_size: 1024,
+            ..PagerConfig::new(512, 16)
+        }
+    }
+
+    #[test]
+    fn commit_publishes_latest_root() {
+        let mut disk = vec![0u8; 16 * 1024];
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let mut pager = Pager::format(device, config()).unwrap();
+        let first = pager.write(None, b"first").unwrap();
+        pager.set_root(first);
+        pager.commit().unwrap();
+        let second = pager.write(Some(first), b"second").unwrap();
+        assert_ne!(first, second);
+        pager.set_root(second);
+        pager.commit().unwrap();
+
+        let reopened = Pager::open(pager.into_device(), config()).unwrap();
+        assert_eq!(reopened.root(), second);
+        assert_eq!(reopened.generation(), 2);
+        assert_eq!(&reopened.read(second).unwrap()[..6], b"second");
+    }
+
+    #[test]
+    fn rollback_reclaims_fresh_pages() {
+        let mut disk = vec![0u8; 16 * 1024];
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let mut pager = Pager::format(device, config()).unwrap();
+        let free = pager.free_pages();
+        let page = pager.write(None, b"scratch").unwrap();
+        pager.set_root(page);
+        assert_eq!(pager.free_pages(), free - 1);
+        pager.rollback();
+        assert_eq!(pager.root(), NO_PAGE);
+        assert_eq!(pager.free_pages(), free);
+    }
+}
@@ -0,0 +1,119 @@
+#![allow(dead_code)]
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Little-endian encoder for catalog rows.
+#[derive(Default)]
+pub struct RecordWriter {
+    buffer: Vec<u8>,
+}
+
+impl RecordWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn u8(&mut self, value: u8) -> &mut Self {
+        self.buffer.push(value);
+        self
+    }
+
+    pub fn u16(&mut self, value: u16) -> &mut Self {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u64(&mut self, value: u64) -> &mut Self {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u128(&mut self, value: u128) -> &mut Self {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    /// Optional values are stored with `u64::MAX` as the absent marker.
+    pub fn opt_u64(&mut self, value: Option<u64>) -> &mut Self {
+        self.u64(value.unwrap_or(u64::MAX))
+    }
+
+    /// Length-prefixed string; callers bound lengths well below `u16::MAX`.
+    pub fn str(&mut self, value: &str) -> &mut Self {
+        self.u16(value.len() as u16);
+        self.buffer.extend_from_slice(value.as_bytes());
+        self
+    }
+
+    pub fn pairs(&mut self, pairs: &[(String, String)]) -> &mut Self {
+        self.u16(pairs.len() as u16);
+        for (key, value) in pairs {
+            self.str(key).str(value);
+        }
+        self
+    }
+
+    pub fn finish(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.buffer)
+    }
+}
+
+/// Decoder matching `RecordWriter`; every accessor returns `None` on a
+/// truncated or malformed record.
+pub struct RecordReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RecordReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let out = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(out)
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        self.take(8)?.try_into().ok().map(u64::from_le_bytes)
+    }
+
+    pub fn u128(&mut self) -> Option<u128> {
+        self.take(16)?.try_into().ok().map(u128::from_le_bytes)
+    }
+
+    pub fn opt_u64(&mut self) -> Option<Option<u64>> {
+        self.u64()
+            .map(|value| Some(value).filter(|v| *v != u64::MAX))
+    }
+
+    pub fn str(&mut self) -> Option<String> {
+        let len = self.u16()? as usize;
+        let bytes = self.take(len)?;
+        core::str::from_utf8(bytes).ok().map(String::from)
+    }
+
+    pub fn pairs(&mut self) -> Option<Vec<(String, String)>> {
+        let count = self.u16()? as usize;
+        let mut out = Vec::with_capacity(count);
+        for _ in 0..count {
+            out.push((self.str()?, self.str()?));
+        }
+        Some(out)
+    }
+}
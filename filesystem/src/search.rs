@@ -0,0 +1,389 @@
+#![allow(dead_code)]
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Bound;
+
+use crate::index::{Entry, IndexError, Resume};
+
+/// Attribute query over one bucket. Every filter that is set must match;
+/// range bounds are inclusive.
+#[derive(Default)]
+pub struct SearchQuery<'a> {
+    pub bucket: &'a str,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub modified_from: Option<u64>,
+    pub modified_to: Option<u64>,
+    pub content_type: Option<&'a str>,
+    pub tag: Option<(&'a str, &'a str)>,
+    /// Opaque token from a previous `SearchResponse::next_token`.
+    pub continuation: Option<&'a str>,
+    /// Upper bound on hits per page; `0` means unlimited.
+    pub max_keys: usize,
+}
+
+pub struct SearchHit<'a> {
+    pub key: &'a str,
+    pub size: u64,
+    pub last_modified: u64,
+    pub content_type: &'a str,
+}
+
+/// One page of hits in key order.
+pub struct SearchResponse<'a> {
+    pub hits: Vec<SearchHit<'a>>,
+    pub next_token: Option<String>,
+    pub is_truncated: bool,
+}
+
+/// Attribute search alongside prefix listing.
+pub trait Search {
+    fn search(&self, query: &SearchQuery<'_>) -> Result<SearchResponse<'_>, IndexError>;
+}
+
+/// Per-bucket secondary indexes over object attributes. Exact-match
+/// indexes map a value to its keys in key order; range indexes order keys
+/// by size and by modification time. Kept in step with the bucket's
+/// primary entries by `add` and `remove`.
+#[derive(Default)]
+pub(crate) struct SecondaryIndex {
+    by_content_type: BTreeMap<String, BTreeSet<String>>,
+    by_tag: BTreeMap<(String, String), BTreeSet<String>>,
+    by_size: BTreeSet<(u64, String)>,
+    by_modified: BTreeSet<(u64, String)>,
+}
+
+impl SecondaryIndex {
+    pub(crate) fn add(&mut self, key: &str, entry: &Entry) {
+        let attributes = &entry.attributes;
+        self.by_content_type
+            .entry(attributes.content_type.clone())
+            .or_default()
+            .insert(key.to_owned());
+        for tag in &attributes.tags {
+            self.by_tag
+                .entry(tag.clone())
+                .or_default()
+                .insert(key.to_owned());
+        }
+        self.by_size.insert((entry.meta.size, key.to_owned()));
+        self.by_modified
+            .insert((attributes.last_modified, key.to_owned()));
+    }
+
+    pub(crate) fn remove(&mut self, key: &str, entry: &Entry) {
+        let attributes = &entry.attributes;
+        remove_from(&mut self.by_content_type, &attributes.content_type, key);
+        for tag in &attributes.tags {
+            remove_from(&mut self.by_tag, tag, key);
+        }
+        self.by_size.remove(&(entry.meta.size, key.to_owned()));
+        self.by_modified
+            .remove(&(attributes.last_modified, key.to_owned()));
+    }
+
+    /// Answers `query` against `objects`, the bucket's primary entries. The
+    /// tag or content-type index supplies candidates when the query has
+    /// one, then the size or modification-time index when it has a range,
+    /// otherwise the primary entries do; either way in key order from the
+    /// continuation key, stopping once the page is known to be truncated.
+    /// Remaining filters are checked against each candidate.
+    pub(crate) fn search<'a>(
+        &self,
+        objects: &'a BTreeMap<String, Entry>,
+        query: &SearchQuery<'_>,
+    ) -> Result<SearchResponse<'a>, IndexError> {
+        let after = match query.continuation {
+            Some(token) => match Resume::decode(token)? {
+                Resume::After(key) => Some(key),
+                Resume::Beyond(_) => return Err(IndexError::InvalidToken),
+            },
+            None => None,
+        };
+        let max_keys = if query.max_keys == 0 {
+            usize::MAX
+        } else {
+            query.max_keys
+        };
+
+        let start = match after.as_deref() {
+            Some(after) => Bound::Excluded(after),
+            None => Bound::Unbounded,
+        };
+        let range = (start, Bound::Unbounded);
+        let ranged;
+        let candidates: Box<dyn Iterator<Item = &str> + '_> = if let Some((name, value)) = query.tag
+        {
+            members(self.by_tag.get(&(name.to_owned(), value.to_owned())), range)
+        } else if let Some(content_type) = query.content_type {
+            members(self.by_content_type.get(content_type), range)
+        } else if query.min_size.is_some() || query.max_size.is_some() {
+            ranged = in_range(&self.by_size, query.min_size, query.max_size);
+            Box::new(ranged.range::<str, _>(range).copied())
+        } else if query.modified_from.is_some() || query.modified_to.is_some() {
+            ranged = in_range(&self.by_modified, query.modified_from, query.modified_to);
+            Box::new(ranged.range::<str, _>(range).copied())
+        } else {
+            Box::new(objects.range::<str, _>(range).map(|(key, _)| key.as_str()))
+        };
+
+        let mut hits = Vec::new();
+        for key in candidates {
+            let Some((key, entry)) = objects.get_key_value(key) else {
+                continue;
+            };
+            if !matches(query, entry) {
+                continue;
+            }
+            hits.push(SearchHit {
+                key,
+                size: entry.meta.size,
+                last_modified: entry.attributes.last_modified,
+                content_type: &entry.attributes.content_type,
+            });
+            // One hit past the page shows there is more.
+            if hits.len() > max_keys {
+                break;
+            }
+        }
+
+        let is_truncated = hits.len() > max_keys;
+        hits.truncate(max_keys);
+        let next_token = if is_truncated {
+            hits.last()
+                .map(|hit| Resume::After(hit.key.to_owned()).encode())
+        } else {
+            None
+        };
+        Ok(SearchResponse {
+            hits,
+            next_token,
+            is_truncated,
+        })
+    }
+}
+
+fn matches(query: &SearchQuery<'_>, entry: &Entry) -> bool {
+    let size = entry.meta.size;
+    let modified = entry.attributes.last_modified;
+    query.min_size.is_none_or(|min| size >= min)
+        && query.max_size.is_none_or(|max| size <= max)
+        && query.modified_from.is_none_or(|from| modified >= from)
+        && query.modified_to.is_none_or(|to| modified <= to)
+        && query
+            .content_type
+            .is_none_or(|content_type| entry.attributes.content_type == content_type)
+        && query.tag.is_none_or(|tag| entry.has_tag(tag))
+}
+
+/// Keys of `set` within `range`, in order.
+fn members<'s>(
+    set: Option<&'s BTreeSet<String>>,
+    range: (Bound<&'s str>, Bound<&'s str>),
+) -> Box<dyn Iterator<Item = &'s str> + 's> {
+    Box::new(
+        set.into_iter()
+            .flat_map(move |keys| keys.range::<str, _>(range))
+            .map(String::as_str),
+    )
+}
+
+/// Keys whose value in `set` lies within the inclusive bounds, re-sorted
+/// into key order.
+fn in_range(set: &BTreeSet<(u64, String)>, min: Option<u64>, max: Option<u64>) -> BTreeSet<&str> {
+    let min = min.unwrap_or(0);
+    if max.is_some_and(|max| max < min) {
+        return BTreeSet::new();
+    }
+    let end = match max.and_then(|max| max.checked_add(1)) {
+        Some(end) => Bound::Excluded((end, String::new())),
+        None => Bound::Unbounded,
+    };
+    set.range((Bound::Included((min, String::new())), end))
+        .map(|(_, key)| key.as_str())
+        .collect()
+}
+
+fn remove_from<K: Ord>(map: &mut BTreeMap<K, BTreeSet<String>>, name: &K, key: &str) {
+    if let Some(keys) = map.get_mut(name) {
+        keys.remove(key);
+        if keys.is_empty() {
+            map.remove(name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::ObjectAttributes;
+    use crate::index::{InMemoryIndex, MutableIndex};
+    use alloc::string::ToString;
+    use storage::object::ObjectMetadata;
+
+    fn put(index: &mut InMemoryIndex, key: &str, size: u64, modified: u64, content_type: &str) {
+        let meta = ObjectMetadata {
+            id: 0,
+            size,
+            checksum: 0,
+        };
+        let attributes = ObjectAttributes {
+            content_type: content_type.to_string(),
+            last_modified: modified,
+            ..ObjectAttributes::default()
+        };
+        index.insert_with("media", key, meta, &attributes);
+    }
+
+    fn keys(response: &SearchResponse<'_>) -> Vec<String> {
+        response
+            .hits
+            .iter()
+            .map(|hit| hit.key.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn filters_combine_and_track_updates() {
+        let mut index = InMemoryIndex::new();
+        put(&mut index, "a.png", 10, 100, "image/png");
+        put(&mut index, "b.png", 500, 200, "image/png");
+        put(&mut index, "c.txt", 50, 300, "text/plain");
+        put(&mut index, "d.png", 70, 400, "image/png");
+        index.set_tags("media", "d.png", &[("tier".to_string(), "hot".to_string())]);
+
+        let query = SearchQuery {
+            bucket: "media",
+            content_type: Some("image/png"),
+            max_size: Some(100),
+            ..SearchQuery::default()
+        };
+        assert_eq!(keys(&index.search(&query).unwrap()), ["a.png", "d.png"]);
+
+        let recent = SearchQuery {
+            bucket: "media",
+            modified_from: Some(200),
+            modified_to: Some(300),
+            ..SearchQuery::default()
+        };
+        assert_eq!(keys(&index.search(&recent).unwrap()), ["b.png", "c.txt"]);
+
+        let tagged = SearchQuery {
+            bucket: "media",
+            tag: Some(("tier", "hot")),
+            ..SearchQuery::default()
+        };
+        assert_eq!(keys(&index.search(&tagged).unwrap()), ["d.png"]);
+
+        // Overwrites and removals leave no stale secondary entries.
+        put(&mut index, "a.png", 10, 100, "text/plain");
+        index.remove("media", "d.png");
+        assert!(index.search(&query).unwrap().hits.is_empty());
+        assert!(index.search(&tagged).unwrap().hits.is_empty());
+    }
+
+    #[test]
+    fn paginates_in_key_order() {
+        let mut index = InMemoryIndex::new();
+        for (n, key) in ["e", "d", "c", "b", "a"].iter().enumerate() {
+            put(&mut index, key, n as u64, 0, "text/plain");
+        }
+        let mut query = SearchQuery {
+            bucket: "media",
+            min_size: Some(1),
+            max_keys: 2,
+            ..SearchQuery::default()
+        };
+        let first = index.search(&query).unwrap();
+        assert_eq!(keys(&first), ["a", "b"]);
+        assert!(first.is_truncated);
+        let token = first.next_token.unwrap();
+        query.continuation = Some(&token);
+        let second = index.search(&query).unwrap();
+        assert_eq!(keys(&second), ["c", "d"]);
+        assert!(!second.is_truncated);
+
+        // Index-backed candidates resume from the token too.
+        for key in ["b", "c", "e"] {
+            index.set_tags("media", key, &[("tier".to_string(), "hot".to_string())]);
+        }
+        let tagged = SearchQuery {
+            bucket: "media",
+            tag: Some(("tier", "hot")),
+            continuation: Some(&token),
+            max_keys: 1,
+            ..SearchQuery::default()
+        };
+        let page = index.search(&tagged).unwrap();
+        assert_eq!(keys(&page), ["c"]);
+        assert!(page.is_truncated);
+
+        query.continuation = Some("zz");
+        assert!(matches!(
+            index.search(&query),
+            Err(IndexError::InvalidToken)
+        ));
+        let missing = SearchQuery {
+            bucket: "nope",
+            ..SearchQuery::default()
+        };
+        assert!(matches!(index.search(&missing), Err(IndexError::NotFound)));
+    }
+
+    #[test]
+    fn range_queries_resume_from_the_continuation_key() {
+        let mut index = InMemoryIndex::new();
+        // Sizes and times deliberately run against key order.
+        for (n, key) in ["a", "b", "c", "d", "e", "f"].iter().enumerate() {
+            let rank = 6 - n as u64;
+            put(&mut index, key, rank * 10, rank * 100, "text/plain");
+        }
+
+        let mut sized = SearchQuery {
+            bucket: "media",
+            min_size: Some(20),
+            max_size: Some(50),
+            max_keys: 3,
+            ..SearchQuery::default()
+        };
+        let first = index.search(&sized).unwrap();
+        assert_eq!(keys(&first), ["b", "c", "d"]);
+        let token = first.next_token.unwrap();
+        sized.continuation = Some(&token);
+        let second = index.search(&sized).unwrap();
+        assert_eq!(keys(&second), ["e"]);
+        assert!(!second.is_truncated);
+
+        let mut dated = SearchQuery {
+            bucket: "media",
+            modified_from: Some(300),
+            max_keys: 2,
+            ..SearchQuery::default()
+        };
+        let first = index.search(&dated).unwrap();
+        assert_eq!(keys(&first), ["a", "b"]);
+        let token = first.next_token.unwrap();
+        dated.continuation = Some(&token);
+        let second = index.search(&dated).unwrap();
+        assert_eq!(keys(&second), ["c", "d"]);
+        assert!(!second.is_truncated);
+
+        // Overwrites move keys within the range indexes.
+        put(&mut index, "f", 35, 0, "text/plain");
+        index.remove("media", "c");
+        let second = index.search(&sized).unwrap();
+        assert_eq!(keys(&second), ["e", "f"]);
+
+        let empty = SearchQuery {
+            bucket: "media",
+            min_size: Some(50),
+            max_size: Some(10),
+            ..SearchQuery::default()
+        };
+        assert!(index.search(&empty).unwrap().hits.is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+#![allow(dead_code)]
+
+//! S3 naming rules for buckets and object keys.
+
+/// Why a bucket name was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BucketNameError {
+    /// Shorter than 3 or longer than 63 characters.
+    Length,
+    /// Contains something other than lowercase letters, digits, `.` or `-`.
+    Character,
+    /// Does not begin and end with a letter or digit.
+    Boundary,
+    AdjacentPeriods,
+    /// Formatted like an IPv4 address, e.g. `192.168.5.4`.
+    IpAddress,
+    /// Uses a prefix or suffix S3 reserves for its own features.
+    Reserved,
+}
+
+/// Why an object key was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyError {
+    Empty,
+    /// Longer than 1024 bytes of UTF-8.
+    TooLong,
+    /// Contains a control character while `ControlCharPolicy::Reject` is in
+    /// effect.
+    ControlCharacter,
+}
+
+/// Treatment of Unicode control characters (`U+0000..=U+001F`,
+/// `U+007F..=U+009F`) in object keys. S3 accepts them but they break
+/// line-oriented listings and most tooling, so rejecting is the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ControlCharPolicy {
+    Allow,
+    #[default]
+    Reject,
+}
+
+pub const MIN_BUCKET_NAME_LEN: usize = 3;
+pub const MAX_BUCKET_NAME_LEN: usize = 63;
+pub const MAX_KEY_LEN: usize = 1024;
+
+const RESERVED_PREFIXES: &[&str] = &["xn--", "sthree-", "amzn-s3-demo-"];
+const RESERVED_SUFFIXES: &[&str] = &["-s3alias", "--ol-s3", ".mrap", "--x-s3"];
+
+pub fn validate_bucket_name(name: &str) -> Result<(), BucketNameError> {
+    if !(MIN_BUCKET_NAME_LEN..=MAX_BUCKET_NAME_LEN).contains(&name.len()) {
+        return Err(BucketNameError::Length);
+    }
+    let bytes = name.as_bytes();
+    if !bytes
+        .iter()
+        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || *b == b'.' || *b == b'-')
+    {
+        return Err(BucketNameError::Character);
+    }
+    let alnum = |b: u8| b.is_ascii_lowercase() || b.is_ascii_digit();
+    if !alnum(bytes[0]) || !alnum(bytes[bytes.len() - 1]) {
+        return Err(BucketNameError::Boundary);
+    }
+    if name.contains("..") {
+        return Err(BucketNameError::AdjacentPeriods);
+    }
+    if looks_like_ipv4(name) {
+        return Err(BucketNameError::IpAddress);
+    }
+    if RESERVED_PREFIXES.iter().any(|p| name.starts_with(p))
+        || RESERVED_SUFFIXES.iter().any(|s| name.ends_with(s))
+    {
+        return Err(BucketNameError::Reserved);
+    }
+    Ok(())
+}
+
+pub fn validate_key(key: &str, policy: ControlCharPolicy) -> Result<(), KeyError> {
+    if key.is_empty() {
+        return Err(KeyError::Empty);
+    }
+    if key.len() > MAX_KEY_LEN {
+        return Err(KeyError::TooLong);
+    }
+    if policy == ControlCharPolicy::Reject && key.chars().any(char::is_control) {
+        return Err(KeyError::ControlCharacter);
+    }
+    Ok(())
+}
+
+fn looks_like_ipv4(name: &str) -> bool {
+    let mut octets = 0;
+    for part in name.split('.') {
+        octets += 1;
+        if part.is_empty() || part.len() > 3 || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return false;
+        }
+    }
+    octets == 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_rules() {
+        assert_eq!(validate_bucket_name("photos"), Ok(()));
+        assert_eq!(validate_bucket_name("my.logs-2024"), Ok(()));
+        assert_eq!(validate_bucket_name("ab"), Err(BucketNameError::Length));
+        assert_eq!(
+            validate_bucket_name(&"a".repeat(64)),
+            Err(BucketNameError::Length)
+        );
+        assert_eq!(
+            validate_bucket_name("Photos"),
+            Err(BucketNameError::Character)
+        );
+        assert_eq!(
+            validate_bucket_name("my_logs"),
+            Err(BucketNameError::Character)
+        );
+        assert_eq!(
+            validate_bucket_name("-logs"),
+            Err(BucketNameError::Boundary)
+        );
+        assert_eq!(
+            validate_bucket_name("logs."),
+            Err(BucketNameError::Boundary)
+        );
+        assert_eq!(
+            validate_bucket_name("my..logs"),
+            Err(BucketNameError::AdjacentPeriods)
+        );
+        assert_eq!(
+            validate_bucket_name("192.168.5.4"),
+            Err(BucketNameError::IpAddress)
+        );
+        assert_eq!(
+            validate_bucket_name("xn--abc"),
+            Err(BucketNameError::Reserved)
+        );
+    }
+
+    #[test]
+    fn key_rules() {
+        assert_eq!(validate_key("a/b/c.txt", ControlCharPolicy::Reject), Ok(()));
+        assert_eq!(
+            validate_key("", ControlCharPolicy::Allow),
+            Err(KeyError::Empty)
+        );
+        assert_eq!(
+            validate_key(&"k".repeat(1025), ControlCharPolicy::Allow),
+            Err(KeyError::TooLong)
+        );
+        // Length is measured in UTF-8 bytes, not characters.
+        assert_eq!(
+            validate_key(&"é".repeat(513), ControlCharPolicy::Allow),
+            Err(KeyError::TooLong)
+        );
+        assert_eq!(
+            validate_key("line\nbreak", ControlCharPolicy::Reject),
+            Err(KeyError::ControlCharacter)
+        );
+        assert_eq!(
+            validate_key("line\nbreak", ControlCharPolicy::Allow),
+            Ok(())
+        );
+    }
+}
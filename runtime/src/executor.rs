@@ -43,7 +43,7 @@ impl Executor for SimpleExecutor {
 
 #[cfg(test)]
 mod tests {
-    use super::{SimpleExecutor, Task};
+    use super::{Executor, SimpleExecutor, Task};
     use core::cell::UnsafeCell;
     use core::sync::atomic::{AtomicUsize, Ordering};
 
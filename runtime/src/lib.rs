@@ -2,6 +2,6 @@
 
 extern crate alloc;
 
-pub mod alloc;
 pub mod executor;
+pub mod heap;
 pub mod timers;
@@ -59,7 +59,7 @@ impl TimerService for TimerQueue {
 
 #[cfg(test)]
 mod tests {
-    use super::{TimerId, TimerQueue, TimerService};
+    use super::{TimerQueue, TimerService};
 
     #[test]
     fn schedule_and_poll() {
@@ -1,31 +1,105 @@
 #![allow(dead_code)]
 
-use crate::keystore::{ApiKeyEntry, KeyStore, KeyStoreError};
+use crate::keystore::{hash_secret, ApiKeyEntry, KeyStatus, KeyStore, KeyStoreError, SALT_LEN};
 
 pub trait ApiKeyValidator {
-    fn validate(&self, key: &str) -> Result<ApiKeyEntry<'_>, AuthError>;
+    /// Resolves `key_id` and checks `secret` against its stored hash.
+    fn validate(&self, key_id: &str, secret: &str) -> Result<ApiKeyEntry<'_>, AuthError>;
+    /// Resolves `key_id` for schemes that sign requests rather than send
+    /// the secret: `check` is offered each signing secret currently
+    /// accepted for the key and reports whether it produced the signature.
+    fn validate_signature(
+        &self,
+        key_id: &str,
+        check: &mut dyn FnMut(&str) -> bool,
+    ) -> Result<ApiKeyEntry<'_>, AuthError>;
+    /// Resolves `key_id` for a client the transport already authenticated,
+    /// e.g. by a verified certificate bound to the key.
+    fn validate_certificate(&self, key_id: &str) -> Result<ApiKeyEntry<'_>, AuthError>;
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AuthError {
     Missing,
     Invalid,
+    /// The request signature did not match the one computed for it.
+    SignatureMismatch,
+    /// The request was signed too far from the server's clock.
+    ClockSkew,
+    /// A presigned request's validity window has passed.
+    Expired,
+    /// The key was revoked.
+    Revoked,
+    /// The key is disabled.
+    Disabled,
+    /// The key passed its expiry timestamp.
+    KeyExpired,
+    /// The client certificate is not bound to any key.
+    UnboundCertificate,
     Store(KeyStoreError),
 }
 
 pub struct StaticApiKeyValidator<'a, S: KeyStore> {
     store: &'a S,
+    /// Unix time used for key expiry and rotation grace periods.
+    now: u64,
 }
 
 impl<'a, S: KeyStore> StaticApiKeyValidator<'a, S> {
     pub fn new(store: &'a S) -> Self {
-        Self { store }
+        Self { store, now: 0 }
+    }
+
+    pub fn at(self, now: u64) -> Self {
+        Self { now, ..self }
+    }
+
+    /// Rejects keys that are revoked, disabled or expired. Only called once
+    /// the credentials checked out, so the reason is not leaked to guessers.
+    fn usable<'e>(&self, entry: ApiKeyEntry<'e>) -> Result<ApiKeyEntry<'e>, AuthError> {
+        match entry.status {
+            KeyStatus::Revoked => Err(AuthError::Revoked),
+            KeyStatus::Disabled => Err(AuthError::Disabled),
+            KeyStatus::Active if entry.is_expired(self.now) => Err(AuthError::KeyExpired),
+            KeyStatus::Active => Ok(entry),
+        }
     }
 }
 
 impl<'a, S: KeyStore> ApiKeyValidator for StaticApiKeyValidator<'a, S> {
-    fn validate(&self, key: &str) -> Result<ApiKeyEntry<'_>, AuthError> {
-        self.store.lookup(key).map_err(AuthError::Store)
+    fn validate(&self, key_id: &str, secret: &str) -> Result<ApiKeyEntry<'_>, AuthError> {
+        match self.store.lookup(key_id) {
+            Ok(entry) if entry.verify(secret, self.now) => self.usable(entry),
+            Ok(_) => Err(AuthError::Invalid),
+            Err(err) => {
+                // Hash anyway so unknown key IDs take as long as wrong secrets.
+                core::hint::black_box(hash_secret(&[0; SALT_LEN], secret));
+                Err(AuthError::Store(err))
+            }
+        }
+    }
+
+    fn validate_signature(
+        &self,
+        key_id: &str,
+        check: &mut dyn FnMut(&str) -> bool,
+    ) -> Result<ApiKeyEntry<'_>, AuthError> {
+        let entry = self.store.lookup(key_id).map_err(AuthError::Store)?;
+        let mut secrets = entry.signing_secrets(self.now).peekable();
+        if secrets.peek().is_none() {
+            // Nothing can verify, revoked keys included; their status is
+            // not revealed without a valid signature.
+            return Err(AuthError::Invalid);
+        }
+        if !secrets.any(check) {
+            return Err(AuthError::SignatureMismatch);
+        }
+        self.usable(entry)
+    }
+
+    fn validate_certificate(&self, key_id: &str) -> Result<ApiKeyEntry<'_>, AuthError> {
+        let entry = self.store.lookup(key_id).map_err(AuthError::Store)?;
+        self.usable(entry)
     }
 }
 
@@ -33,21 +107,85 @@ impl<'a, S: KeyStore> ApiKeyValidator for StaticApiKeyValidator<'a, S> {
 mod tests {
     use super::*;
     use crate::keystore::{InMemoryKeyStore, KeyStore};
+    use crate::permissions::{Permission, Permissions};
 
     #[test]
     fn validates_existing_key() {
         let mut store = InMemoryKeyStore::new();
         store
-            .insert(ApiKeyEntry {
-                key_id: "key1",
-                hash: [0xAB; 32],
-                bucket: "photos",
-                permissions: 0x1,
-            })
+            .insert(ApiKeyEntry::with_secret(
+                "key1",
+                "s3cret",
+                [0xAB; SALT_LEN],
+                "photos",
+                Permissions::NONE.with(Permission::Read),
+            ))
             .unwrap();
         let validator = StaticApiKeyValidator::new(&store);
-        let entry = validator.validate("key1").unwrap();
+        let entry = validator.validate("key1", "s3cret").unwrap();
         assert_eq!(entry.bucket, "photos");
+        assert_eq!(validator.validate("key1", "guess"), Err(AuthError::Invalid));
+    }
+
+    #[test]
+    fn rejects_revoked_disabled_and_expired_keys() {
+        let mut store = InMemoryKeyStore::new();
+        let entry = ApiKeyEntry::with_signing_secret(
+            "key1",
+            "old",
+            [1; SALT_LEN],
+            "photos",
+            Permissions::ALL,
+        );
+        store.insert(entry.with_expiry(2_000)).unwrap();
+        store.rotate("key1", "new", [2; SALT_LEN], 1_500).unwrap();
+
+        // The old secret works through its grace period only.
+        let validator = StaticApiKeyValidator::new(&store).at(1_000);
+        assert!(validator.validate("key1", "old").is_ok());
+        assert!(validator.validate("key1", "new").is_ok());
+        assert!(validator
+            .validate_signature("key1", &mut |s| s == "old")
+            .is_ok());
+        let validator = StaticApiKeyValidator::new(&store).at(1_600);
+        assert_eq!(validator.validate("key1", "old"), Err(AuthError::Invalid));
+        assert_eq!(
+            validator
+                .validate_signature("key1", &mut |s| s == "old")
+                .map(|e| e.key_id),
+            Err(AuthError::SignatureMismatch)
+        );
+        let validator = StaticApiKeyValidator::new(&store).at(2_000);
+        assert_eq!(
+            validator.validate("key1", "new"),
+            Err(AuthError::KeyExpired)
+        );
+
+        store.set_expiry("key1", None).unwrap();
+        store.set_enabled("key1", false).unwrap();
+        let validator = StaticApiKeyValidator::new(&store).at(2_000);
+        assert_eq!(validator.validate("key1", "new"), Err(AuthError::Disabled));
+        // Wrong secrets stay `Invalid` whatever the key's state.
+        assert_eq!(validator.validate("key1", "guess"), Err(AuthError::Invalid));
+
+        store.revoke("key1").unwrap();
+        assert_eq!(store.set_enabled("key1", true), Err(KeyStoreError::Revoked));
+        let validator = StaticApiKeyValidator::new(&store);
+        assert_eq!(validator.validate("key1", "new"), Err(AuthError::Revoked));
+        // Revoked keys have no signing secrets left, so signed requests
+        // cannot learn that the key exists.
+        assert_eq!(
+            validator
+                .validate_signature("key1", &mut |_| true)
+                .map(|e| e.key_id),
+            Err(AuthError::Invalid)
+        );
+        let mut listed = 0;
+        store.keys(&mut |entry| {
+            assert_eq!(entry.status, KeyStatus::Revoked);
+            listed += 1;
+        });
+        assert_eq!(listed, 1);
     }
 
     #[test]
@@ -55,7 +193,7 @@ mod tests {
         let store = InMemoryKeyStore::new();
         let validator = StaticApiKeyValidator::new(&store);
         assert!(matches!(
-            validator.validate("missing"),
+            validator.validate("missing", "s3cret"),
             Err(AuthError::Store(KeyStoreError::NotFound))
         ));
     }
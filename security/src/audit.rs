@@ -0,0 +1,282 @@
+#![allow(dead_code)]
+
+//! Tamper-evident log of authentication and authorization decisions.
+//!
+//! Each entry's hash covers its fields and the previous entry's hash, so
+//! editing, removing or reordering retained entries breaks the chain.
+//! Dropping entries off the end is only caught against a `head` recorded
+//! elsewhere. With a signing key the hashes are HMACs, so the chain cannot
+//! be rebuilt after an edit without the key.
+
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+
+use crate::crypto::{HmacSha256, Sha256, SHA256_LEN};
+use crate::masterkey::SubKey;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditDecision {
+    Allow,
+    Deny,
+}
+
+impl AuditDecision {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditDecision::Allow => "allow",
+            AuditDecision::Deny => "deny",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditError {
+    /// An entry's contents no longer match its hash.
+    Edited,
+    /// Sequence numbers skip, so entries were removed or reordered.
+    Removed,
+    /// The newest hash differs from the expected head.
+    Truncated,
+}
+
+/// One decision as reported by the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuditRecord<'a> {
+    /// Key ID the request authenticated as; `None` if it did not.
+    pub key_id: Option<&'a str>,
+    pub action: &'a str,
+    pub resource: &'a str,
+    pub decision: AuditDecision,
+    /// Why the decision was made, e.g. an error code or the rule applied.
+    pub reason: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub time: u64,
+    pub key_id: Option<String>,
+    pub action: String,
+    pub resource: String,
+    pub decision: AuditDecision,
+    pub reason: String,
+    /// Chain hash over the previous entry's hash and this entry.
+    pub hash: [u8; SHA256_LEN],
+}
+
+impl AuditEntry {
+    fn chain(&self, key: Option<&SubKey>, previous: &[u8; SHA256_LEN]) -> [u8; SHA256_LEN] {
+        match key {
+            Some(key) => {
+                let mut mac = HmacSha256::new(key.as_bytes());
+                self.feed(previous, &mut |bytes| mac.update(bytes));
+                mac.finish()
+            }
+            None => {
+                let mut hasher = Sha256::new();
+                self.feed(previous, &mut |bytes| hasher.update(bytes));
+                hasher.finish()
+            }
+        }
+    }
+
+    fn feed(&self, previous: &[u8; SHA256_LEN], update: &mut dyn FnMut(&[u8])) {
+        update(previous);
+        update(&self.seq.to_le_bytes());
+        update(&self.time.to_le_bytes());
+        update(&[self.decision as u8, self.key_id.is_some() as u8]);
+        let fields = [
+            self.key_id.as_deref().unwrap_or(""),
+            &self.action,
+            &self.resource,
+            &self.reason,
+        ];
+        for field in fields {
+            // Length prefixes keep field boundaries unambiguous.
+            update(&(field.len() as u32).to_le_bytes());
+            update(field.as_bytes());
+        }
+    }
+}
+
+/// Bounded hash chain of audit entries. When full, the oldest entry is
+/// dropped and its hash becomes the anchor the rest are verified from.
+pub struct AuditLog {
+    entries: VecDeque<AuditEntry>,
+    capacity: usize,
+    /// Hash preceding the oldest retained entry.
+    base: [u8; SHA256_LEN],
+    next_seq: u64,
+    /// `Signing` subkey the chain is keyed under, if any.
+    key: Option<SubKey>,
+}
+
+impl AuditLog {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity: capacity.max(1),
+            base: [0; SHA256_LEN],
+            next_seq: 0,
+            key: None,
+        }
+    }
+
+    /// Keys the chain under `key`, the `Signing` subkey.
+    pub fn with_signing_key(self, key: SubKey) -> Self {
+        Self {
+            key: Some(key),
+            ..self
+        }
+    }
+
+    /// Appends a decision made at `time` and returns its sequence number.
+    pub fn record(&mut self, time: u64, record: AuditRecord<'_>) -> u64 {
+        if self.entries.len() == self.capacity {
+            if let Some(oldest) = self.entries.pop_front() {
+                self.base = oldest.hash;
+            }
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let mut entry = AuditEntry {
+            seq,
+            time,
+            key_id: record.key_id.map(ToString::to_string),
+            action: record.action.to_string(),
+            resource: record.resource.to_string(),
+            decision: record.decision,
+            reason: record.reason.to_string(),
+            hash: [0; SHA256_LEN],
+        };
+        entry.hash = entry.chain(self.key.as_ref(), &self.head());
+        self.entries.push_back(entry);
+        seq
+    }
+
+    /// Hash of the newest entry; keep a copy elsewhere to detect
+    /// truncation.
+    pub fn head(&self) -> [u8; SHA256_LEN] {
+        self.entries.back().map_or(self.base, |entry| entry.hash)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &AuditEntry> {
+        self.entries.iter()
+    }
+
+    /// Recomputes the chain over the retained entries, and checks it ends
+    /// at `head` when one is given.
+    pub fn verify(&self, head: Option<&[u8; SHA256_LEN]>) -> Result<(), AuditError> {
+        let mut previous = self.base;
+        let mut expected_seq = self.entries.front().map(|entry| entry.seq);
+        for entry in &self.entries {
+            if Some(entry.seq) != expected_seq {
+                return Err(AuditError::Removed);
+            }
+            if entry.chain(self.key.as_ref(), &previous) != entry.hash {
+                return Err(AuditError::Edited);
+            }
+            previous = entry.hash;
+            expected_seq = entry.seq.checked_add(1);
+        }
+        match head {
+            Some(head) if *head != previous => Err(AuditError::Truncated),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::with_capacity(4096)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(log: &mut AuditLog, time: u64, decision: AuditDecision) {
+        log.record(
+            time,
+            AuditRecord {
+                key_id: Some("key1"),
+                action: "s3:GetObject",
+                resource: "arn:aws:s3:::photos/cat.jpg",
+                decision,
+                reason: "KeyPermission",
+            },
+        );
+    }
+
+    #[test]
+    fn verify_detects_edits_removals_and_truncation() {
+        let mut log = AuditLog::default();
+        for time in 0..4 {
+            record(&mut log, time, AuditDecision::Allow);
+        }
+        let head = log.head();
+        assert_eq!(log.verify(Some(&head)), Ok(()));
+
+        let mut edited = log.entries.clone();
+        edited[1].decision = AuditDecision::Deny;
+        let tampered = |entries| AuditLog {
+            entries,
+            ..AuditLog::default()
+        };
+        assert_eq!(tampered(edited).verify(None), Err(AuditError::Edited));
+
+        let mut removed = log.entries.clone();
+        removed.remove(2);
+        assert_eq!(tampered(removed).verify(None), Err(AuditError::Removed));
+
+        let mut truncated = log.entries.clone();
+        truncated.pop_back();
+        assert_eq!(tampered(truncated.clone()).verify(None), Ok(()));
+        assert_eq!(
+            tampered(truncated).verify(Some(&head)),
+            Err(AuditError::Truncated)
+        );
+    }
+
+    #[test]
+    fn eviction_keeps_the_chain_verifiable() {
+        let mut log = AuditLog::with_capacity(2);
+        for time in 0..5 {
+            record(&mut log, time, AuditDecision::Deny);
+        }
+        assert_eq!(
+            log.entries()
+                .map(|entry| entry.seq)
+                .collect::<alloc::vec::Vec<_>>(),
+            [3, 4]
+        );
+        assert_eq!(log.verify(Some(&log.head())), Ok(()));
+    }
+
+    #[test]
+    fn signed_chain_needs_the_key() {
+        use crate::masterkey::{KeyPurpose, MasterKey};
+
+        let signed = |seed| {
+            let key = MasterKey::from_bytes([seed; 32]).derive(KeyPurpose::Signing);
+            AuditLog::default().with_signing_key(key)
+        };
+        let mut log = signed(1);
+        record(&mut log, 0, AuditDecision::Allow);
+        assert_eq!(log.verify(Some(&log.head())), Ok(()));
+
+        // An edit re-chained without the key, or under another key, fails.
+        let mut forged = AuditLog::default();
+        record(&mut forged, 0, AuditDecision::Deny);
+        let mut other = signed(2);
+        record(&mut other, 0, AuditDecision::Deny);
+        for entries in [forged.entries, other.entries] {
+            let tampered = AuditLog {
+                entries,
+                ..signed(1)
+            };
+            assert_eq!(tampered.verify(None), Err(AuditError::Edited));
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Primitives used to protect stored credentials and derive keys, from the
+//! shared `crypto` crate.
+
+pub use crypto::aead::{AeadError, ChaCha20Poly1305};
+pub use crypto::chacha20::NONCE_LEN;
+pub use crypto::ct::{constant_time_eq, zeroize};
+pub use crypto::hmac::{hmac_sha256, HmacSha256};
+pub use crypto::kdf::{hkdf_expand, hkdf_extract, pbkdf2_sha256};
+pub use crypto::poly1305::TAG_LEN;
+pub use crypto::sha256::{Sha256, SHA256_LEN};
@@ -2,111 +2,910 @@
 
 use alloc::borrow::ToOwned;
 use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
+use core::fmt;
 
-/// Represents a hashed API key entry stored in the keystore.
+use storage::block::BlockDevice;
+
+use crate::crypto::{
+    constant_time_eq, hkdf_expand, hmac_sha256, zeroize, ChaCha20Poly1305, HmacSha256, NONCE_LEN,
+    TAG_LEN,
+};
+use crate::masterkey::{KeyPurpose, MasterKey, SubKey, KEY_LEN};
+use crate::permissions::{Permission, Permissions, ANY_BUCKET};
+
+pub const SALT_LEN: usize = 16;
+
+/// Represents a hashed API key entry stored in the keystore. Header
+/// credentials are checked against `HMAC-SHA-256(salt, secret)`, which a
+/// peppered store keys once more under its pepper; the secret itself is
+/// only kept for keys that sign requests.
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct ApiKeyEntry<'a> {
     pub key_id: &'a str,
+    pub salt: [u8; SALT_LEN],
     pub hash: [u8; 32],
+    /// Bucket the key is scoped to, or `ANY_BUCKET`.
     pub bucket: &'a str,
-    pub permissions: u32,
+    pub permissions: Permissions,
+    /// Secret used to derive request-signing keys (SigV4), which cannot be
+    /// done from a hash. `None` for keys limited to header credentials.
+    pub signing_secret: Option<&'a str>,
+    pub status: KeyStatus,
+    /// Unix time from which the key is rejected.
+    pub expires_at: Option<u64>,
+    /// Secret replaced by the last rotation, while still in its grace
+    /// period.
+    pub retired: Option<RetiredSecret<'a>>,
+    /// Pepper of the store the entry was read from; `None` for entries
+    /// not yet stored, whose hashes are unpeppered.
+    pub pepper: Option<&'a [u8; KEY_LEN]>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyStatus {
+    #[default]
+    Active,
+    /// Rejected until enabled again.
+    Disabled,
+    /// Rejected for good; the entry is kept so callers learn why.
+    Revoked,
+}
+
+/// Credentials replaced by `KeyStore::rotate`, accepted until
+/// `valid_until` (Unix time, inclusive).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct RetiredSecret<'a> {
+    pub salt: [u8; SALT_LEN],
+    pub hash: [u8; 32],
+    pub signing_secret: Option<&'a str>,
+    pub valid_until: u64,
+}
+
+/// Stands in for secrets and hashes in `Debug` output.
+const REDACTED: &str = "<redacted>";
+
+impl fmt::Debug for ApiKeyEntry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiKeyEntry")
+            .field("key_id", &self.key_id)
+            .field("hash", &REDACTED)
+            .field("bucket", &self.bucket)
+            .field("permissions", &self.permissions)
+            .field("signing_secret", &self.signing_secret.map(|_| REDACTED))
+            .field("status", &self.status)
+            .field("expires_at", &self.expires_at)
+            .field("retired", &self.retired)
+            .finish()
+    }
+}
+
+impl fmt::Debug for RetiredSecret<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetiredSecret")
+            .field("hash", &REDACTED)
+            .field("signing_secret", &self.signing_secret.map(|_| REDACTED))
+            .field("valid_until", &self.valid_until)
+            .finish()
+    }
+}
+
+impl<'a> ApiKeyEntry<'a> {
+    /// Builds an entry for `secret`. `salt` should be random and unique to
+    /// the key.
+    pub fn with_secret(
+        key_id: &'a str,
+        secret: &str,
+        salt: [u8; SALT_LEN],
+        bucket: &'a str,
+        permissions: Permissions,
+    ) -> Self {
+        Self {
+            key_id,
+            salt,
+            hash: hash_secret(&salt, secret),
+            bucket,
+            permissions,
+            signing_secret: None,
+            status: KeyStatus::Active,
+            expires_at: None,
+            retired: None,
+            pepper: None,
+        }
+    }
+
+    /// Like `with_secret`, but also keeps `secret` so the key can sign
+    /// requests.
+    pub fn with_signing_secret(
+        key_id: &'a str,
+        secret: &'a str,
+        salt: [u8; SALT_LEN],
+        bucket: &'a str,
+        permissions: Permissions,
+    ) -> Self {
+        Self {
+            signing_secret: Some(secret),
+            ..Self::with_secret(key_id, secret, salt, bucket, permissions)
+        }
+    }
+
+    pub fn with_expiry(self, expires_at: u64) -> Self {
+        Self {
+            expires_at: Some(expires_at),
+            ..self
+        }
+    }
+
+    /// Checks `secret` in constant time against the current hash, or the
+    /// retired one while its grace period lasts at `now`.
+    pub fn verify(&self, secret: &str, now: u64) -> bool {
+        let hash = |salt: &[u8; SALT_LEN]| pepper_hash(self.pepper, hash_secret(salt, secret));
+        let current = constant_time_eq(&hash(&self.salt), &self.hash);
+        let retired = self.retired.is_some_and(|retired| {
+            now <= retired.valid_until && constant_time_eq(&hash(&retired.salt), &retired.hash)
+        });
+        current | retired
+    }
+
+    /// Signing secrets accepted at `now`: the current one, then the
+    /// retired one during its grace period.
+    pub fn signing_secrets(&self, now: u64) -> impl Iterator<Item = &'a str> {
+        let retired = self
+            .retired
+            .filter(|retired| now <= retired.valid_until)
+            .and_then(|retired| retired.signing_secret);
+        self.signing_secret.into_iter().chain(retired)
+    }
+
+    /// Whether the key has passed its expiry at `now`.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|at| now >= at)
+    }
+
+    /// Whether the key may perform `permission` on `bucket`. Service-wide
+    /// operations pass `None` and need an `ANY_BUCKET` scope.
+    pub fn authorizes(&self, bucket: Option<&str>, permission: Permission) -> bool {
+        let in_scope = self.bucket == ANY_BUCKET || bucket == Some(self.bucket);
+        in_scope && self.permissions.allows(permission)
+    }
+}
+
+pub fn hash_secret(salt: &[u8; SALT_LEN], secret: &str) -> [u8; 32] {
+    hmac_sha256(salt, secret.as_bytes())
+}
+
+/// Keys a salted hash under the store's pepper, so stored hashes cannot be
+/// brute-forced without the `ApiKeyHashing` subkey.
+fn pepper_hash(pepper: Option<&[u8; KEY_LEN]>, hash: [u8; 32]) -> [u8; 32] {
+    pepper.map_or(hash, |pepper| hmac_sha256(pepper, &hash))
 }
 
 pub trait KeyStore {
     fn insert(&mut self, entry: ApiKeyEntry<'_>) -> Result<(), KeyStoreError>;
     fn lookup(&self, key_id: &str) -> Result<ApiKeyEntry<'_>, KeyStoreError>;
+    /// Visits every key, revoked ones included.
+    fn keys(&self, visit: &mut dyn FnMut(ApiKeyEntry<'_>));
+    fn revoke(&mut self, key_id: &str) -> Result<(), KeyStoreError>;
+    /// Disables or re-enables a key. Revoked keys cannot be re-enabled.
+    fn set_enabled(&mut self, key_id: &str, enabled: bool) -> Result<(), KeyStoreError>;
+    fn set_expiry(&mut self, key_id: &str, expires_at: Option<u64>) -> Result<(), KeyStoreError>;
+    /// Replaces the key's secret, keeping the old one valid until
+    /// `grace_until`. Keys that sign requests sign with the new secret.
+    fn rotate(
+        &mut self,
+        key_id: &str,
+        secret: &str,
+        salt: [u8; SALT_LEN],
+        grace_until: u64,
+    ) -> Result<(), KeyStoreError>;
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyStoreError {
     NotFound,
+    /// A key with the same ID is already stored.
+    AlreadyExists,
     Storage,
+    /// Neither on-disk snapshot is readable.
+    Corrupt,
+    /// The entries no longer fit in a region slot.
+    Full,
+    /// The key was revoked and can no longer change.
+    Revoked,
 }
 
 #[derive(Default)]
 pub struct InMemoryKeyStore {
     entries: Vec<StoredEntry>,
+    pepper: Option<SubKey>,
 }
 
 #[derive(Clone)]
 struct StoredEntry {
     key_id: String,
+    salt: [u8; SALT_LEN],
     hash: [u8; 32],
     bucket: String,
-    permissions: u32,
+    permissions: Permissions,
+    signing_secret: Option<String>,
+    status: KeyStatus,
+    expires_at: Option<u64>,
+    retired: Option<StoredRetired>,
+}
+
+#[derive(Clone)]
+struct StoredRetired {
+    salt: [u8; SALT_LEN],
+    hash: [u8; 32],
+    signing_secret: Option<String>,
+    valid_until: u64,
+}
+
+impl Drop for StoredEntry {
+    fn drop(&mut self) {
+        wipe(&mut self.signing_secret);
+    }
+}
+
+impl Drop for StoredRetired {
+    fn drop(&mut self) {
+        wipe(&mut self.signing_secret);
+    }
+}
+
+fn wipe(secret: &mut Option<String>) {
+    if let Some(secret) = secret.take() {
+        let mut bytes = secret.into_bytes();
+        zeroize(&mut bytes);
+    }
+}
+
+impl StoredEntry {
+    fn view<'a>(&'a self, pepper: Option<&'a [u8; KEY_LEN]>) -> ApiKeyEntry<'a> {
+        ApiKeyEntry {
+            key_id: &self.key_id,
+            salt: self.salt,
+            hash: self.hash,
+            bucket: &self.bucket,
+            permissions: self.permissions,
+            signing_secret: self.signing_secret.as_deref(),
+            status: self.status,
+            expires_at: self.expires_at,
+            retired: self.retired.as_ref().map(|retired| RetiredSecret {
+                salt: retired.salt,
+                hash: retired.hash,
+                signing_secret: retired.signing_secret.as_deref(),
+                valid_until: retired.valid_until,
+            }),
+            pepper,
+        }
+    }
 }
 
 impl InMemoryKeyStore {
     pub fn new() -> Self {
         Self {
             entries: Vec::new(),
+            pepper: None,
+        }
+    }
+
+    /// Peppers stored hashes with the `ApiKeyHashing` subkey of `master`.
+    pub fn with_master_key(self, master: &MasterKey) -> Self {
+        Self {
+            pepper: Some(master.derive(KeyPurpose::ApiKeyHashing)),
+            ..self
+        }
+    }
+
+    fn pepper(&self) -> Option<&[u8; KEY_LEN]> {
+        self.pepper.as_ref().map(SubKey::as_bytes)
+    }
+
+    /// Entry for `key_id`, refusing revoked keys.
+    fn live_entry(&mut self, key_id: &str) -> Result<&mut StoredEntry, KeyStoreError> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|e| e.key_id == key_id)
+            .ok_or(KeyStoreError::NotFound)?;
+        if entry.status == KeyStatus::Revoked {
+            return Err(KeyStoreError::Revoked);
         }
+        Ok(entry)
     }
 }
 
 impl KeyStore for InMemoryKeyStore {
     fn insert(&mut self, entry: ApiKeyEntry<'_>) -> Result<(), KeyStoreError> {
         if self.entries.iter().any(|e| e.key_id == entry.key_id) {
-            return Err(KeyStoreError::Storage);
+            return Err(KeyStoreError::AlreadyExists);
         }
+        // Entries read from a store already carry that store's pepper.
+        let pepper = |hash| match entry.pepper {
+            Some(_) => hash,
+            None => pepper_hash(self.pepper(), hash),
+        };
         self.entries.push(StoredEntry {
             key_id: entry.key_id.to_owned(),
-            hash: entry.hash,
+            salt: entry.salt,
+            hash: pepper(entry.hash),
             bucket: entry.bucket.to_owned(),
             permissions: entry.permissions,
+            signing_secret: entry.signing_secret.map(ToOwned::to_owned),
+            status: entry.status,
+            expires_at: entry.expires_at,
+            retired: entry.retired.map(|retired| StoredRetired {
+                salt: retired.salt,
+                hash: pepper(retired.hash),
+                signing_secret: retired.signing_secret.map(ToOwned::to_owned),
+                valid_until: retired.valid_until,
+            }),
         });
         Ok(())
     }
 
     fn lookup(&self, key_id: &str) -> Result<ApiKeyEntry<'_>, KeyStoreError> {
-        let entry = self.entries.iter().find(|e| e.key_id == key_id);
-        match entry {
-            Some(stored) => Ok(ApiKeyEntry {
-                key_id: &stored.key_id,
-                hash: stored.hash,
-                bucket: &stored.bucket,
-                permissions: stored.permissions,
-            }),
-            None => Err(KeyStoreError::NotFound),
+        self.entries
+            .iter()
+            .find(|e| e.key_id == key_id)
+            .map(|e| e.view(self.pepper()))
+            .ok_or(KeyStoreError::NotFound)
+    }
+
+    fn keys(&self, visit: &mut dyn FnMut(ApiKeyEntry<'_>)) {
+        for entry in &self.entries {
+            visit(entry.view(self.pepper()));
+        }
+    }
+
+    fn revoke(&mut self, key_id: &str) -> Result<(), KeyStoreError> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|e| e.key_id == key_id)
+            .ok_or(KeyStoreError::NotFound)?;
+        entry.status = KeyStatus::Revoked;
+        entry.signing_secret = None;
+        entry.retired = None;
+        Ok(())
+    }
+
+    fn set_enabled(&mut self, key_id: &str, enabled: bool) -> Result<(), KeyStoreError> {
+        self.live_entry(key_id)?.status = if enabled {
+            KeyStatus::Active
+        } else {
+            KeyStatus::Disabled
+        };
+        Ok(())
+    }
+
+    fn set_expiry(&mut self, key_id: &str, expires_at: Option<u64>) -> Result<(), KeyStoreError> {
+        self.live_entry(key_id)?.expires_at = expires_at;
+        Ok(())
+    }
+
+    fn rotate(
+        &mut self,
+        key_id: &str,
+        secret: &str,
+        salt: [u8; SALT_LEN],
+        grace_until: u64,
+    ) -> Result<(), KeyStoreError> {
+        let hash = pepper_hash(self.pepper(), hash_secret(&salt, secret));
+        let entry = self.live_entry(key_id)?;
+        let signing_secret = entry.signing_secret.as_ref().map(|_| secret.to_owned());
+        entry.retired = Some(StoredRetired {
+            salt: entry.salt,
+            hash: entry.hash,
+            signing_secret: core::mem::replace(&mut entry.signing_secret, signing_secret),
+            valid_until: grace_until,
+        });
+        entry.salt = salt;
+        entry.hash = hash;
+        Ok(())
+    }
+}
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"RCKEYS04";
+/// Magic, generation, payload length and nonce.
+const SNAPSHOT_HEADER_LEN: usize = 20 + NONCE_LEN;
+
+/// Location of a `BlockKeyStore` on its device. The region is two slots
+/// of `slot_blocks` blocks each, written alternately so a torn write
+/// leaves the previous snapshot readable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyStoreRegion {
+    pub block_size: usize,
+    pub base_lba: u64,
+    pub slot_blocks: u64,
+}
+
+impl KeyStoreRegion {
+    pub const fn new(block_size: usize, slot_blocks: u64) -> Self {
+        Self {
+            block_size,
+            base_lba: 0,
+            slot_blocks,
+        }
+    }
+
+    fn slot_len(&self) -> usize {
+        self.block_size * self.slot_blocks as usize
+    }
+
+    fn slot_lba(&self, slot: u64) -> u64 {
+        self.base_lba + slot * self.slot_blocks
+    }
+}
+
+/// Keystore persisted to a region of a block device. Entries are held in
+/// memory and every insert rewrites the full snapshot, sealed under the
+/// `DataEncryption` subkey since it carries signing secrets.
+pub struct BlockKeyStore<D: BlockDevice> {
+    device: D,
+    region: KeyStoreRegion,
+    generation: u64,
+    entries: InMemoryKeyStore,
+    seal: SnapshotSeal,
+}
+
+/// Seals snapshots with ChaCha20-Poly1305. There is no entropy source and
+/// generations restart on `format`, so the nonce is synthetic: an HMAC of
+/// the snapshot under a separate key. Only identical snapshots share one.
+struct SnapshotSeal {
+    cipher: ChaCha20Poly1305,
+    nonce_key: [u8; KEY_LEN],
+}
+
+impl SnapshotSeal {
+    fn new(master: &MasterKey) -> Self {
+        let key = master.derive(KeyPurpose::DataEncryption);
+        let mut okm = [0u8; 2 * KEY_LEN];
+        hkdf_expand(key.as_bytes(), b"rustcore/v1/keystore-snapshot", &mut okm);
+        let (cipher_key, nonce_key) = okm.split_at(KEY_LEN);
+        let seal = Self {
+            cipher: ChaCha20Poly1305::new(cipher_key.try_into().unwrap()),
+            nonce_key: nonce_key.try_into().unwrap(),
+        };
+        zeroize(&mut okm);
+        seal
+    }
+}
+
+impl Drop for SnapshotSeal {
+    fn drop(&mut self) {
+        zeroize(&mut self.nonce_key);
+    }
+}
+
+impl<D: BlockDevice> BlockKeyStore<D> {
+    /// Initialises an empty keystore, discarding anything in the region.
+    /// Hashes are peppered and snapshots sealed with subkeys of `master`.
+    pub fn format(
+        mut device: D,
+        region: KeyStoreRegion,
+        master: &MasterKey,
+    ) -> Result<Self, KeyStoreError> {
+        if region.block_size == 0 || region.slot_len() < SNAPSHOT_HEADER_LEN + TAG_LEN {
+            return Err(KeyStoreError::Full);
+        }
+        let blank = vec![0u8; region.slot_len()];
+        for slot in 0..2 {
+            device
+                .write(region.slot_lba(slot), &blank)
+                .map_err(|_| KeyStoreError::Storage)?;
+        }
+        let mut store = Self {
+            device,
+            region,
+            generation: 0,
+            entries: InMemoryKeyStore::new().with_master_key(master),
+            seal: SnapshotSeal::new(master),
+        };
+        store.persist()?;
+        Ok(store)
+    }
+
+    /// Loads the newest intact snapshot in the region, written under the
+    /// same `master`. Snapshots sealed under another key read as corrupt.
+    pub fn open(
+        mut device: D,
+        region: KeyStoreRegion,
+        master: &MasterKey,
+    ) -> Result<Self, KeyStoreError> {
+        let seal = SnapshotSeal::new(master);
+        let mut newest: Option<(u64, Vec<StoredEntry>)> = None;
+        let mut buffer = vec![0u8; region.slot_len()];
+        for slot in 0..2 {
+            device
+                .read(region.slot_lba(slot), &mut buffer)
+                .map_err(|_| KeyStoreError::Storage)?;
+            if let Some((generation, entries)) = decode_snapshot(&seal, &buffer) {
+                if newest.as_ref().is_none_or(|(best, _)| generation > *best) {
+                    newest = Some((generation, entries));
+                }
+            }
+        }
+        let (generation, entries) = newest.ok_or(KeyStoreError::Corrupt)?;
+        Ok(Self {
+            device,
+            region,
+            generation,
+            entries: InMemoryKeyStore {
+                entries,
+                pepper: Some(master.derive(KeyPurpose::ApiKeyHashing)),
+            },
+            seal,
+        })
+    }
+
+    pub fn into_device(self) -> D {
+        self.device
+    }
+
+    /// Applies `change` and persists the result, undoing it in memory if
+    /// either step fails.
+    fn update(
+        &mut self,
+        change: impl FnOnce(&mut InMemoryKeyStore) -> Result<(), KeyStoreError>,
+    ) -> Result<(), KeyStoreError> {
+        let before = self.entries.entries.clone();
+        let result = change(&mut self.entries).and_then(|()| self.persist());
+        if result.is_err() {
+            self.entries.entries = before;
+        }
+        result
+    }
+
+    fn persist(&mut self) -> Result<(), KeyStoreError> {
+        let generation = self.generation + 1;
+        // Sealed, so the snapshot holds no plaintext secrets to wipe.
+        let snapshot = encode_snapshot(&self.seal, generation, &self.entries.entries);
+        if snapshot.len() > self.region.slot_len() {
+            return Err(KeyStoreError::Full);
         }
+        let mut buffer = vec![0u8; self.region.slot_len()];
+        buffer[..snapshot.len()].copy_from_slice(&snapshot);
+        let written = self
+            .device
+            .write(self.region.slot_lba(generation % 2), &buffer)
+            .and_then(|()| self.device.flush());
+        written.map_err(|_| KeyStoreError::Storage)?;
+        self.generation = generation;
+        Ok(())
+    }
+}
+
+impl<D: BlockDevice> KeyStore for BlockKeyStore<D> {
+    fn insert(&mut self, entry: ApiKeyEntry<'_>) -> Result<(), KeyStoreError> {
+        self.update(|entries| entries.insert(entry))
+    }
+
+    fn lookup(&self, key_id: &str) -> Result<ApiKeyEntry<'_>, KeyStoreError> {
+        self.entries.lookup(key_id)
+    }
+
+    fn keys(&self, visit: &mut dyn FnMut(ApiKeyEntry<'_>)) {
+        self.entries.keys(visit)
+    }
+
+    fn revoke(&mut self, key_id: &str) -> Result<(), KeyStoreError> {
+        self.update(|entries| entries.revoke(key_id))
+    }
+
+    fn set_enabled(&mut self, key_id: &str, enabled: bool) -> Result<(), KeyStoreError> {
+        self.update(|entries| entries.set_enabled(key_id, enabled))
+    }
+
+    fn set_expiry(&mut self, key_id: &str, expires_at: Option<u64>) -> Result<(), KeyStoreError> {
+        self.update(|entries| entries.set_expiry(key_id, expires_at))
+    }
+
+    fn rotate(
+        &mut self,
+        key_id: &str,
+        secret: &str,
+        salt: [u8; SALT_LEN],
+        grace_until: u64,
+    ) -> Result<(), KeyStoreError> {
+        self.update(|entries| entries.rotate(key_id, secret, salt, grace_until))
+    }
+}
+
+fn encode_snapshot(seal: &SnapshotSeal, generation: u64, entries: &[StoredEntry]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    for entry in entries {
+        put_str(&mut payload, &entry.key_id);
+        payload.extend_from_slice(&entry.salt);
+        payload.extend_from_slice(&entry.hash);
+        put_str(&mut payload, &entry.bucket);
+        payload.extend_from_slice(&entry.permissions.bits().to_le_bytes());
+        put_opt_str(&mut payload, entry.signing_secret.as_deref());
+        payload.push(match entry.status {
+            KeyStatus::Active => 0,
+            KeyStatus::Disabled => 1,
+            KeyStatus::Revoked => 2,
+        });
+        match entry.expires_at {
+            Some(at) => {
+                payload.push(1);
+                payload.extend_from_slice(&at.to_le_bytes());
+            }
+            None => payload.push(0),
+        }
+        match &entry.retired {
+            Some(retired) => {
+                payload.push(1);
+                payload.extend_from_slice(&retired.salt);
+                payload.extend_from_slice(&retired.hash);
+                put_opt_str(&mut payload, retired.signing_secret.as_deref());
+                payload.extend_from_slice(&retired.valid_until.to_le_bytes());
+            }
+            None => payload.push(0),
+        }
+    }
+    let mut out = Vec::with_capacity(SNAPSHOT_HEADER_LEN + payload.len() + TAG_LEN);
+    out.extend_from_slice(SNAPSHOT_MAGIC);
+    out.extend_from_slice(&generation.to_le_bytes());
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    let mut nonce = [0u8; NONCE_LEN];
+    let mut mac = HmacSha256::new(&seal.nonce_key);
+    mac.update(&out);
+    mac.update(&payload);
+    nonce.copy_from_slice(&mac.finish()[..NONCE_LEN]);
+    out.extend_from_slice(&nonce);
+    // The header, nonce included, is authenticated but not encrypted.
+    let tag = seal.cipher.encrypt_in_place(&nonce, &out, &mut payload);
+    out.extend_from_slice(&payload);
+    out.extend_from_slice(&tag);
+    out
+}
+
+fn decode_snapshot(seal: &SnapshotSeal, bytes: &[u8]) -> Option<(u64, Vec<StoredEntry>)> {
+    if bytes.get(..8)? != SNAPSHOT_MAGIC {
+        return None;
+    }
+    let generation = u64::from_le_bytes(bytes.get(8..16)?.try_into().ok()?);
+    let len = u32::from_le_bytes(bytes.get(16..20)?.try_into().ok()?) as usize;
+    let nonce: &[u8; NONCE_LEN] = bytes.get(20..SNAPSHOT_HEADER_LEN)?.try_into().ok()?;
+    let end = SNAPSHOT_HEADER_LEN.checked_add(len)?;
+    let tag: &[u8; TAG_LEN] = bytes.get(end..end.checked_add(TAG_LEN)?)?.try_into().ok()?;
+    let mut payload = bytes[SNAPSHOT_HEADER_LEN..end].to_vec();
+    seal.cipher
+        .decrypt_in_place(nonce, &bytes[..SNAPSHOT_HEADER_LEN], &mut payload, tag)
+        .ok()?;
+    let entries = decode_entries(&payload);
+    zeroize(&mut payload);
+    Some((generation, entries?))
+}
+
+fn decode_entries(payload: &[u8]) -> Option<Vec<StoredEntry>> {
+    let mut reader = Reader(payload);
+    let count = u32::from_le_bytes(reader.take(4)?.try_into().ok()?);
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        entries.push(StoredEntry {
+            key_id: reader.string()?,
+            salt: reader.take(SALT_LEN)?.try_into().ok()?,
+            hash: reader.take(32)?.try_into().ok()?,
+            bucket: reader.string()?,
+            permissions: Permissions::from_bits(u32::from_le_bytes(
+                reader.take(4)?.try_into().ok()?,
+            )),
+            signing_secret: reader.opt_string()?,
+            status: match reader.byte()? {
+                0 => KeyStatus::Active,
+                1 => KeyStatus::Disabled,
+                2 => KeyStatus::Revoked,
+                _ => return None,
+            },
+            expires_at: match reader.byte()? {
+                0 => None,
+                1 => Some(reader.u64()?),
+                _ => return None,
+            },
+            retired: match reader.byte()? {
+                0 => None,
+                1 => Some(StoredRetired {
+                    salt: reader.take(SALT_LEN)?.try_into().ok()?,
+                    hash: reader.take(32)?.try_into().ok()?,
+                    signing_secret: reader.opt_string()?,
+                    valid_until: reader.u64()?,
+                }),
+                _ => return None,
+            },
+        });
+    }
+    Some(entries)
+}
+
+fn put_str(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn put_opt_str(out: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            out.push(1);
+            put_str(out, value);
+        }
+        None => out.push(0),
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    /// Flag byte, then a string when the flag is 1. `Some(None)` when the
+    /// flag is 0; `None` on malformed input.
+    fn opt_string(&mut self) -> Option<Option<String>> {
+        match self.byte()? {
+            0 => Some(None),
+            1 => Some(Some(self.string()?)),
+            _ => None,
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = u16::from_le_bytes(self.take(2)?.try_into().ok()?) as usize;
+        core::str::from_utf8(self.take(len)?)
+            .ok()
+            .map(ToOwned::to_owned)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{ApiKeyEntry, InMemoryKeyStore, KeyStore, KeyStoreError};
+    use super::*;
+    use storage::block::MemoryBlockDevice;
 
     #[test]
     fn insert_and_lookup() {
         let mut store = InMemoryKeyStore::new();
-        let entry = ApiKeyEntry {
-            key_id: "test",
-            hash: [1; 32],
-            bucket: "photos",
-            permissions: 0xFF,
-        };
+        let entry =
+            ApiKeyEntry::with_secret("test", "s3cret", [1; SALT_LEN], "photos", Permissions::ALL);
         store.insert(entry).unwrap();
         let fetched = store.lookup("test").unwrap();
         assert_eq!(fetched.bucket, "photos");
-        assert_eq!(fetched.permissions, 0xFF);
+        assert_eq!(fetched.permissions, Permissions::ALL);
+        assert!(fetched.verify("s3cret", 0));
+        assert!(!fetched.verify("s3cre", 0));
+        // The same secret under another salt hashes differently.
+        let other =
+            ApiKeyEntry::with_secret("test", "s3cret", [2; SALT_LEN], "photos", Permissions::ALL);
+        assert_ne!(other.hash, fetched.hash);
+
+        let signer =
+            ApiKeyEntry::with_signing_secret("s", "hunter2", [3; SALT_LEN], "b", Permissions::ALL);
+        let shown = alloc::format!("{:?}", signer);
+        assert!(!shown.contains("hunter2") && shown.contains("<redacted>"));
+
+        // A peppered store keeps hashes that only verify with its pepper.
+        let mut peppered =
+            InMemoryKeyStore::new().with_master_key(&MasterKey::from_bytes([9; KEY_LEN]));
+        peppered.insert(entry).unwrap();
+        let stored = peppered.lookup("test").unwrap();
+        assert_ne!(stored.hash, entry.hash);
+        assert!(stored.verify("s3cret", 0) && !stored.verify("s3cre", 0));
+        assert!(!ApiKeyEntry {
+            pepper: None,
+            ..stored
+        }
+        .verify("s3cret", 0));
+        peppered.rotate("test", "fresh", [4; SALT_LEN], 10).unwrap();
+        let rotated = peppered.lookup("test").unwrap();
+        assert!(rotated.verify("fresh", 0) && rotated.verify("s3cret", 10));
     }
 
     #[test]
     fn duplicate_insert_fails() {
         let mut store = InMemoryKeyStore::new();
-        let entry = ApiKeyEntry {
-            key_id: "dup",
-            hash: [0; 32],
-            bucket: "bucket",
-            permissions: 1,
-        };
+        let entry = ApiKeyEntry::with_secret("dup", "x", [0; SALT_LEN], "bucket", Permissions::ALL);
         store.insert(entry).unwrap();
-        assert_eq!(store.insert(entry), Err(KeyStoreError::Storage));
+        assert_eq!(store.insert(entry), Err(KeyStoreError::AlreadyExists));
+        assert_eq!(store.lookup("missing"), Err(KeyStoreError::NotFound));
     }
 
     #[test]
-    fn lookup_missing_key() {
-        let store = InMemoryKeyStore::new();
-        assert_eq!(store.lookup("missing"), Err(KeyStoreError::NotFound));
+    fn block_store_survives_reopen_and_torn_write() {
+        let mut disk = vec![0u8; 8 * 512];
+        let region = KeyStoreRegion::new(512, 4);
+        let master = MasterKey::from_bytes([9; KEY_LEN]);
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let mut store = BlockKeyStore::format(device, region, &master).unwrap();
+        store
+            .insert(ApiKeyEntry::with_signing_secret(
+                "a",
+                "one",
+                [1; SALT_LEN],
+                "photos",
+                Permissions::of(&[Permission::Read, Permission::List]),
+            ))
+            .unwrap();
+        store
+            .insert(ApiKeyEntry::with_secret(
+                "b",
+                "two",
+                [2; SALT_LEN],
+                "docs",
+                Permissions::ALL,
+            ))
+            .unwrap();
+        store.into_device();
+
+        // Corrupt the newest slot; the previous snapshot is still used.
+        disk[4 * 512 + 30] ^= 0xFF;
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let mut store = BlockKeyStore::open(device, region, &master).unwrap();
+        let a = store.lookup("a").unwrap();
+        assert!(a.verify("one", 0));
+        assert_eq!(a.signing_secret, Some("one"));
+        assert!(a.authorizes(Some("photos"), Permission::List));
+        assert!(!a.authorizes(Some("photos"), Permission::Write));
+        assert!(!a.authorizes(Some("docs"), Permission::Read));
+        assert_eq!(store.lookup("b"), Err(KeyStoreError::NotFound));
+
+        // Lifecycle changes are persisted too.
+        store.rotate("a", "uno", [3; SALT_LEN], 50).unwrap();
+        store.set_enabled("a", false).unwrap();
+        store.set_expiry("a", Some(100)).unwrap();
+        store.into_device();
+        // Snapshots are sealed: no secret is readable on disk, and another
+        // master key cannot open them.
+        assert!(!disk.windows(3).any(|w| w == b"uno" || w == b"one"));
+        let other = MasterKey::from_bytes([8; KEY_LEN]);
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        assert!(matches!(
+            BlockKeyStore::open(device, region, &other),
+            Err(KeyStoreError::Corrupt)
+        ));
+        let device = MemoryBlockDevice::new(512, &mut disk).unwrap();
+        let store = BlockKeyStore::open(device, region, &master).unwrap();
+        let a = store.lookup("a").unwrap();
+        assert_eq!(a.status, KeyStatus::Disabled);
+        assert_eq!(a.expires_at, Some(100));
+        assert!(a.verify("uno", 60) && a.verify("one", 50) && !a.verify("one", 51));
+        assert_eq!(a.signing_secrets(0).collect::<Vec<_>>(), ["uno", "one"]);
+
+        let mut tiny = vec![0u8; 2 * 512];
+        let device = MemoryBlockDevice::new(512, &mut tiny).unwrap();
+        let mut store =
+            BlockKeyStore::format(device, KeyStoreRegion::new(512, 1), &master).unwrap();
+        let long = "k".repeat(400);
+        assert_eq!(
+            store.insert(ApiKeyEntry::with_secret(
+                &long,
+                "x",
+                [0; SALT_LEN],
+                "photos",
+                Permissions::ALL
+            )),
+            Err(KeyStoreError::Full)
+        );
+        assert_eq!(
+            store.lookup(&long).map(|e| e.key_id),
+            Err(KeyStoreError::NotFound)
+        );
     }
 }
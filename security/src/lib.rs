@@ -3,4 +3,11 @@
 extern crate alloc;
 
 pub mod apikey;
+pub mod audit;
+pub mod crypto;
+mod json;
 pub mod keystore;
+pub mod masterkey;
+pub mod permissions;
+pub mod policy;
+pub mod ratelimit;
@@ -0,0 +1,282 @@
+#![allow(dead_code)]
+
+//! Master key and the subkeys derived from it.
+//!
+//! The master key is only stored sealed under a key-encryption key (KEK)
+//! taken from a boot-provided secret or a passphrase. Subkeys for each
+//! purpose are derived with HKDF, so none of them reveals the master or
+//! another subkey. Every key type zeroizes itself on drop and prints as
+//! redacted in `Debug` output.
+
+use core::fmt;
+
+use crate::crypto::{
+    constant_time_eq, hkdf_expand, hkdf_extract, hmac_sha256, pbkdf2_sha256, zeroize, SHA256_LEN,
+};
+
+pub const KEY_LEN: usize = 32;
+const SEALED_MAGIC: &[u8; 8] = b"RCMKEY01";
+/// Magic, synthetic IV and ciphertext.
+pub const SEALED_LEN: usize = SEALED_MAGIC.len() + SHA256_LEN + KEY_LEN;
+/// PBKDF2 iterations used unless the caller picks a count.
+pub const DEFAULT_PASSPHRASE_ITERATIONS: u32 = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SealError {
+    /// Wrong length or magic.
+    Malformed,
+    /// Wrong KEK, or the sealed key was altered.
+    Unauthenticated,
+}
+
+/// What a subkey is for; each purpose yields an independent key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyPurpose {
+    /// Pepper for API key secret hashes.
+    ApiKeyHashing,
+    /// Encryption of stored data.
+    DataEncryption,
+    /// Signing tokens and records, e.g. audit log anchors.
+    Signing,
+}
+
+impl KeyPurpose {
+    fn info(self) -> &'static [u8] {
+        match self {
+            KeyPurpose::ApiKeyHashing => b"rustcore/v1/api-key-hashing",
+            KeyPurpose::DataEncryption => b"rustcore/v1/data-encryption",
+            KeyPurpose::Signing => b"rustcore/v1/signing",
+        }
+    }
+}
+
+/// Root of the key hierarchy. Never stored unsealed.
+pub struct MasterKey([u8; KEY_LEN]);
+
+impl MasterKey {
+    /// Wraps fresh key material. There is no entropy source in this crate,
+    /// so the caller supplies it, e.g. from the platform RNG at first boot.
+    pub fn from_bytes(bytes: [u8; KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn derive(&self, purpose: KeyPurpose) -> SubKey {
+        let mut prk = hkdf_extract(b"rustcore/v1/master", &self.0);
+        let mut key = [0u8; KEY_LEN];
+        hkdf_expand(&prk, purpose.info(), &mut key);
+        zeroize(&mut prk);
+        SubKey { purpose, key }
+    }
+
+    /// Seals the key under `kek` with a deterministic SIV construction:
+    /// the IV is an HMAC of the key, so no nonce is needed and a wrong KEK
+    /// or altered ciphertext fails to unseal.
+    pub fn seal(&self, kek: &SealingKey) -> SealedMasterKey {
+        let (mut enc_key, mut mac_key) = kek.split();
+        let siv = siv(&mac_key, &self.0);
+        let mut stream = hmac_sha256(&enc_key, &siv);
+        let mut sealed = [0u8; SEALED_LEN];
+        sealed[..8].copy_from_slice(SEALED_MAGIC);
+        sealed[8..8 + SHA256_LEN].copy_from_slice(&siv);
+        for (out, (key, pad)) in sealed[8 + SHA256_LEN..]
+            .iter_mut()
+            .zip(self.0.iter().zip(&stream))
+        {
+            *out = key ^ pad;
+        }
+        zeroize(&mut stream);
+        zeroize(&mut enc_key);
+        zeroize(&mut mac_key);
+        SealedMasterKey(sealed)
+    }
+}
+
+impl Drop for MasterKey {
+    fn drop(&mut self) {
+        zeroize(&mut self.0);
+    }
+}
+
+impl fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MasterKey(<redacted>)")
+    }
+}
+
+fn siv(mac_key: &[u8; SHA256_LEN], key: &[u8; KEY_LEN]) -> [u8; SHA256_LEN] {
+    let mut message = [0u8; SEALED_MAGIC.len() + KEY_LEN];
+    message[..8].copy_from_slice(SEALED_MAGIC);
+    message[8..].copy_from_slice(key);
+    let iv = hmac_sha256(mac_key, &message);
+    zeroize(&mut message);
+    iv
+}
+
+/// Key-encryption key that seals the master key.
+pub struct SealingKey([u8; KEY_LEN]);
+
+impl SealingKey {
+    /// KEK from a high-entropy secret handed over by the boot loader.
+    pub fn from_boot_secret(secret: &[u8]) -> Self {
+        Self(hkdf_extract(b"rustcore/v1/seal", secret))
+    }
+
+    /// KEK from an operator passphrase. `salt` should be unique per
+    /// installation and stored next to the sealed key.
+    pub fn from_passphrase(passphrase: &str, salt: &[u8], iterations: u32) -> Self {
+        let mut key = [0u8; KEY_LEN];
+        pbkdf2_sha256(passphrase.as_bytes(), salt, iterations, &mut key);
+        Self(key)
+    }
+
+    /// Independent encryption and authentication keys.
+    fn split(&self) -> ([u8; KEY_LEN], [u8; KEY_LEN]) {
+        let (mut enc, mut mac) = ([0u8; KEY_LEN], [0u8; KEY_LEN]);
+        hkdf_expand(&self.0, b"rustcore/v1/seal/enc", &mut enc);
+        hkdf_expand(&self.0, b"rustcore/v1/seal/mac", &mut mac);
+        (enc, mac)
+    }
+}
+
+impl Drop for SealingKey {
+    fn drop(&mut self) {
+        zeroize(&mut self.0);
+    }
+}
+
+impl fmt::Debug for SealingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SealingKey(<redacted>)")
+    }
+}
+
+/// Master key as stored at rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SealedMasterKey([u8; SEALED_LEN]);
+
+impl SealedMasterKey {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SealError> {
+        let sealed: [u8; SEALED_LEN] = bytes.try_into().map_err(|_| SealError::Malformed)?;
+        if &sealed[..8] != SEALED_MAGIC {
+            return Err(SealError::Malformed);
+        }
+        Ok(Self(sealed))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; SEALED_LEN] {
+        &self.0
+    }
+
+    pub fn unseal(&self, kek: &SealingKey) -> Result<MasterKey, SealError> {
+        let (mut enc_key, mut mac_key) = kek.split();
+        let iv = &self.0[8..8 + SHA256_LEN];
+        let mut stream = hmac_sha256(&enc_key, iv);
+        let mut key = MasterKey([0; KEY_LEN]);
+        for (out, (sealed, pad)) in key
+            .0
+            .iter_mut()
+            .zip(self.0[8 + SHA256_LEN..].iter().zip(&stream))
+        {
+            *out = sealed ^ pad;
+        }
+        let valid = constant_time_eq(&siv(&mac_key, &key.0), iv);
+        zeroize(&mut stream);
+        zeroize(&mut enc_key);
+        zeroize(&mut mac_key);
+        // `key` zeroizes itself when dropped on failure.
+        valid.then_some(key).ok_or(SealError::Unauthenticated)
+    }
+}
+
+/// Key derived from the master key for one purpose.
+pub struct SubKey {
+    purpose: KeyPurpose,
+    key: [u8; KEY_LEN],
+}
+
+impl SubKey {
+    pub fn purpose(&self) -> KeyPurpose {
+        self.purpose
+    }
+
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        &self.key
+    }
+}
+
+impl Drop for SubKey {
+    fn drop(&mut self) {
+        zeroize(&mut self.key);
+    }
+}
+
+impl fmt::Debug for SubKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubKey")
+            .field("purpose", &self.purpose)
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    #[test]
+    fn seal_round_trip_and_tamper_detection() {
+        let master = MasterKey::from_bytes([0x42; KEY_LEN]);
+        let kek = SealingKey::from_boot_secret(b"boot-provided secret");
+        let sealed = master.seal(&kek);
+        assert!(!sealed
+            .as_bytes()
+            .windows(KEY_LEN)
+            .any(|w| w == [0x42; KEY_LEN]));
+
+        let stored = SealedMasterKey::from_bytes(sealed.as_bytes()).unwrap();
+        let unsealed = stored.unseal(&kek).unwrap();
+        assert_eq!(
+            unsealed.derive(KeyPurpose::Signing).as_bytes(),
+            master.derive(KeyPurpose::Signing).as_bytes()
+        );
+
+        let wrong = SealingKey::from_passphrase("hunter2", b"install-salt", 2);
+        assert_eq!(
+            stored.unseal(&wrong).map(|_| ()),
+            Err(SealError::Unauthenticated)
+        );
+        let mut altered = *sealed.as_bytes();
+        altered[SEALED_LEN - 1] ^= 1;
+        let altered = SealedMasterKey::from_bytes(&altered).unwrap();
+        assert_eq!(
+            altered.unseal(&kek).map(|_| ()),
+            Err(SealError::Unauthenticated)
+        );
+        assert_eq!(
+            SealedMasterKey::from_bytes(&[0; 8]),
+            Err(SealError::Malformed)
+        );
+    }
+
+    #[test]
+    fn subkeys_are_independent_and_redacted() {
+        let master = MasterKey::from_bytes([7; KEY_LEN]);
+        let hashing = master.derive(KeyPurpose::ApiKeyHashing);
+        let data = master.derive(KeyPurpose::DataEncryption);
+        assert_ne!(hashing.as_bytes(), data.as_bytes());
+        assert_ne!(hashing.as_bytes(), &[7; KEY_LEN]);
+
+        let shown = format!(
+            "{:?} {:?} {:?}",
+            master,
+            hashing,
+            SealingKey::from_boot_secret(b"s")
+        );
+        assert!(!shown.contains('7'), "{}", shown);
+        assert_eq!(
+            format!("{:?}", data),
+            "SubKey { purpose: DataEncryption, key: \"<redacted>\" }"
+        );
+    }
+}
@@ -0,0 +1,108 @@
+#![allow(dead_code)]
+
+//! Typed permissions carried by API keys.
+
+/// Bucket scope granting access to every bucket.
+pub const ANY_BUCKET: &str = "*";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Fetch objects and their attributes.
+    Read,
+    /// Create or overwrite objects and their tags.
+    Write,
+    /// List keys in a bucket, or the buckets themselves.
+    List,
+    /// Remove objects and their tags.
+    Delete,
+    /// Create and delete buckets and use administrative routes. Implies
+    /// every other permission.
+    Admin,
+    /// Initiate, upload parts of, complete and abort multipart uploads.
+    Multipart,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 6] = [
+        Permission::Read,
+        Permission::Write,
+        Permission::List,
+        Permission::Delete,
+        Permission::Admin,
+        Permission::Multipart,
+    ];
+
+    pub const fn bit(self) -> u32 {
+        1 << self as u32
+    }
+
+    /// Lower-case name used by the admin API.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Permission::Read => "read",
+            Permission::Write => "write",
+            Permission::List => "list",
+            Permission::Delete => "delete",
+            Permission::Admin => "admin",
+            Permission::Multipart => "multipart",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.as_str() == name)
+    }
+}
+
+/// Set of permissions, stored as a bitmask of `Permission::bit` values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Permissions(u32);
+
+impl Permissions {
+    pub const NONE: Self = Self(0);
+    pub const ALL: Self = Self(0x3F);
+
+    /// Unknown bits are dropped.
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits & Self::ALL.0)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub const fn with(self, permission: Permission) -> Self {
+        Self(self.0 | permission.bit())
+    }
+
+    pub fn of(permissions: &[Permission]) -> Self {
+        permissions
+            .iter()
+            .fold(Self::NONE, |set, permission| set.with(*permission))
+    }
+
+    pub const fn contains(self, permission: Permission) -> bool {
+        self.0 & permission.bit() != 0
+    }
+
+    /// Whether the set grants `permission`, directly or through `Admin`.
+    pub const fn allows(self, permission: Permission) -> bool {
+        self.contains(permission) || self.contains(Permission::Admin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admin_implies_everything() {
+        let reader = Permissions::of(&[Permission::Read, Permission::List]);
+        assert!(reader.allows(Permission::List));
+        assert!(!reader.allows(Permission::Write));
+        assert_eq!(Permissions::from_bits(reader.bits() | 0x100), reader);
+
+        let admin = Permissions::NONE.with(Permission::Admin);
+        assert!(admin.allows(Permission::Delete));
+        assert!(!admin.contains(Permission::Delete));
+    }
+}
@@ -0,0 +1,506 @@
+#![allow(dead_code)]
+
+//! Bucket policies: a subset of the S3 policy language and its evaluator.
+//!
+//! Supported statement fields are `Sid`, `Effect`, `Principal`, `Action`,
+//! `Resource` and `Condition`. Principals are API key IDs, either `"*"` or
+//! under `"AWS"`. Conditions support `StringEquals`/`StringLike` on
+//! `s3:prefix` and `IpAddress`/`NotIpAddress` on `aws:SourceIp`. Anything
+//! else is rejected rather than ignored, so a policy never means less than
+//! it says.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::json::{self, Value};
+
+/// Prefix of every S3 resource ARN.
+pub const RESOURCE_ARN_PREFIX: &str = "arn:aws:s3:::";
+/// Longest policy document accepted, as AWS limits bucket policies.
+pub const MAX_POLICY_LEN: usize = 20 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyError {
+    /// Not well-formed JSON, or too long.
+    Syntax,
+    /// A required field is absent.
+    MissingField,
+    /// A field has the wrong type or an unparseable value.
+    InvalidValue,
+    /// Valid policy language outside the supported subset.
+    Unsupported,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    Allow,
+    Deny,
+}
+
+/// Outcome of evaluating a policy against one request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// A matching statement denies the request; nothing can override it.
+    Deny,
+    /// A matching statement allows the request and none denies it.
+    Allow,
+    /// No statement matched; fall back to the key's own permissions.
+    NotApplicable,
+}
+
+/// What a request does, in the terms policies are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PolicyRequest<'a> {
+    /// Authenticated API key ID.
+    pub principal: &'a str,
+    /// S3 action name such as `s3:GetObject`.
+    pub action: &'a str,
+    pub bucket: &'a str,
+    /// Object key, for object-level actions.
+    pub key: Option<&'a str>,
+    /// `prefix` parameter of listings.
+    pub prefix: Option<&'a str>,
+    /// Client IPv4 address, when known.
+    pub source_ip: Option<[u8; 4]>,
+}
+
+impl PolicyRequest<'_> {
+    fn resource(&self) -> String {
+        match self.key {
+            Some(key) => format!("{}{}/{}", RESOURCE_ARN_PREFIX, self.bucket, key),
+            None => format!("{}{}", RESOURCE_ARN_PREFIX, self.bucket),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Condition {
+    /// `s3:prefix` equal to, or with `like` matching, one of the values.
+    Prefix { values: Vec<String>, like: bool },
+    /// `aws:SourceIp` inside (or with `negate`, outside) one of the ranges.
+    SourceIp { ranges: Vec<Cidr>, negate: bool },
+}
+
+impl Condition {
+    fn holds(&self, request: &PolicyRequest<'_>) -> bool {
+        match self {
+            Condition::Prefix { values, like } => request.prefix.is_some_and(|prefix| {
+                values.iter().any(|value| {
+                    if *like {
+                        wildcard_match(value.as_bytes(), prefix.as_bytes(), false)
+                    } else {
+                        value == prefix
+                    }
+                })
+            }),
+            // An unknown address is inside no range, so `NotIpAddress`
+            // holds and a deny conditioned on it fails closed.
+            Condition::SourceIp { ranges, negate } => match request.source_ip {
+                Some(ip) => ranges.iter().any(|range| range.contains(ip)) != *negate,
+                None => *negate,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cidr {
+    network: u32,
+    prefix_len: u32,
+}
+
+impl Cidr {
+    fn parse(text: &str) -> Option<Self> {
+        let (addr, prefix_len) = match text.split_once('/') {
+            Some((addr, len)) => (addr, len.parse().ok().filter(|len| *len <= 32)?),
+            None => (text, 32),
+        };
+        let mut octets = [0u8; 4];
+        let mut parts = addr.split('.');
+        for octet in &mut octets {
+            *octet = parts.next()?.parse().ok()?;
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            network: u32::from_be_bytes(octets) & Self::mask(prefix_len),
+            prefix_len,
+        })
+    }
+
+    fn mask(prefix_len: u32) -> u32 {
+        u32::MAX.checked_shl(32 - prefix_len).unwrap_or(0)
+    }
+
+    fn contains(&self, ip: [u8; 4]) -> bool {
+        u32::from_be_bytes(ip) & Self::mask(self.prefix_len) == self.network
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Statement {
+    effect: Effect,
+    /// Key IDs; `"*"` matches every authenticated key.
+    principals: Vec<String>,
+    actions: Vec<String>,
+    resources: Vec<String>,
+    /// All must hold for the statement to apply.
+    conditions: Vec<Condition>,
+}
+
+impl Statement {
+    fn parse(value: &Value) -> Result<Self, PolicyError> {
+        let Value::Object(members) = value else {
+            return Err(PolicyError::InvalidValue);
+        };
+        let mut statement = Self {
+            effect: Effect::Deny,
+            principals: Vec::new(),
+            actions: Vec::new(),
+            resources: Vec::new(),
+            conditions: Vec::new(),
+        };
+        let mut effect = None;
+        for (name, value) in members {
+            match name.as_str() {
+                "Sid" => {
+                    value.as_str().ok_or(PolicyError::InvalidValue)?;
+                }
+                "Effect" => {
+                    effect = Some(match value.as_str() {
+                        Some("Allow") => Effect::Allow,
+                        Some("Deny") => Effect::Deny,
+                        _ => return Err(PolicyError::InvalidValue),
+                    })
+                }
+                "Principal" => statement.principals = parse_principal(value)?,
+                "Action" => statement.actions = strings(value)?,
+                "Resource" => statement.resources = strings(value)?,
+                "Condition" => statement.conditions = parse_conditions(value)?,
+                _ => return Err(PolicyError::Unsupported),
+            }
+        }
+        statement.effect = effect.ok_or(PolicyError::MissingField)?;
+        if statement.principals.is_empty()
+            || statement.actions.is_empty()
+            || statement.resources.is_empty()
+        {
+            return Err(PolicyError::MissingField);
+        }
+        if statement.actions.iter().any(|a| !a.starts_with("s3:")) {
+            return Err(PolicyError::InvalidValue);
+        }
+        if statement
+            .resources
+            .iter()
+            .any(|r| !r.starts_with(RESOURCE_ARN_PREFIX))
+        {
+            return Err(PolicyError::InvalidValue);
+        }
+        Ok(statement)
+    }
+
+    fn applies(&self, request: &PolicyRequest<'_>, resource: &str) -> bool {
+        self.principals
+            .iter()
+            .any(|p| p == "*" || p == request.principal)
+            && self
+                .actions
+                .iter()
+                .any(|a| wildcard_match(a.as_bytes(), request.action.as_bytes(), true))
+            && self
+                .resources
+                .iter()
+                .any(|r| wildcard_match(r.as_bytes(), resource.as_bytes(), false))
+            && self.conditions.iter().all(|c| c.holds(request))
+    }
+}
+
+/// A parsed bucket policy together with the document it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BucketPolicy {
+    source: String,
+    statements: Vec<Statement>,
+}
+
+impl BucketPolicy {
+    pub fn parse(source: &str) -> Result<Self, PolicyError> {
+        if source.len() > MAX_POLICY_LEN {
+            return Err(PolicyError::Syntax);
+        }
+        let document = json::parse(source).ok_or(PolicyError::Syntax)?;
+        let Value::Object(members) = &document else {
+            return Err(PolicyError::InvalidValue);
+        };
+        let mut statements = Vec::new();
+        for (name, value) in members {
+            match (name.as_str(), value) {
+                ("Version" | "Id", Value::String(_)) => {}
+                ("Statement", Value::Array(items)) => {
+                    for item in items {
+                        statements.push(Statement::parse(item)?);
+                    }
+                }
+                ("Statement", item @ Value::Object(_)) => statements.push(Statement::parse(item)?),
+                ("Version" | "Id" | "Statement", _) => return Err(PolicyError::InvalidValue),
+                _ => return Err(PolicyError::Unsupported),
+            }
+        }
+        if statements.is_empty() {
+            return Err(PolicyError::MissingField);
+        }
+        Ok(Self {
+            source: source.to_string(),
+            statements,
+        })
+    }
+
+    /// The document as submitted.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The policy for bucket `from` renamed to `to`, with resources naming
+    /// `from` rewritten in both the statements and the source. `None` if a
+    /// resource matches bucket names by wildcard, or the source spells the
+    /// ARN in a way that cannot be rewritten textually.
+    pub fn rebind(&self, from: &str, to: &str) -> Option<Self> {
+        let mut statements = self.statements.clone();
+        for statement in &mut statements {
+            for resource in &mut statement.resources {
+                *resource = rebind_resource(resource, from, to)?;
+            }
+        }
+        let (old, new) = (
+            format!("\"{}{}", RESOURCE_ARN_PREFIX, from),
+            format!("\"{}{}", RESOURCE_ARN_PREFIX, to),
+        );
+        let source = self
+            .source
+            .replace(&format!("{}\"", old), &format!("{}\"", new))
+            .replace(&format!("{}/", old), &format!("{}/", new));
+        let rebound = Self::parse(&source).ok()?;
+        (rebound.statements == statements).then_some(rebound)
+    }
+
+    /// Explicit deny wins over any allow; no match is `NotApplicable`.
+    pub fn evaluate(&self, request: &PolicyRequest<'_>) -> Decision {
+        let resource = request.resource();
+        let mut decision = Decision::NotApplicable;
+        for statement in &self.statements {
+            if statement.applies(request, &resource) {
+                match statement.effect {
+                    Effect::Deny => return Decision::Deny,
+                    Effect::Allow => decision = Decision::Allow,
+                }
+            }
+        }
+        decision
+    }
+}
+
+/// `resource` with bucket `from` renamed to `to`; other buckets are left
+/// alone. `None` if the bucket part is a wildcard.
+fn rebind_resource(resource: &str, from: &str, to: &str) -> Option<String> {
+    let path = &resource[RESOURCE_ARN_PREFIX.len()..];
+    let (bucket, rest) = match path.find('/') {
+        Some(slash) => path.split_at(slash),
+        None => (path, ""),
+    };
+    if bucket.contains(['*', '?']) {
+        return None;
+    }
+    let bucket = if bucket == from { to } else { bucket };
+    Some(format!("{}{}{}", RESOURCE_ARN_PREFIX, bucket, rest))
+}
+
+/// A string or an array of strings.
+fn strings(value: &Value) -> Result<Vec<String>, PolicyError> {
+    match value {
+        Value::String(s) => Ok(alloc::vec![s.clone()]),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| item.as_str().map(ToString::to_string))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(PolicyError::InvalidValue),
+        _ => Err(PolicyError::InvalidValue),
+    }
+}
+
+fn parse_principal(value: &Value) -> Result<Vec<String>, PolicyError> {
+    match value {
+        Value::String(s) if s == "*" => Ok(alloc::vec![s.clone()]),
+        Value::Object(members) => match members.as_slice() {
+            [(name, ids)] if name == "AWS" => strings(ids),
+            _ => Err(PolicyError::Unsupported),
+        },
+        _ => Err(PolicyError::InvalidValue),
+    }
+}
+
+fn parse_conditions(value: &Value) -> Result<Vec<Condition>, PolicyError> {
+    let Value::Object(operators) = value else {
+        return Err(PolicyError::InvalidValue);
+    };
+    let mut conditions = Vec::new();
+    for (operator, keys) in operators {
+        let Value::Object(keys) = keys else {
+            return Err(PolicyError::InvalidValue);
+        };
+        for (key, values) in keys {
+            let values = strings(values)?;
+            conditions.push(match (operator.as_str(), key.as_str()) {
+                ("StringEquals" | "StringLike", "s3:prefix") => Condition::Prefix {
+                    values,
+                    like: operator == "StringLike",
+                },
+                ("IpAddress" | "NotIpAddress", "aws:SourceIp") => Condition::SourceIp {
+                    ranges: values
+                        .iter()
+                        .map(|v| Cidr::parse(v))
+                        .collect::<Option<_>>()
+                        .ok_or(PolicyError::InvalidValue)?,
+                    negate: operator == "NotIpAddress",
+                },
+                _ => return Err(PolicyError::Unsupported),
+            });
+        }
+    }
+    Ok(conditions)
+}
+
+/// Matches `text` against `pattern`, where `*` matches any run of bytes
+/// and `?` any single byte.
+fn wildcard_match(pattern: &[u8], text: &[u8], ignore_case: bool) -> bool {
+    let eq = |p: u8, t: u8| p == b'?' || p == t || (ignore_case && p.eq_ignore_ascii_case(&t));
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it was tried at.
+    let mut star = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p + 1, t));
+            p += 1;
+        } else if p < pattern.len() && eq(pattern[p], text[t]) {
+            p += 1;
+            t += 1;
+        } else if let Some((after, tried)) = star {
+            p = after;
+            t = tried + 1;
+            star = Some((after, tried + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&b| b == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request<'a>(principal: &'a str, action: &'a str, key: Option<&'a str>) -> PolicyRequest<'a> {
+        PolicyRequest {
+            principal,
+            action,
+            bucket: "photos",
+            key,
+            prefix: None,
+            source_ip: Some([10, 1, 2, 3]),
+        }
+    }
+
+    #[test]
+    fn explicit_deny_wins() {
+        let policy = BucketPolicy::parse(
+            r#"{
+                "Version": "2012-10-17",
+                "Statement": [
+                    {"Effect": "Allow", "Principal": {"AWS": ["alice", "bob"]},
+                     "Action": "s3:*", "Resource": "arn:aws:s3:::photos/*"},
+                    {"Sid": "NoSecrets", "Effect": "Deny", "Principal": "*",
+                     "Action": ["s3:GetObject"], "Resource": "arn:aws:s3:::photos/private/*"},
+                    {"Effect": "Allow", "Principal": {"AWS": "carol"},
+                     "Action": "s3:ListBucket", "Resource": "arn:aws:s3:::photos",
+                     "Condition": {"StringLike": {"s3:prefix": "public/*"},
+                                   "IpAddress": {"aws:SourceIp": "10.0.0.0/8"}}}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let get = |principal, key| policy.evaluate(&request(principal, "s3:GetObject", Some(key)));
+        assert_eq!(get("alice", "cat.jpg"), Decision::Allow);
+        assert_eq!(get("alice", "private/cat.jpg"), Decision::Deny);
+        assert_eq!(get("mallory", "private/cat.jpg"), Decision::Deny);
+        assert_eq!(get("mallory", "cat.jpg"), Decision::NotApplicable);
+        assert_eq!(
+            policy.evaluate(&request("bob", "S3:putobject", Some("x"))),
+            Decision::Allow
+        );
+
+        let list = |prefix, ip| {
+            policy.evaluate(&PolicyRequest {
+                prefix,
+                source_ip: ip,
+                ..request("carol", "s3:ListBucket", None)
+            })
+        };
+        assert_eq!(
+            list(Some("public/2024"), Some([10, 9, 9, 9])),
+            Decision::Allow
+        );
+        assert_eq!(
+            list(Some("private/"), Some([10, 9, 9, 9])),
+            Decision::NotApplicable
+        );
+        assert_eq!(
+            list(Some("public/"), Some([192, 168, 0, 1])),
+            Decision::NotApplicable
+        );
+        assert_eq!(list(None, Some([10, 0, 0, 1])), Decision::NotApplicable);
+        assert_eq!(list(Some("public/"), None), Decision::NotApplicable);
+    }
+
+    #[test]
+    fn rejects_unsupported_policies() {
+        let statement =
+            |body: &str| BucketPolicy::parse(&format!("{{\"Statement\": [{}]}}", body)).map(|_| ());
+        let base = r#""Effect": "Allow", "Principal": "*", "Action": "s3:GetObject""#;
+        assert_eq!(
+            statement(&format!(r#"{{{}, "Resource": "arn:aws:s3:::b/*"}}"#, base)),
+            Ok(())
+        );
+        assert_eq!(
+            statement(&format!("{{{}}}", base)),
+            Err(PolicyError::MissingField)
+        );
+        assert_eq!(
+            statement(&format!(r#"{{{}, "NotResource": "arn:aws:s3:::b"}}"#, base)),
+            Err(PolicyError::Unsupported)
+        );
+        assert_eq!(
+            statement(&format!(
+                r#"{{{}, "Resource": "arn:aws:s3:::b", "Condition": {{"DateLessThan": {{"aws:CurrentTime": "2030"}}}}}}"#,
+                base
+            )),
+            Err(PolicyError::Unsupported)
+        );
+        assert_eq!(
+            statement(&format!(
+                r#"{{{}, "Resource": "arn:aws:s3:::b", "Condition": {{"IpAddress": {{"aws:SourceIp": "10.0.0.0/33"}}}}}}"#,
+                base
+            )),
+            Err(PolicyError::InvalidValue)
+        );
+        assert_eq!(
+            BucketPolicy::parse("{\"Statement\": "),
+            Err(PolicyError::Syntax)
+        );
+
+        assert!(wildcard_match(b"a*b?d*", b"aXXbcdEF", false));
+        assert!(!wildcard_match(b"a*b?d", b"abd", false));
+        assert!(Cidr::parse("0.0.0.0/0").unwrap().contains([8, 8, 8, 8]));
+    }
+}
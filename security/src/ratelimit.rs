@@ -0,0 +1,274 @@
+#![allow(dead_code)]
+
+//! Token-bucket rate limiting per API key, and brute-force protection per
+//! client address.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+
+use runtime::timers::{TimerId, TimerQueue, TimerService};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitError {
+    /// The bucket is empty; retry after the next refill.
+    SlowDown,
+    /// Too many consecutive failed authentications from this client.
+    LockedOut,
+}
+
+/// Size and refill rate of one token bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenBucket {
+    pub burst: u32,
+    /// Tokens added per refill interval, up to `burst`.
+    pub refill: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    /// Requests each API key may make.
+    pub requests: TokenBucket,
+    /// Failed authentications each client address may make.
+    pub failures: TokenBucket,
+    /// Ticks between refills.
+    pub interval: u64,
+    /// Consecutive failures from one client that trigger a lockout.
+    pub lockout_after: u32,
+    /// Ticks a lockout lasts.
+    pub lockout_for: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests: TokenBucket {
+                burst: 1000,
+                refill: 100,
+            },
+            failures: TokenBucket {
+                burst: 20,
+                refill: 1,
+            },
+            interval: 1,
+            lockout_after: 10,
+            lockout_for: 900,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ClientState {
+    tokens: u32,
+    /// Failures since the last success or lockout.
+    failures: u32,
+    locked_until: Option<u64>,
+}
+
+/// Per-key and per-client buckets, refilled by a periodic timer that also
+/// forgets buckets which are full and idle.
+pub struct RateLimiter<T: TimerService = TimerQueue> {
+    config: RateLimitConfig,
+    timers: T,
+    timer: Option<TimerId>,
+    last_refill: u64,
+    keys: BTreeMap<String, u32>,
+    clients: BTreeMap<[u8; 4], ClientState>,
+}
+
+impl RateLimiter<TimerQueue> {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self::with_timers(TimerQueue::new(), config)
+    }
+}
+
+impl Default for RateLimiter<TimerQueue> {
+    fn default() -> Self {
+        Self::new(RateLimitConfig::default())
+    }
+}
+
+impl<T: TimerService> RateLimiter<T> {
+    pub fn with_timers(timers: T, config: RateLimitConfig) -> Self {
+        Self {
+            config: RateLimitConfig {
+                interval: config.interval.max(1),
+                ..config
+            },
+            timers,
+            timer: None,
+            last_refill: 0,
+            keys: BTreeMap::new(),
+            clients: BTreeMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &RateLimitConfig {
+        &self.config
+    }
+
+    /// Polls the refill timer, arming it on first use. When it fires, every
+    /// bucket gains a refill for each interval elapsed since the last one.
+    pub fn poll(&mut self, now: u64) {
+        let Some(armed) = self.timer else {
+            self.last_refill = now;
+            self.timer = Some(
+                self.timers
+                    .schedule(now.saturating_add(self.config.interval)),
+            );
+            return;
+        };
+        let mut fired = false;
+        self.timers.poll(now, &mut |id| fired |= id == armed);
+        if !fired {
+            return;
+        }
+        let intervals = (now.saturating_sub(self.last_refill) / self.config.interval).max(1);
+        self.last_refill = now;
+        self.timer = Some(
+            self.timers
+                .schedule(now.saturating_add(self.config.interval)),
+        );
+        self.refill(intervals, now);
+    }
+
+    fn refill(&mut self, intervals: u64, now: u64) {
+        let gain = |bucket: TokenBucket| {
+            u32::try_from(intervals.saturating_mul(bucket.refill as u64)).unwrap_or(u32::MAX)
+        };
+        let (requests, failures) = (self.config.requests, self.config.failures);
+        self.keys.retain(|_, tokens| {
+            *tokens = tokens.saturating_add(gain(requests)).min(requests.burst);
+            *tokens < requests.burst
+        });
+        self.clients.retain(|_, client| {
+            client.tokens = client
+                .tokens
+                .saturating_add(gain(failures))
+                .min(failures.burst);
+            if client.locked_until.is_some_and(|until| until <= now) {
+                client.locked_until = None;
+            }
+            client.tokens < failures.burst || client.failures > 0 || client.locked_until.is_some()
+        });
+    }
+
+    /// Spends one of `key_id`'s request tokens.
+    pub fn check_key(&mut self, key_id: &str) -> Result<(), RateLimitError> {
+        let burst = self.config.requests.burst;
+        let tokens = match self.keys.get_mut(key_id) {
+            Some(tokens) => tokens,
+            None => self.keys.entry(key_id.to_string()).or_insert(burst),
+        };
+        if *tokens == 0 {
+            return Err(RateLimitError::SlowDown);
+        }
+        *tokens -= 1;
+        Ok(())
+    }
+
+    /// Whether `client` may attempt to authenticate at `now`.
+    pub fn check_client(&self, client: [u8; 4], now: u64) -> Result<(), RateLimitError> {
+        match self.clients.get(&client) {
+            Some(state) if state.locked_until.is_some_and(|until| now < until) => {
+                Err(RateLimitError::LockedOut)
+            }
+            Some(state) if state.tokens == 0 => Err(RateLimitError::SlowDown),
+            _ => Ok(()),
+        }
+    }
+
+    /// Records a failed authentication from `client`, locking it out once
+    /// `lockout_after` failures happen without a success in between.
+    pub fn record_failure(&mut self, client: [u8; 4], now: u64) {
+        let config = self.config;
+        let state = self.clients.entry(client).or_insert(ClientState {
+            tokens: config.failures.burst,
+            failures: 0,
+            locked_until: None,
+        });
+        state.tokens = state.tokens.saturating_sub(1);
+        state.failures += 1;
+        if state.failures >= config.lockout_after {
+            state.failures = 0;
+            state.locked_until = Some(now.saturating_add(config.lockout_for));
+        }
+    }
+
+    /// Clears `client`'s run of failures after it authenticates.
+    pub fn record_success(&mut self, client: [u8; 4]) {
+        if let Some(state) = self.clients.get_mut(&client) {
+            state.failures = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            requests: TokenBucket {
+                burst: 3,
+                refill: 1,
+            },
+            failures: TokenBucket {
+                burst: 4,
+                refill: 2,
+            },
+            interval: 10,
+            lockout_after: 3,
+            lockout_for: 100,
+        })
+    }
+
+    #[test]
+    fn key_buckets_drain_and_refill_on_timer() {
+        let mut limiter = limiter();
+        limiter.poll(0);
+        for _ in 0..3 {
+            assert_eq!(limiter.check_key("k"), Ok(()));
+        }
+        assert_eq!(limiter.check_key("k"), Err(RateLimitError::SlowDown));
+        assert_eq!(limiter.check_key("other"), Ok(()));
+
+        // Nothing refills before the timer fires.
+        limiter.poll(9);
+        assert_eq!(limiter.check_key("k"), Err(RateLimitError::SlowDown));
+        limiter.poll(10);
+        assert_eq!(limiter.check_key("k"), Ok(()));
+        assert_eq!(limiter.check_key("k"), Err(RateLimitError::SlowDown));
+
+        // A late poll refills for every elapsed interval; full buckets are
+        // dropped.
+        limiter.poll(50);
+        assert!(limiter.keys.is_empty());
+    }
+
+    #[test]
+    fn repeated_failures_lock_the_client_out() {
+        let mut limiter = limiter();
+        let client = [10, 0, 0, 7];
+        limiter.poll(0);
+        limiter.record_failure(client, 1);
+        limiter.record_failure(client, 2);
+        limiter.record_success(client);
+        limiter.record_failure(client, 3);
+        limiter.record_failure(client, 4);
+        // Four failures drained the bucket without a lockout.
+        assert_eq!(
+            limiter.check_client(client, 5),
+            Err(RateLimitError::SlowDown)
+        );
+        limiter.poll(10);
+        assert_eq!(limiter.check_client(client, 10), Ok(()));
+
+        limiter.record_failure(client, 11);
+        assert_eq!(
+            limiter.check_client(client, 11),
+            Err(RateLimitError::LockedOut)
+        );
+        assert_eq!(limiter.check_client([10, 0, 0, 8], 11), Ok(()));
+        assert_eq!(limiter.check_client(client, 111), Ok(()));
+    }
+}
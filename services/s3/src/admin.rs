@@ -0,0 +1,291 @@
+#![allow(dead_code)]
+
+//! Routes and request bodies of the administrative API under `_admin`.
+//! Bodies are `name=value` lines, like object tagging.
+
+use alloc::format;
+use alloc::string::String;
+
+use crate::http::Method;
+use security::crypto::HmacSha256;
+use security::keystore::{ApiKeyEntry, KeyStatus, SALT_LEN};
+use security::masterkey::SubKey;
+use security::permissions::{Permission, Permissions, ANY_BUCKET};
+
+/// First path segment of the admin routes.
+pub const ADMIN_PREFIX: &str = "_admin";
+const MAX_KEY_ID_LEN: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdminRoute<'a> {
+    /// `_admin/keys`
+    Keys,
+    /// `_admin/keys/<id>`
+    Key(&'a str),
+    /// `_admin/keys/<id>/rotate`
+    RotateKey(&'a str),
+    /// `_admin/buckets`
+    Buckets,
+    /// `_admin/buckets/<name>`
+    Bucket(&'a str),
+    /// `_admin/audit`
+    Audit,
+}
+
+impl<'a> AdminRoute<'a> {
+    /// Parses a request path without its leading slash or query.
+    pub fn parse(path: &'a str) -> Option<Self> {
+        let rest = path.strip_prefix(ADMIN_PREFIX)?.strip_prefix('/')?;
+        let mut parts = rest.split('/');
+        let route = match (parts.next()?, parts.next(), parts.next()) {
+            ("keys", None, _) => Self::Keys,
+            ("keys", Some(id), None) => Self::Key(id),
+            ("keys", Some(id), Some("rotate")) => Self::RotateKey(id),
+            ("buckets", None, _) => Self::Buckets,
+            ("buckets", Some(name), None) if !name.is_empty() => Self::Bucket(name),
+            ("audit", None, _) => Self::Audit,
+            _ => return None,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        match route {
+            Self::Key(id) | Self::RotateKey(id) if !valid_key_id(id) => None,
+            route => Some(route),
+        }
+    }
+}
+
+/// Action name recorded in the audit log for an admin request.
+pub fn action(path: &str, method: &Method) -> &'static str {
+    match (AdminRoute::parse(path), method) {
+        (Some(AdminRoute::Keys), _) => "admin:ListKeys",
+        (Some(AdminRoute::Key(_)), Method::Put) => "admin:CreateKey",
+        (Some(AdminRoute::Key(_)), Method::Delete) => "admin:RevokeKey",
+        (Some(AdminRoute::Key(_)), _) => "admin:GetKey",
+        (Some(AdminRoute::RotateKey(_)), _) => "admin:RotateKey",
+        (Some(AdminRoute::Buckets | AdminRoute::Bucket(_)), _) => "admin:GetUsage",
+        (Some(AdminRoute::Audit), _) => "admin:GetAudit",
+        (None, _) => "admin:Unknown",
+    }
+}
+
+/// Key IDs travel in `key:secret` headers and SigV4 credential scopes, so
+/// they are limited to characters neither uses as a separator.
+pub fn valid_key_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_KEY_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+/// Body of `PUT _admin/keys/<id>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NewKey<'a> {
+    pub secret: &'a str,
+    /// Bucket the key is scoped to; `*` when omitted.
+    pub bucket: &'a str,
+    pub permissions: Permissions,
+    /// Whether to keep the secret for SigV4 signing.
+    pub signing: bool,
+    pub expires_at: Option<u64>,
+}
+
+impl<'a> NewKey<'a> {
+    pub fn parse(body: &'a str) -> Option<Self> {
+        let (mut secret, mut permissions) = (None, None);
+        let mut key = Self {
+            secret: "",
+            bucket: ANY_BUCKET,
+            permissions: Permissions::NONE,
+            signing: false,
+            expires_at: None,
+        };
+        for field in fields(body) {
+            match field? {
+                ("secret", value) => secret = Some(value),
+                ("bucket", value) if !value.is_empty() => key.bucket = value,
+                ("permissions", value) => permissions = Some(parse_permissions(value)?),
+                ("signing", value) => key.signing = parse_bool(value)?,
+                ("expires", value) => key.expires_at = Some(value.parse().ok()?),
+                _ => return None,
+            }
+        }
+        key.secret = secret.filter(|s| !s.is_empty())?;
+        key.permissions = permissions?;
+        Some(key)
+    }
+
+    pub fn entry(&self, key_id: &'a str, salt: [u8; SALT_LEN]) -> ApiKeyEntry<'a> {
+        let entry = if self.signing {
+            ApiKeyEntry::with_signing_secret(
+                key_id,
+                self.secret,
+                salt,
+                self.bucket,
+                self.permissions,
+            )
+        } else {
+            ApiKeyEntry::with_secret(key_id, self.secret, salt, self.bucket, self.permissions)
+        };
+        match self.expires_at {
+            Some(at) => entry.with_expiry(at),
+            None => entry,
+        }
+    }
+}
+
+/// Body of `POST _admin/keys/<id>/rotate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rotation<'a> {
+    pub secret: &'a str,
+    /// Seconds the old secret stays valid; 0 when omitted.
+    pub grace: u64,
+}
+
+impl<'a> Rotation<'a> {
+    pub fn parse(body: &'a str) -> Option<Self> {
+        let mut secret = None;
+        let mut grace = 0;
+        for field in fields(body) {
+            match field? {
+                ("secret", value) => secret = Some(value),
+                ("grace", value) => grace = value.parse().ok()?,
+                _ => return None,
+            }
+        }
+        Some(Self {
+            secret: secret.filter(|s| !s.is_empty())?,
+            grace,
+        })
+    }
+}
+
+/// Salt for a new secret of `key_id`. There is no entropy source here, so
+/// it is an HMAC under `key` (the `ApiKeyHashing` subkey) of values that
+/// differ for every secret a key is given: the key ID, the time, and the
+/// hash of the secret being replaced (zero for a new key). Without the
+/// subkey the salt cannot be predicted.
+pub fn derive_salt(key: &SubKey, key_id: &str, now: u64, previous: &[u8; 32]) -> [u8; SALT_LEN] {
+    let mut hasher = HmacSha256::new(key.as_bytes());
+    hasher.update(b"salt");
+    hasher.update(key_id.as_bytes());
+    hasher.update(&now.to_le_bytes());
+    hasher.update(previous);
+    let mut salt = [0; SALT_LEN];
+    salt.copy_from_slice(&hasher.finish()[..SALT_LEN]);
+    salt
+}
+
+/// One listing line for `entry`. Never includes secrets or hashes.
+pub fn describe_key(out: &mut String, entry: &ApiKeyEntry<'_>) {
+    let status = match entry.status {
+        KeyStatus::Active => "active",
+        KeyStatus::Disabled => "disabled",
+        KeyStatus::Revoked => "revoked",
+    };
+    out.push_str(&format!(
+        "{} bucket={} permissions=",
+        entry.key_id, entry.bucket
+    ));
+    let mut names = Permission::ALL
+        .into_iter()
+        .filter(|p| entry.permissions.contains(*p))
+        .map(Permission::as_str);
+    if let Some(first) = names.next() {
+        out.push_str(first);
+        for name in names {
+            out.push(',');
+            out.push_str(name);
+        }
+    }
+    out.push_str(&format!(" status={}", status));
+    if let Some(at) = entry.expires_at {
+        out.push_str(&format!(" expires={}", at));
+    }
+    if entry.signing_secret.is_some() {
+        out.push_str(" signing");
+    }
+    if let Some(retired) = entry.retired {
+        out.push_str(&format!(" grace_until={}", retired.valid_until));
+    }
+    out.push('\n');
+}
+
+fn fields(body: &str) -> impl Iterator<Item = Option<(&str, &str)>> {
+    body.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.split_once('='))
+}
+
+fn parse_permissions(value: &str) -> Option<Permissions> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .try_fold(Permissions::NONE, |set, name| {
+            Permission::parse(name).map(|p| set.with(p))
+        })
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use security::masterkey::{KeyPurpose, MasterKey};
+
+    #[test]
+    fn parses_routes_and_bodies() {
+        assert_eq!(AdminRoute::parse("_admin/keys"), Some(AdminRoute::Keys));
+        assert_eq!(
+            AdminRoute::parse("_admin/keys/ops-1/rotate"),
+            Some(AdminRoute::RotateKey("ops-1"))
+        );
+        assert_eq!(
+            AdminRoute::parse("_admin/buckets/photos"),
+            Some(AdminRoute::Bucket("photos"))
+        );
+        assert_eq!(AdminRoute::parse("_admin/keys/a:b"), None);
+        assert_eq!(AdminRoute::parse("_admin/keys/a/b"), None);
+        assert_eq!(AdminRoute::parse("_admin"), None);
+        assert_eq!(AdminRoute::parse("_admin/audit"), Some(AdminRoute::Audit));
+
+        let key = NewKey::parse("secret=pa=ss\nbucket=photos\npermissions=read, list\nexpires=9")
+            .unwrap();
+        assert_eq!(key.secret, "pa=ss");
+        assert_eq!(
+            key.permissions,
+            Permissions::of(&[Permission::Read, Permission::List])
+        );
+        assert_eq!(key.expires_at, Some(9));
+        assert_eq!(NewKey::parse("secret=x"), None);
+        assert_eq!(NewKey::parse("secret=x\npermissions=fly"), None);
+        assert_eq!(NewKey::parse("permissions=read\nowner=me\nsecret=x"), None);
+
+        let mut line = String::new();
+        describe_key(&mut line, &key.entry("ops", [0; SALT_LEN]));
+        assert_eq!(
+            line,
+            "ops bucket=photos permissions=read,list status=active expires=9\n"
+        );
+        let key = MasterKey::from_bytes([7; 32]).derive(KeyPurpose::ApiKeyHashing);
+        let other = MasterKey::from_bytes([8; 32]).derive(KeyPurpose::ApiKeyHashing);
+        assert_ne!(
+            derive_salt(&key, "ops", 1, &[0; 32]),
+            derive_salt(&key, "ops", 2, &[0; 32])
+        );
+        assert_ne!(
+            derive_salt(&key, "ops", 1, &[0; 32]),
+            derive_salt(&other, "ops", 1, &[0; 32])
+        );
+    }
+}
@@ -4,15 +4,27 @@ pub enum S3Error<'a> {
     AccessDenied,
     NoSuchBucket(&'a str),
     NoSuchKey(&'a str),
+    QuotaExceeded(&'a str),
     Internal,
 }
 
 impl<'a> S3Error<'a> {
     pub fn status_code(&self) -> u16 {
         match self {
-            Self::AccessDenied => 403,
+            Self::AccessDenied | Self::QuotaExceeded(_) => 403,
             Self::NoSuchBucket(_) | Self::NoSuchKey(_) => 404,
             Self::Internal => 500,
         }
     }
+
+    /// Error code sent as the response body.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::AccessDenied => "AccessDenied",
+            Self::NoSuchBucket(_) => "NoSuchBucket",
+            Self::NoSuchKey(_) => "NoSuchKey",
+            Self::QuotaExceeded(_) => "QuotaExceeded",
+            Self::Internal => "InternalError",
+        }
+    }
 }
@@ -0,0 +1,417 @@
+#![allow(dead_code)]
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use filesystem::catalog::{Catalog, ObjectDescription};
+use filesystem::index::{ListDirection, ListRequest, MutableIndex};
+use storage::object::{ObjectMetadata, ObjectStore};
+
+/// Object-store key under which the service keeps an object's data.
+pub fn storage_key(bucket: &str, key: &str) -> String {
+    format!("{}/{}", bucket, key)
+}
+
+/// One disagreement between the catalog, the index and the object store.
+/// The catalog is treated as the source of truth for which objects exist,
+/// and the object store for how large they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// Data in the object store with no catalog entry.
+    OrphanedObject { storage_key: String },
+    /// Catalog entry whose data is missing from the object store.
+    DanglingEntry { bucket: String, key: String },
+    /// Catalog size differs from the stored data.
+    SizeMismatch {
+        bucket: String,
+        key: String,
+        catalog: u64,
+        stored: u64,
+    },
+    /// Catalog entry absent from the index.
+    MissingIndexEntry { bucket: String, key: String },
+    /// Index entry with no catalog entry.
+    StaleIndexEntry { bucket: String, key: String },
+    /// Index size differs from the catalog.
+    IndexSizeMismatch {
+        bucket: String,
+        key: String,
+        catalog: u64,
+        indexed: u64,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct FsckReport {
+    pub findings: Vec<Finding>,
+    /// Findings successfully repaired; always zero for a read-only check.
+    pub repaired: usize,
+    /// Buckets whose catalog entries could not be listed. Their data and
+    /// index entries are left unchecked rather than judged against an
+    /// incomplete catalog.
+    pub unreadable: Vec<String>,
+}
+
+impl FsckReport {
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+type ObjectKey = (String, String);
+
+pub fn check<C, I, O>(catalog: &mut C, index: &mut I, store: &mut O, repair: bool) -> FsckReport
+where
+    C: Catalog,
+    I: MutableIndex,
+    O: ObjectStore,
+{
+    let mut report = FsckReport::default();
+    let catalogued = catalog_objects(catalog, &mut report.unreadable);
+    let readable = |bucket: &str| !report.unreadable.iter().any(|b| b == bucket);
+    let mut stored = BTreeMap::new();
+    store.list(&mut |key, size| {
+        // Bucket names cannot contain '/', so it ends the bucket part.
+        let bucket = key.split('/').next().unwrap_or(key);
+        if readable(bucket) {
+            stored.insert(key.to_string(), size);
+        }
+    });
+    let mut indexed = index_entries(index);
+    indexed.retain(|(bucket, _), _| readable(bucket));
+
+    for ((bucket, key), object) in &catalogued {
+        let meta = &object.meta;
+        let in_index = indexed.remove(&(bucket.clone(), key.clone()));
+        let finding = match stored.remove(&storage_key(bucket, key)) {
+            None => Finding::DanglingEntry {
+                bucket: bucket.clone(),
+                key: key.clone(),
+            },
+            Some(size) if size != meta.size => Finding::SizeMismatch {
+                bucket: bucket.clone(),
+                key: key.clone(),
+                catalog: meta.size,
+                stored: size,
+            },
+            Some(_) => match in_index {
+                None => Finding::MissingIndexEntry {
+                    bucket: bucket.clone(),
+                    key: key.clone(),
+                },
+                Some(size) if size != meta.size => Finding::IndexSizeMismatch {
+                    bucket: bucket.clone(),
+                    key: key.clone(),
+                    catalog: meta.size,
+                    indexed: size,
+                },
+                Some(_) => continue,
+            },
+        };
+        report.findings.push(finding);
+    }
+    report.findings.extend(
+        stored
+            .into_keys()
+            .map(|storage_key| Finding::OrphanedObject { storage_key }),
+    );
+    report.findings.extend(
+        indexed
+            .into_keys()
+            .map(|(bucket, key)| Finding::StaleIndexEntry { bucket, key }),
+    );
+
+    if repair {
+        for finding in &report.findings {
+            if apply_repair(catalog, index, store, &catalogued, finding) {
+                report.repaired += 1;
+            }
+        }
+    }
+    report
+}
+
+/// Every catalogued object, leaving out the buckets that fail to list,
+/// which are added to `unreadable`.
+fn catalog_objects<C: Catalog>(
+    catalog: &C,
+    unreadable: &mut Vec<String>,
+) -> BTreeMap<ObjectKey, ObjectDescription> {
+    let mut buckets = Vec::new();
+    catalog.list_buckets(&mut |info| buckets.push(info.name.to_string()));
+    let mut objects = BTreeMap::new();
+    for bucket in buckets {
+        let mut listed = BTreeMap::new();
+        let result = catalog.list_objects(&bucket, &mut |key, object| {
+            listed.insert((bucket.clone(), key.to_string()), object.clone());
+        });
+        match result {
+            Ok(()) => objects.append(&mut listed),
+            Err(_) => unreadable.push(bucket),
+        }
+    }
+    objects
+}
+
+fn index_entries<I: MutableIndex>(index: &I) -> BTreeMap<ObjectKey, u64> {
+    let mut buckets = Vec::new();
+    index.buckets(&mut |name| buckets.push(name.to_string()));
+    let mut entries = BTreeMap::new();
+    for bucket in buckets {
+        let request = ListRequest {
+            bucket: &bucket,
+            prefix: None,
+            delimiter: None,
+            continuation: None,
+            start_after: None,
+            direction: ListDirection::Ascending,
+            max_keys: 0,
+            tag: None,
+        };
+        if let Ok(response) = index.list(&request) {
+            for object in response.objects {
+                entries.insert((bucket.clone(), object.key.to_string()), object.size);
+            }
+        }
+    }
+    entries
+}
+
+fn apply_repair<C, I, O>(
+    catalog: &mut C,
+    index: &mut I,
+    store: &mut O,
+    catalogued: &BTreeMap<ObjectKey, ObjectDescription>,
+    finding: &Finding,
+) -> bool
+where
+    C: Catalog,
+    I: MutableIndex,
+    O: ObjectStore,
+{
+    match finding {
+        Finding::OrphanedObject { storage_key } => store.delete(storage_key).is_ok(),
+        Finding::DanglingEntry { bucket, key } => {
+            index.remove(bucket, key);
+            catalog.remove_object(bucket, key).is_ok()
+        }
+        Finding::SizeMismatch {
+            bucket,
+            key,
+            stored,
+            ..
+        } => {
+            let Some(object) = catalogued.get(&(bucket.clone(), key.clone())) else {
+                return false;
+            };
+            let fixed = ObjectMetadata {
+                size: *stored,
+                ..object.meta
+            };
+            if catalog
+                .put_object_with(bucket, key, fixed, object.attributes.clone())
+                .is_err()
+            {
+                return false;
+            }
+            index.insert_with(bucket, key, fixed, &object.attributes);
+            true
+        }
+        Finding::MissingIndexEntry { bucket, key }
+        | Finding::IndexSizeMismatch { bucket, key, .. } => {
+            match catalogued.get(&(bucket.clone(), key.clone())) {
+                Some(object) => {
+                    index.insert_with(bucket, key, object.meta, &object.attributes);
+                    true
+                }
+                None => false,
+            }
+        }
+        Finding::StaleIndexEntry { bucket, key } => {
+            index.remove(bucket, key);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filesystem::catalog::{
+        BucketDescription, BucketInfo, BucketMetadata, BucketQuota, BucketUsage, CatalogError,
+        InMemoryCatalog, ObjectAttributes,
+    };
+    use filesystem::index::InMemoryIndex;
+    use storage::object::InMemoryObjectStore;
+
+    fn meta(size: u64) -> ObjectMetadata {
+        ObjectMetadata {
+            id: 1,
+            size,
+            checksum: 0,
+        }
+    }
+
+    #[test]
+    fn detects_and_repairs_disagreements() {
+        let mut catalog = InMemoryCatalog::new();
+        let mut index = InMemoryIndex::new();
+        let mut store = InMemoryObjectStore::new();
+        catalog.create_bucket("docs").unwrap();
+
+        // Consistent object.
+        let ok = store.put("docs/ok.txt", b"ok").unwrap();
+        catalog.put_object("docs", "ok.txt", ok).unwrap();
+        index.insert("docs", "ok.txt", ok);
+        // Catalog entry without data.
+        catalog.put_object("docs", "gone.txt", meta(3)).unwrap();
+        index.insert("docs", "gone.txt", meta(3));
+        // Data without catalog entry.
+        store.put("docs/orphan.txt", b"xx").unwrap();
+        // Catalog and store disagree on size, index is missing.
+        store.put("docs/grown.txt", b"12345").unwrap();
+        catalog.put_object("docs", "grown.txt", meta(2)).unwrap();
+        // Index entry for nothing.
+        index.insert("docs", "stale.txt", meta(9));
+
+        let report = check(&mut catalog, &mut index, &mut store, false);
+        assert_eq!(
+            report.findings,
+            [
+                Finding::DanglingEntry {
+                    bucket: "docs".to_string(),
+                    key: "gone.txt".to_string(),
+                },
+                Finding::SizeMismatch {
+                    bucket: "docs".to_string(),
+                    key: "grown.txt".to_string(),
+                    catalog: 2,
+                    stored: 5,
+                },
+                Finding::OrphanedObject {
+                    storage_key: "docs/orphan.txt".to_string(),
+                },
+                Finding::StaleIndexEntry {
+                    bucket: "docs".to_string(),
+                    key: "stale.txt".to_string(),
+                },
+            ]
+        );
+        assert_eq!(report.repaired, 0);
+
+        let repaired = check(&mut catalog, &mut index, &mut store, true);
+        assert_eq!(repaired.repaired, 4);
+        assert!(check(&mut catalog, &mut index, &mut store, false).is_clean());
+        assert_eq!(
+            catalog
+                .object_metadata("docs", "grown.txt")
+                .unwrap()
+                .meta
+                .size,
+            5
+        );
+    }
+
+    /// Catalog whose object listing fails for one bucket.
+    struct Unlistable {
+        inner: InMemoryCatalog,
+        broken: &'static str,
+    }
+
+    impl Catalog for Unlistable {
+        fn create_bucket_with(
+            &mut self,
+            name: &str,
+            metadata: BucketMetadata,
+        ) -> Result<(), CatalogError> {
+            self.inner.create_bucket_with(name, metadata)
+        }
+        fn delete_bucket(&mut self, name: &str) -> Result<(), CatalogError> {
+            self.inner.delete_bucket(name)
+        }
+        fn rename_bucket(&mut self, from: &str, to: &str) -> Result<(), CatalogError> {
+            self.inner.rename_bucket(from, to)
+        }
+        fn list_buckets(&self, sink: &mut dyn FnMut(BucketInfo<'_>)) {
+            self.inner.list_buckets(sink)
+        }
+        fn describe_bucket(&self, name: &str) -> Result<BucketDescription, CatalogError> {
+            self.inner.describe_bucket(name)
+        }
+        fn set_bucket_metadata(
+            &mut self,
+            name: &str,
+            metadata: BucketMetadata,
+        ) -> Result<(), CatalogError> {
+            self.inner.set_bucket_metadata(name, metadata)
+        }
+        fn list_objects(
+            &self,
+            bucket: &str,
+            sink: &mut dyn FnMut(&str, &ObjectDescription),
+        ) -> Result<(), CatalogError> {
+            if bucket == self.broken {
+                return Err(CatalogError::Backend);
+            }
+            self.inner.list_objects(bucket, sink)
+        }
+        fn put_object_with(
+            &mut self,
+            bucket: &str,
+            key: &str,
+            meta: ObjectMetadata,
+            attributes: ObjectAttributes,
+        ) -> Result<(), CatalogError> {
+            self.inner.put_object_with(bucket, key, meta, attributes)
+        }
+        fn remove_object(&mut self, bucket: &str, key: &str) -> Result<(), CatalogError> {
+            self.inner.remove_object(bucket, key)
+        }
+        fn object_metadata(
+            &self,
+            bucket: &str,
+            key: &str,
+        ) -> Result<ObjectDescription, CatalogError> {
+            self.inner.object_metadata(bucket, key)
+        }
+        fn usage(&self, bucket: &str) -> Result<BucketUsage, CatalogError> {
+            self.inner.usage(bucket)
+        }
+        fn quota(&self, bucket: &str) -> Result<BucketQuota, CatalogError> {
+            self.inner.quota(bucket)
+        }
+        fn set_quota(&mut self, bucket: &str, quota: BucketQuota) -> Result<(), CatalogError> {
+            self.inner.set_quota(bucket, quota)
+        }
+    }
+
+    #[test]
+    fn unlistable_buckets_are_skipped_not_repaired() {
+        let mut catalog = Unlistable {
+            inner: InMemoryCatalog::new(),
+            broken: "docs",
+        };
+        let mut index = InMemoryIndex::new();
+        let mut store = InMemoryObjectStore::new();
+        catalog.create_bucket("docs").unwrap();
+        catalog.create_bucket("logs").unwrap();
+        let kept = store.put("docs/kept.txt", b"data").unwrap();
+        catalog.put_object("docs", "kept.txt", kept).unwrap();
+        index.insert("docs", "kept.txt", kept);
+        store.put("logs/orphan.txt", b"xx").unwrap();
+
+        let report = check(&mut catalog, &mut index, &mut store, true);
+        assert_eq!(report.unreadable, ["docs"]);
+        assert_eq!(
+            report.findings,
+            [Finding::OrphanedObject {
+                storage_key: "logs/orphan.txt".to_string(),
+            }]
+        );
+        let mut buffer = [0u8; 4];
+        assert!(store.get("docs/kept.txt", &mut buffer).is_ok());
+        assert!(index_entries(&index).contains_key(&("docs".to_string(), "kept.txt".to_string())));
+    }
+}
@@ -1,22 +1,46 @@
 #![allow(dead_code)]
 
+use alloc::collections::BTreeMap;
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
 
-use crate::auth::{authenticate_request, HeaderAuth};
+use crate::admin::{self, AdminRoute, NewKey, Rotation, ADMIN_PREFIX};
+use crate::auth::{
+    authenticate_request, uri_decode, CertificateBindings, HeaderAuth, MtlsAuth, PresignedAuth,
+    SigV4Auth, API_KEY_HEADER,
+};
+use crate::error::S3Error;
+use crate::fsck::{self, FsckReport};
 use crate::http::{Header as HttpHeader, HttpHandler, Method, Request, Response};
 use crate::log::EventLog;
 use crate::multipart::{
-    InMemoryMultipart, MultipartError, MultipartManager, MultipartPart, MultipartUpload,
+    MultipartError, MultipartHooks, MultipartManager, MultipartPart, MultipartUpload,
+};
+use filesystem::catalog::{
+    BucketMetadata, Catalog, CatalogError, ObjectAttributes, ObjectDescription, StorageClass,
 };
-use filesystem::catalog::{Catalog, CatalogError};
-use filesystem::index::{IndexError, ListRequest, MutableIndex};
+use filesystem::index::{IndexError, ListDirection, ListRequest, MutableIndex};
+use filesystem::lifecycle::{Lifecycle, LifecycleAction, LifecycleReport};
+use filesystem::validation::KeyError;
 use security::apikey::{AuthError, StaticApiKeyValidator};
-use security::keystore::KeyStore;
+use security::audit::{AuditDecision, AuditError, AuditLog, AuditRecord};
+use security::keystore::{KeyStore, KeyStoreError};
+use security::masterkey::{KeyPurpose, MasterKey, SubKey};
+use security::permissions::Permission;
+use security::policy::{BucketPolicy, Decision, PolicyRequest, RESOURCE_ARN_PREFIX};
+use security::ratelimit::RateLimiter;
 use storage::object::{ObjectError, ObjectStore};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameError {
+    Catalog(CatalogError),
+    /// The bucket policy matches bucket names by wildcard, so it cannot be
+    /// moved to the new name.
+    PolicyNotPortable,
+}
+
 pub struct S3Service<C, O, S, I, M>
 where
     C: Catalog,
@@ -32,6 +56,14 @@ where
     multipart: M,
     auth_header: &'static str,
     events: EventLog,
+    clock: u64,
+    lifecycle: Lifecycle,
+    policies: BTreeMap<String, BucketPolicy>,
+    limiter: RateLimiter,
+    audit: AuditLog,
+    certificates: CertificateBindings,
+    /// Keys the salts of new API key secrets.
+    salt_key: SubKey,
 }
 
 impl<C, O, S, I, M> S3Service<C, O, S, I, M>
@@ -42,16 +74,160 @@ where
     I: MutableIndex,
     M: MultipartManager,
 {
-    pub fn new(catalog: C, store: O, keystore: S, index: I, multipart: M) -> Self {
+    /// `master` keys salts of new API key secrets and the audit chain;
+    /// `keystore` should pepper its hashes with the same master key.
+    pub fn new(
+        catalog: C,
+        store: O,
+        keystore: S,
+        index: I,
+        multipart: M,
+        master: &MasterKey,
+    ) -> Self {
         Self {
             catalog,
             store,
             keystore,
             index,
             multipart,
-            auth_header: "x-api-key",
+            auth_header: API_KEY_HEADER,
             events: EventLog::default(),
+            clock: 0,
+            lifecycle: Lifecycle::default(),
+            policies: BTreeMap::new(),
+            limiter: RateLimiter::default(),
+            audit: AuditLog::default().with_signing_key(master.derive(KeyPurpose::Signing)),
+            certificates: CertificateBindings::new(),
+            salt_key: master.derive(KeyPurpose::ApiKeyHashing),
+        }
+    }
+
+    /// Cross-checks the catalog, index and object store, optionally
+    /// repairing what disagrees.
+    pub fn fsck(&mut self, repair: bool) -> FsckReport {
+        let report = fsck::check(&mut self.catalog, &mut self.index, &mut self.store, repair);
+        self.events.record(format!(
+            "FSCK findings={} repaired={}",
+            report.findings.len(),
+            report.repaired
+        ));
+        report
+    }
+
+    /// Updates the service's notion of the current time, used to stamp
+    /// bucket creation. Read as Unix seconds when checking SigV4 request
+    /// dates.
+    pub fn set_clock(&mut self, ticks: u64) {
+        self.clock = ticks;
+    }
+
+    /// Client certificates accepted over mutual TLS, and the keys they
+    /// authenticate as.
+    pub fn certificates_mut(&mut self) -> &mut CertificateBindings {
+        &mut self.certificates
+    }
+
+    pub fn lifecycle_mut(&mut self) -> &mut Lifecycle {
+        &mut self.lifecycle
+    }
+
+    /// Replaces the rate limiter, e.g. to change its limits. It is driven
+    /// by the clock set through `set_clock`.
+    pub fn set_rate_limiter(&mut self, limiter: RateLimiter) {
+        self.limiter = limiter;
+    }
+
+    /// Advances the clock and, when the lifecycle timer has fired, applies
+    /// every bucket's lifecycle rules and logs each removal.
+    pub fn poll_lifecycle(&mut self, now: u64) -> Option<LifecycleReport> {
+        self.clock = now;
+        if !self.lifecycle.due(now) {
+            return None;
+        }
+        let report = self.lifecycle.evaluate(
+            now,
+            &mut self.catalog,
+            &mut self.store,
+            fsck::storage_key,
+            &mut MultipartHooks(&mut self.multipart),
+        );
+        for action in &report.actions {
+            let line = match action {
+                LifecycleAction::Expired { bucket, key, rule } => {
+                    self.index.remove(bucket, key);
+                    format!("LIFECYCLE_EXPIRE {}/{} rule={}", bucket, key, rule)
+                }
+                LifecycleAction::AbortedUpload {
+                    bucket,
+                    key,
+                    upload_id,
+                    rule,
+                } => format!(
+                    "LIFECYCLE_ABORT {} {}/{} rule={}",
+                    upload_id, bucket, key, rule
+                ),
+                LifecycleAction::ExpiredVersion {
+                    bucket,
+                    key,
+                    version_id,
+                    rule,
+                } => format!(
+                    "LIFECYCLE_EXPIRE_VERSION {}/{} version={} rule={}",
+                    bucket, key, version_id, rule
+                ),
+            };
+            self.events.record(line);
+        }
+        if report.failures > 0 {
+            self.events
+                .record(format!("LIFECYCLE_FAILURES {}", report.failures));
+        }
+        Some(report)
+    }
+
+    /// Renames a bucket across the catalog, object store, index and bucket
+    /// policy. The catalog rename is atomic; if moving object data fails
+    /// midway, the objects already moved and the catalog entry are moved
+    /// back.
+    pub fn rename_bucket(&mut self, from: &str, to: &str) -> Result<(), RenameError> {
+        let policy = match self.policies.get(from) {
+            Some(policy) => Some(
+                policy
+                    .rebind(from, to)
+                    .ok_or(RenameError::PolicyNotPortable)?,
+            ),
+            None => None,
+        };
+        let mut keys = Vec::new();
+        self.catalog
+            .list_objects(from, &mut |key, _| keys.push(key.to_string()))
+            .map_err(RenameError::Catalog)?;
+        self.catalog
+            .rename_bucket(from, to)
+            .map_err(RenameError::Catalog)?;
+        for (moved, key) in keys.iter().enumerate() {
+            let result = self
+                .store
+                .rename(&Self::storage_key(from, key), &Self::storage_key(to, key));
+            if matches!(result, Err(err) if err != ObjectError::NotFound) {
+                for key in &keys[..moved] {
+                    let _ = self
+                        .store
+                        .rename(&Self::storage_key(to, key), &Self::storage_key(from, key));
+                }
+                let _ = self.catalog.rename_bucket(to, from);
+                return Err(RenameError::Catalog(CatalogError::Backend));
+            }
         }
+        self.index.rename_bucket(from, to);
+        self.lifecycle.rename_bucket(from, to);
+        if let Some(policy) = policy {
+            self.policies.remove(from);
+            self.policies.insert(to.to_string(), policy);
+        }
+        self.events
+            .record(format!("RENAME_BUCKET {} -> {}", from, to));
+        Ok(())
     }
 
     pub fn catalog_mut(&mut self) -> &mut C {
@@ -62,6 +238,11 @@ where
         &mut self.keystore
     }
 
+    /// Authentication and authorization decisions, one per request.
+    pub fn audit_log(&self) -> &AuditLog {
+        &self.audit
+    }
+
     pub fn index_mut(&mut self) -> &mut I {
         &mut self.index
     }
@@ -75,6 +256,10 @@ where
         self.events.entries()
     }
 
+    fn error_response(error: S3Error<'_>) -> Response {
+        Self::response(error.status_code(), error.code().as_bytes().to_vec())
+    }
+
     fn response(status: u16, body: Vec<u8>) -> Response {
         Response {
             status,
@@ -90,8 +275,17 @@ where
         Self::response(status, Vec::new())
     }
 
+    fn invalid_key(reason: KeyError) -> Response {
+        match reason {
+            KeyError::TooLong => Self::response(400, b"KeyTooLongError".to_vec()),
+            KeyError::Empty | KeyError::ControlCharacter => {
+                Self::response(400, b"InvalidArgument".to_vec())
+            }
+        }
+    }
+
     fn storage_key(bucket: &str, key: &str) -> String {
-        format!("{}/{}", bucket, key)
+        fsck::storage_key(bucket, key)
     }
 
     fn bucket_exists(&self, bucket: &str) -> bool {
@@ -104,26 +298,66 @@ where
         found
     }
 
-    fn handle_put(&mut self, bucket: &str, key: &str, body: &[u8]) -> Response {
+    fn handle_put(
+        &mut self,
+        bucket: &str,
+        key: &str,
+        body: &[u8],
+        mut attributes: ObjectAttributes,
+    ) -> Response {
         if key.is_empty() {
             return Self::response(400, b"MissingObjectKey".to_vec());
         }
+        attributes.last_modified = self.clock;
+        attributes.etag = etag(body);
+        // Refuse writes the catalog would reject before the store
+        // overwrites any existing object data under the same key.
+        match self
+            .catalog
+            .check_put(bucket, key, body.len() as u64, &attributes)
+        {
+            Ok(()) => {}
+            Err(CatalogError::NotFound) => return Self::response(404, b"BucketNotFound".to_vec()),
+            Err(CatalogError::QuotaExceeded) => {
+                return Self::error_response(S3Error::QuotaExceeded(bucket))
+            }
+            Err(CatalogError::InvalidKey(reason)) => return Self::invalid_key(reason),
+            Err(CatalogError::InvalidMetadata) => {
+                return Self::response(400, b"MetadataTooLarge".to_vec())
+            }
+            Err(CatalogError::InvalidTag) => return Self::response(400, b"InvalidTag".to_vec()),
+            Err(_) => return Self::response(500, b"CatalogError".to_vec()),
+        }
+        let indexed = attributes.clone();
         let storage_key = Self::storage_key(bucket, key);
         match self.store.put(&storage_key, body) {
-            Ok(meta) => match self.catalog.put_object(bucket, key, meta) {
+            Ok(meta) => match self.catalog.put_object_with(bucket, key, meta, attributes) {
                 Ok(()) => {
-                    self.index.insert(bucket, key, meta);
+                    self.index.insert_with(bucket, key, meta, &indexed);
                     self.events
                         .record(format!("PUT {}/{} size={}", bucket, key, body.len()));
+                    self.record_soft_quota(bucket);
                     Self::empty_response(200)
                 }
                 Err(CatalogError::NotFound) => {
                     let _ = self.store.delete(&storage_key);
                     Self::response(404, b"BucketNotFound".to_vec())
                 }
-                Err(CatalogError::InvalidName) => {
+                Err(CatalogError::InvalidKey(reason)) => {
+                    let _ = self.store.delete(&storage_key);
+                    Self::invalid_key(reason)
+                }
+                Err(CatalogError::QuotaExceeded) => {
+                    let _ = self.store.delete(&storage_key);
+                    Self::error_response(S3Error::QuotaExceeded(bucket))
+                }
+                Err(CatalogError::InvalidMetadata) => {
+                    let _ = self.store.delete(&storage_key);
+                    Self::response(400, b"MetadataTooLarge".to_vec())
+                }
+                Err(CatalogError::InvalidTag) => {
                     let _ = self.store.delete(&storage_key);
-                    Self::response(400, b"InvalidName".to_vec())
+                    Self::response(400, b"InvalidTag".to_vec())
                 }
                 Err(_) => {
                     let _ = self.store.delete(&storage_key);
@@ -136,20 +370,158 @@ where
         }
     }
 
+    fn record_soft_quota(&mut self, bucket: &str) {
+        let (Ok(usage), Ok(quota)) = (self.catalog.usage(bucket), self.catalog.quota(bucket))
+        else {
+            return;
+        };
+        if quota.soft.exceeded_by(usage) {
+            self.events.record(format!(
+                "QUOTA_SOFT {} bytes={} objects={}",
+                bucket, usage.bytes, usage.objects
+            ));
+        }
+    }
+
+    /// Reads object attributes from a PUT's `Content-Type`,
+    /// `x-amz-storage-class`, `x-amz-tagging` and `x-amz-meta-*` headers.
+    fn object_attributes(request: &Request) -> Result<ObjectAttributes, Response> {
+        let storage_class = match request.header("x-amz-storage-class") {
+            Some(name) => StorageClass::parse(name)
+                .ok_or_else(|| Self::response(400, b"InvalidStorageClass".to_vec()))?,
+            None => StorageClass::default(),
+        };
+        let tags = match request.header("x-amz-tagging") {
+            Some(encoded) => parse_tags(encoded, '&')
+                .ok_or_else(|| Self::response(400, b"InvalidTag".to_vec()))?,
+            None => Vec::new(),
+        };
+        let user_metadata = request
+            .headers
+            .iter()
+            .filter_map(|header| {
+                let name = header.name.to_ascii_lowercase();
+                let name = name.strip_prefix("x-amz-meta-")?.to_string();
+                Some((name, header.value.clone()))
+            })
+            .collect();
+        Ok(ObjectAttributes {
+            content_type: request
+                .header("content-type")
+                .unwrap_or("application/octet-stream")
+                .to_string(),
+            storage_class,
+            user_metadata,
+            tags,
+            ..ObjectAttributes::default()
+        })
+    }
+
+    fn object_headers(object: &ObjectDescription) -> Vec<HttpHeader> {
+        let attributes = &object.attributes;
+        let header = |name: &str, value: &str| HttpHeader {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        let mut headers = vec![
+            header("Content-Type", &attributes.content_type),
+            header("ETag", &attributes.etag),
+            header("Last-Modified", &attributes.last_modified.to_string()),
+            header("x-amz-storage-class", attributes.storage_class.as_str()),
+        ];
+        for (name, value) in &attributes.user_metadata {
+            headers.push(header(&format!("x-amz-meta-{}", name), value));
+        }
+        if !attributes.tags.is_empty() {
+            headers.push(header(
+                "x-amz-tagging-count",
+                &attributes.tags.len().to_string(),
+            ));
+        }
+        headers
+    }
+
+    fn handle_head(&mut self, bucket: &str, key: &str) -> Response {
+        let object = match self.catalog.object_metadata(bucket, key) {
+            Ok(object) => object,
+            Err(_) => return Self::empty_response(404),
+        };
+        let mut response = Self::empty_response(200);
+        response.headers[0].value = object.meta.size.to_string();
+        response.headers.extend(Self::object_headers(&object));
+        response
+    }
+
     fn handle_get(&mut self, bucket: &str, key: &str) -> Response {
-        let meta = match self.catalog.object_metadata(bucket, key) {
-            Ok(meta) => meta,
+        let object = match self.catalog.object_metadata(bucket, key) {
+            Ok(object) => object,
             Err(_) => return Self::response(404, b"NoSuchKey".to_vec()),
         };
         let storage_key = Self::storage_key(bucket, key);
-        let mut buffer = vec![0u8; meta.size as usize];
+        let mut buffer = vec![0u8; object.meta.size as usize];
         match self.store.get(&storage_key, &mut buffer) {
-            Ok(_) => Self::response(200, buffer),
+            Ok(_) => {
+                let mut response = Self::response(200, buffer);
+                response.headers.extend(Self::object_headers(&object));
+                response
+            }
             Err(ObjectError::NotFound) => Self::response(404, b"NoSuchKey".to_vec()),
             Err(_) => Self::response(500, b"StorageError".to_vec()),
         }
     }
 
+    fn handle_put_tagging(&mut self, bucket: &str, key: &str, body: &[u8]) -> Response {
+        let Some(tags) = core::str::from_utf8(body)
+            .ok()
+            .and_then(|text| parse_tags(text, '\n'))
+        else {
+            return Self::response(400, b"InvalidTag".to_vec());
+        };
+        let count = tags.len();
+        match self.catalog.put_object_tagging(bucket, key, tags.clone()) {
+            Ok(()) => {
+                self.index.set_tags(bucket, key, &tags);
+                self.events
+                    .record(format!("PUT_TAGGING {}/{} tags={}", bucket, key, count));
+                Self::empty_response(200)
+            }
+            Err(err) => Self::tagging_error(err),
+        }
+    }
+
+    fn handle_get_tagging(&mut self, bucket: &str, key: &str) -> Response {
+        match self.catalog.object_tagging(bucket, key) {
+            Ok(tags) => {
+                let mut body = String::from("TagSet:\n");
+                for (name, value) in &tags {
+                    body.push_str(&format!("{}={}\n", name, value));
+                }
+                Self::response(200, body.into_bytes())
+            }
+            Err(err) => Self::tagging_error(err),
+        }
+    }
+
+    fn handle_delete_tagging(&mut self, bucket: &str, key: &str) -> Response {
+        match self.catalog.delete_object_tagging(bucket, key) {
+            Ok(()) => {
+                self.index.set_tags(bucket, key, &[]);
+                self.events
+                    .record(format!("DELETE_TAGGING {}/{}", bucket, key));
+                Self::empty_response(204)
+            }
+            Err(err) => Self::tagging_error(err),
+        }
+    }
+
+    fn tagging_error(err: CatalogError) -> Response {
+        match err {
+            CatalogError::NotFound => Self::response(404, b"NoSuchKey".to_vec()),
+            CatalogError::InvalidTag => Self::response(400, b"InvalidTag".to_vec()),
+            _ => Self::response(500, b"CatalogError".to_vec()),
+        }
+    }
+
     fn handle_delete(&mut self, bucket: &str, key: &str) -> Response {
         if self.catalog.remove_object(bucket, key).is_err() {
             return Self::response(404, b"NoSuchKey".to_vec());
@@ -173,7 +545,13 @@ where
             prefix: params.prefix.as_deref(),
             delimiter: params.delimiter,
             continuation: params.continuation_token.as_deref(),
-            max_keys: params.max_keys.unwrap_or(1000),
+            start_after: params.start_after.as_deref(),
+            direction: params.direction,
+            max_keys: params.max_keys.unwrap_or(1000).min(1000),
+            tag: params
+                .tag_key
+                .as_deref()
+                .map(|key| (key, params.tag_value.as_deref().unwrap_or(""))),
         };
         match self.index.list(&list_req) {
             Ok(result) => {
@@ -190,6 +568,10 @@ where
                         body.push('\n');
                     }
                 }
+                body.push_str(&format!(
+                    "KeyCount:{}\nIsTruncated:{}\n",
+                    result.key_count, result.is_truncated
+                ));
                 if let Some(token) = result.next_token {
                     body.push_str("NextToken:");
                     body.push_str(&token);
@@ -199,12 +581,18 @@ where
                 Self::response(200, body.into_bytes())
             }
             Err(IndexError::NotFound) => Self::response(404, b"BucketNotFound".to_vec()),
+            Err(IndexError::InvalidToken) => Self::response(400, b"InvalidArgument".to_vec()),
             Err(_) => Self::response(500, b"IndexError".to_vec()),
         }
     }
 
-    fn handle_create_bucket(&mut self, bucket: &str) -> Response {
-        match self.catalog.create_bucket(bucket) {
+    fn handle_create_bucket(&mut self, bucket: &str, owner: &str) -> Response {
+        let metadata = BucketMetadata {
+            created_at: self.clock,
+            owner: owner.to_string(),
+            ..BucketMetadata::default()
+        };
+        match self.catalog.create_bucket_with(bucket, metadata) {
             Ok(()) => {
                 self.events.record(format!("CREATE_BUCKET {}", bucket));
                 Self::empty_response(200)
@@ -212,7 +600,9 @@ where
             Err(CatalogError::AlreadyExists) => {
                 Self::response(409, b"BucketAlreadyExists".to_vec())
             }
-            Err(CatalogError::InvalidName) => Self::response(400, b"InvalidBucketName".to_vec()),
+            Err(CatalogError::InvalidBucketName(_)) => {
+                Self::response(400, b"InvalidBucketName".to_vec())
+            }
             Err(_) => Self::response(500, b"CatalogError".to_vec()),
         }
     }
@@ -221,6 +611,8 @@ where
         match self.catalog.delete_bucket(bucket) {
             Ok(()) => {
                 self.index.purge_bucket(bucket);
+                self.lifecycle.remove_bucket(bucket);
+                self.policies.remove(bucket);
                 self.events.record(format!("DELETE_BUCKET {}", bucket));
                 Self::empty_response(204)
             }
@@ -229,6 +621,35 @@ where
         }
     }
 
+    fn handle_put_policy(&mut self, bucket: &str, body: &[u8]) -> Response {
+        if !self.bucket_exists(bucket) {
+            return Self::response(404, b"NoSuchBucket".to_vec());
+        }
+        let parsed = core::str::from_utf8(body)
+            .ok()
+            .and_then(|text| BucketPolicy::parse(text).ok());
+        let Some(policy) = parsed else {
+            return Self::response(400, b"MalformedPolicy".to_vec());
+        };
+        self.policies.insert(bucket.to_string(), policy);
+        self.events.record(format!("PUT_POLICY {}", bucket));
+        Self::empty_response(204)
+    }
+
+    fn handle_get_policy(&self, bucket: &str) -> Response {
+        match self.policies.get(bucket) {
+            Some(policy) => Self::response(200, policy.source().as_bytes().to_vec()),
+            None => Self::response(404, b"NoSuchBucketPolicy".to_vec()),
+        }
+    }
+
+    fn handle_delete_policy(&mut self, bucket: &str) -> Response {
+        if self.policies.remove(bucket).is_some() {
+            self.events.record(format!("DELETE_POLICY {}", bucket));
+        }
+        Self::empty_response(204)
+    }
+
     fn handle_list_buckets(&mut self) -> Response {
         let mut body = String::new();
         body.push_str("Buckets:\n");
@@ -240,11 +661,16 @@ where
         Self::response(200, body.into_bytes())
     }
 
-    fn handle_initiate_multipart(&mut self, bucket: &str, key: &str) -> Response {
+    fn handle_initiate_multipart(
+        &mut self,
+        bucket: &str,
+        key: &str,
+        attributes: ObjectAttributes,
+    ) -> Response {
         if !self.bucket_exists(bucket) {
             return Self::response(404, b"BucketNotFound".to_vec());
         }
-        match self.multipart.initiate(bucket, key) {
+        match self.multipart.initiate(bucket, key, attributes, self.clock) {
             Ok(upload_id) => {
                 self.events
                     .record(format!("MP_INIT {} {}/{}", upload_id, bucket, key));
@@ -254,7 +680,16 @@ where
         }
     }
 
-    fn handle_upload_part(&mut self, upload_id: &str, part_number: u32, data: &[u8]) -> Response {
+    fn handle_upload_part(
+        &mut self,
+        bucket: &str,
+        upload_id: &str,
+        part_number: u32,
+        data: &[u8],
+    ) -> Response {
+        if !self.upload_in_bucket(bucket, upload_id) {
+            return Self::response(404, b"NoSuchUpload".to_vec());
+        }
         let part = MultipartPart {
             upload_id,
             part_number,
@@ -277,17 +712,30 @@ where
         }
     }
 
+    /// The caller was authorized for the bucket in the path, so an upload
+    /// it touches must belong to that bucket.
+    fn upload_in_bucket(&self, bucket: &str, upload_id: &str) -> bool {
+        let mut in_bucket = false;
+        self.multipart.pending(bucket, &mut |id, _, _| {
+            in_bucket |= id == upload_id;
+        });
+        in_bucket
+    }
+
     fn handle_complete_multipart(&mut self, bucket: &str, key: &str, upload_id: &str) -> Response {
+        if !self.upload_in_bucket(bucket, upload_id) {
+            return Self::response(404, b"NoSuchUpload".to_vec());
+        }
         let upload = MultipartUpload {
             upload_id,
             bucket,
             key,
         };
         match self.multipart.complete(&upload) {
-            Ok(data) => {
+            Ok(upload) => {
                 self.events
                     .record(format!("MP_COMPLETE {} {}/{}", upload_id, bucket, key));
-                self.handle_put(bucket, key, &data)
+                self.handle_put(bucket, key, &upload.data, upload.attributes)
             }
             Err(MultipartError::NotFound) => Self::response(404, b"NoSuchUpload".to_vec()),
             Err(MultipartError::InvalidState) => {
@@ -297,6 +745,9 @@ where
     }
 
     fn handle_abort_multipart(&mut self, bucket: &str, key: &str, upload_id: &str) -> Response {
+        if !self.upload_in_bucket(bucket, upload_id) {
+            return Self::response(404, b"NoSuchUpload".to_vec());
+        }
         let upload = MultipartUpload {
             upload_id,
             bucket,
@@ -315,13 +766,236 @@ where
         }
     }
 
-    fn auth_error_response(err: AuthError) -> Response {
-        let body = match err {
-            AuthError::Missing => b"MissingApiKey".to_vec(),
-            AuthError::Invalid => b"InvalidApiKey".to_vec(),
-            AuthError::Store(_) => b"InvalidApiKey".to_vec(),
+    /// Permission the route for a request needs; mirrors the dispatch in
+    /// `handle`. Creating and deleting buckets is administrative.
+    fn required_permission(method: &Method, path: &str, params: &QueryParams) -> Permission {
+        let (first, key) = path.split_once('/').unwrap_or((path, ""));
+        match first {
+            "_logs" | ADMIN_PREFIX => return Permission::Admin,
+            "" => return Permission::List,
+            _ => {}
+        }
+        if params.policy && key.is_empty() {
+            return Permission::Admin;
+        }
+        if (params.uploads && matches!(method, Method::Post)) || params.upload_id.is_some() {
+            return Permission::Multipart;
+        }
+        match method {
+            Method::Put | Method::Delete if key.is_empty() => Permission::Admin,
+            Method::Put | Method::Post => Permission::Write,
+            Method::Delete => Permission::Delete,
+            Method::Get if key.is_empty() => Permission::List,
+            _ => Permission::Read,
+        }
+    }
+
+    /// S3 action name a bucket route performs, as matched by bucket
+    /// policies; mirrors the dispatch in `handle`.
+    fn policy_action(method: &Method, key: &str, params: &QueryParams) -> &'static str {
+        if params.policy && key.is_empty() {
+            return match method {
+                Method::Put => "s3:PutBucketPolicy",
+                Method::Delete => "s3:DeleteBucketPolicy",
+                _ => "s3:GetBucketPolicy",
+            };
+        }
+        if params.uploads && matches!(method, Method::Post) {
+            return "s3:PutObject";
+        }
+        if params.upload_id.is_some() {
+            return match method {
+                Method::Delete => "s3:AbortMultipartUpload",
+                Method::Get => "s3:ListMultipartUploadParts",
+                _ => "s3:PutObject",
+            };
+        }
+        if params.tagging && !key.is_empty() {
+            return match method {
+                Method::Put => "s3:PutObjectTagging",
+                Method::Delete => "s3:DeleteObjectTagging",
+                _ => "s3:GetObjectTagging",
+            };
+        }
+        match method {
+            Method::Put if key.is_empty() => "s3:CreateBucket",
+            Method::Delete if key.is_empty() => "s3:DeleteBucket",
+            _ if key.is_empty() => "s3:ListBucket",
+            Method::Put | Method::Post => "s3:PutObject",
+            Method::Delete => "s3:DeleteObject",
+            _ => "s3:GetObject",
+        }
+    }
+
+    /// Action and resource a request is audited under.
+    fn audit_target(method: &Method, path: &str, params: &QueryParams) -> (&'static str, String) {
+        let (first, key) = path.split_once('/').unwrap_or((path, ""));
+        match first {
+            "" => ("s3:ListAllMyBuckets", String::from("/")),
+            "_logs" => ("admin:GetLogs", format!("/{}", path)),
+            ADMIN_PREFIX => (admin::action(path, method), format!("/{}", path)),
+            bucket if key.is_empty() => (
+                Self::policy_action(method, key, params),
+                format!("{}{}", RESOURCE_ARN_PREFIX, bucket),
+            ),
+            bucket => (
+                Self::policy_action(method, key, params),
+                format!("{}{}/{}", RESOURCE_ARN_PREFIX, bucket, key),
+            ),
+        }
+    }
+
+    fn auth_error_code(err: AuthError) -> &'static str {
+        match err {
+            AuthError::Missing => "MissingApiKey",
+            AuthError::Invalid => "InvalidApiKey",
+            AuthError::SignatureMismatch => "SignatureDoesNotMatch",
+            AuthError::ClockSkew => "RequestTimeTooSkewed",
+            AuthError::Expired => "RequestExpired",
+            AuthError::Revoked => "KeyRevoked",
+            AuthError::Disabled => "KeyDisabled",
+            AuthError::KeyExpired => "KeyExpired",
+            AuthError::UnboundCertificate => "UnknownClientCertificate",
+            AuthError::Store(_) => "InvalidApiKey",
+        }
+    }
+
+    fn handle_admin(&mut self, method: &Method, path: &str, body: &[u8]) -> Response {
+        let Some(route) = AdminRoute::parse(path) else {
+            return Self::response(404, b"NoSuchRoute".to_vec());
+        };
+        let Ok(body) = core::str::from_utf8(body) else {
+            return Self::response(400, b"MalformedKeySpec".to_vec());
+        };
+        match (route, method) {
+            (AdminRoute::Keys, Method::Get) => {
+                let mut listing = String::from("Keys:\n");
+                self.keystore
+                    .keys(&mut |entry| admin::describe_key(&mut listing, &entry));
+                Self::response(200, listing.into_bytes())
+            }
+            (AdminRoute::Key(id), Method::Get) => match self.keystore.lookup(id) {
+                Ok(entry) => {
+                    let mut line = String::new();
+                    admin::describe_key(&mut line, &entry);
+                    Self::response(200, line.into_bytes())
+                }
+                Err(err) => Self::keystore_error(err),
+            },
+            (AdminRoute::Key(id), Method::Put) => self.handle_create_key(id, body),
+            (AdminRoute::Key(id), Method::Delete) => match self.keystore.revoke(id) {
+                Ok(()) => {
+                    self.events.record(format!("ADMIN_REVOKE_KEY {}", id));
+                    Self::empty_response(204)
+                }
+                Err(err) => Self::keystore_error(err),
+            },
+            (AdminRoute::RotateKey(id), Method::Post) => self.handle_rotate_key(id, body),
+            (AdminRoute::Audit, Method::Get) => {
+                let mut listing = String::from("Audit:\n");
+                for entry in self.audit.entries() {
+                    listing.push_str(&format!(
+                        "{} {} {} {} {} {} {}\n",
+                        entry.seq,
+                        entry.time,
+                        entry.key_id.as_deref().unwrap_or("-"),
+                        entry.action,
+                        entry.resource,
+                        entry.decision.as_str(),
+                        entry.reason
+                    ));
+                }
+                let chain = match self.audit.verify(None) {
+                    Ok(()) => "intact",
+                    Err(AuditError::Edited) => "edited",
+                    Err(AuditError::Removed) => "removed",
+                    Err(AuditError::Truncated) => "truncated",
+                };
+                listing.push_str(&format!("Chain: {}\nHead: ", chain));
+                for byte in self.audit.head() {
+                    listing.push_str(&format!("{:02x}", byte));
+                }
+                listing.push('\n');
+                Self::response(200, listing.into_bytes())
+            }
+            (AdminRoute::Buckets, Method::Get) => {
+                let mut listing = String::from("Buckets:\n");
+                self.catalog.list_buckets(&mut |info| {
+                    listing.push_str(&format!(
+                        "{} objects={} bytes={} owner={}\n",
+                        info.name, info.object_count, info.bytes_used, info.metadata.owner
+                    ));
+                });
+                Self::response(200, listing.into_bytes())
+            }
+            (AdminRoute::Bucket(name), Method::Get) => match self.catalog.describe_bucket(name) {
+                Ok(bucket) => {
+                    let limit =
+                        |value: Option<u64>| value.map_or("-".to_string(), |v| v.to_string());
+                    let body = format!(
+                        "Bucket: {}\nObjects: {}\nBytes: {}\nOwner: {}\n\
+                         SoftQuota: bytes={} objects={}\nHardQuota: bytes={} objects={}\n",
+                        bucket.name,
+                        bucket.usage.objects,
+                        bucket.usage.bytes,
+                        bucket.metadata.owner,
+                        limit(bucket.quota.soft.max_bytes),
+                        limit(bucket.quota.soft.max_objects),
+                        limit(bucket.quota.hard.max_bytes),
+                        limit(bucket.quota.hard.max_objects),
+                    );
+                    Self::response(200, body.into_bytes())
+                }
+                Err(CatalogError::NotFound) => Self::response(404, b"NoSuchBucket".to_vec()),
+                Err(_) => Self::response(500, b"CatalogError".to_vec()),
+            },
+            _ => Self::response(405, b"MethodNotAllowed".to_vec()),
+        }
+    }
+
+    fn handle_create_key(&mut self, id: &str, body: &str) -> Response {
+        let Some(spec) = NewKey::parse(body) else {
+            return Self::response(400, b"MalformedKeySpec".to_vec());
+        };
+        let salt = admin::derive_salt(&self.salt_key, id, self.clock, &[0; 32]);
+        match self.keystore.insert(spec.entry(id, salt)) {
+            Ok(()) => {
+                self.events.record(format!("ADMIN_CREATE_KEY {}", id));
+                Self::empty_response(200)
+            }
+            Err(err) => Self::keystore_error(err),
+        }
+    }
+
+    fn handle_rotate_key(&mut self, id: &str, body: &str) -> Response {
+        let Some(rotation) = Rotation::parse(body) else {
+            return Self::response(400, b"MalformedKeySpec".to_vec());
+        };
+        let previous = match self.keystore.lookup(id) {
+            Ok(entry) => entry.hash,
+            Err(err) => return Self::keystore_error(err),
         };
-        Self::response(403, body)
+        let salt = admin::derive_salt(&self.salt_key, id, self.clock, &previous);
+        let grace_until = self.clock.saturating_add(rotation.grace);
+        match self.keystore.rotate(id, rotation.secret, salt, grace_until) {
+            Ok(()) => {
+                self.events.record(format!("ADMIN_ROTATE_KEY {}", id));
+                Self::empty_response(200)
+            }
+            Err(err) => Self::keystore_error(err),
+        }
+    }
+
+    fn keystore_error(err: KeyStoreError) -> Response {
+        match err {
+            KeyStoreError::NotFound => Self::response(404, b"NoSuchApiKey".to_vec()),
+            KeyStoreError::AlreadyExists => Self::response(409, b"KeyAlreadyExists".to_vec()),
+            KeyStoreError::Revoked => Self::response(409, b"KeyRevoked".to_vec()),
+            KeyStoreError::Full => Self::response(507, b"KeyStoreFull".to_vec()),
+            KeyStoreError::Storage | KeyStoreError::Corrupt => {
+                Self::response(500, b"KeyStoreError".to_vec())
+            }
+        }
     }
 
     fn handle_logs(&self) -> Response {
@@ -344,16 +1018,116 @@ where
     M: MultipartManager,
 {
     fn handle(&mut self, request: &Request) -> Response {
-        let validator = StaticApiKeyValidator::new(&self.keystore);
-        if let Err(err) = authenticate_request(request, &validator, &HeaderAuth, self.auth_header) {
-            return Self::auth_error_response(err);
-        }
-
         let trimmed = request.path.trim_start_matches('/');
-        let (path, query) = match trimmed.split_once('?') {
+        let (raw_path, query) = match trimmed.split_once('?') {
             Some((p, q)) => (p, Some(q)),
             None => (trimmed, None),
         };
+        // Routing, policies and storage all see the decoded bucket and key,
+        // as signature verification does.
+        let Some(decoded) = uri_decode(raw_path).and_then(|bytes| String::from_utf8(bytes).ok())
+        else {
+            return Self::response(400, b"InvalidURI".to_vec());
+        };
+        let path = decoded.as_str();
+        let params = QueryParams::parse(query.unwrap_or(""));
+
+        self.limiter.poll(self.clock);
+        let (action, resource) = Self::audit_target(&request.method, path, &params);
+        let now = self.clock;
+        let audit = |log: &mut AuditLog, key_id, decision, reason| {
+            log.record(
+                now,
+                AuditRecord {
+                    key_id,
+                    action,
+                    resource: &resource,
+                    decision,
+                    reason,
+                },
+            );
+        };
+        let client = request.remote_addr;
+        if let Some(addr) = client {
+            if self.limiter.check_client(addr, self.clock).is_err() {
+                audit(&mut self.audit, None, AuditDecision::Deny, "SlowDown");
+                return Self::response(503, b"SlowDown".to_vec());
+            }
+        }
+
+        let caller = {
+            let validator = StaticApiKeyValidator::new(&self.keystore).at(self.clock);
+            let authenticated = if PresignedAuth::applies(request) {
+                authenticate_request(request, &validator, &PresignedAuth::new(self.clock))
+            } else if SigV4Auth::applies(request) {
+                authenticate_request(request, &validator, &SigV4Auth::new(self.clock))
+            } else if MtlsAuth::applies(request) && request.header(self.auth_header).is_none() {
+                // Explicit credentials take precedence over the certificate.
+                authenticate_request(request, &validator, &MtlsAuth::new(&self.certificates))
+            } else {
+                authenticate_request(request, &validator, &HeaderAuth::new(self.auth_header))
+            };
+            let entry = match authenticated {
+                Ok(entry) => entry,
+                Err(err) => {
+                    // Count guesses at credentials, not malformed requests.
+                    let guess = matches!(
+                        err,
+                        AuthError::Invalid | AuthError::SignatureMismatch | AuthError::Store(_)
+                    );
+                    if let (Some(addr), true) = (client, guess) {
+                        self.limiter.record_failure(addr, self.clock);
+                    }
+                    let code = Self::auth_error_code(err);
+                    audit(&mut self.audit, None, AuditDecision::Deny, code);
+                    return Self::response(403, code.as_bytes().to_vec());
+                }
+            };
+            let key_id = Some(entry.key_id);
+            if let Some(addr) = client {
+                self.limiter.record_success(addr);
+            }
+            if self.limiter.check_key(entry.key_id).is_err() {
+                audit(&mut self.audit, key_id, AuditDecision::Deny, "SlowDown");
+                return Self::response(503, b"SlowDown".to_vec());
+            }
+            let scope = match path.split('/').next() {
+                Some("" | "_logs" | ADMIN_PREFIX) => None,
+                first => first,
+            };
+            let permission = Self::required_permission(&request.method, path, &params);
+            let decision = match scope.and_then(|bucket| Some((bucket, self.policies.get(bucket)?)))
+            {
+                Some((bucket, policy)) => {
+                    let key = path.split_once('/').map_or("", |(_, key)| key);
+                    policy.evaluate(&PolicyRequest {
+                        principal: entry.key_id,
+                        action,
+                        bucket,
+                        key: (!key.is_empty()).then_some(key),
+                        prefix: params.prefix.as_deref(),
+                        source_ip: request.remote_addr,
+                    })
+                }
+                None => Decision::NotApplicable,
+            };
+            // A bucket policy can deny what the key allows, or grant what
+            // the key's own permissions and scope do not.
+            let (allowed, reason) = match decision {
+                Decision::Deny => (false, "PolicyDeny"),
+                Decision::Allow => (true, "PolicyAllow"),
+                Decision::NotApplicable if entry.authorizes(scope, permission) => {
+                    (true, "KeyPermission")
+                }
+                Decision::NotApplicable => (false, "AccessDenied"),
+            };
+            if !allowed {
+                audit(&mut self.audit, key_id, AuditDecision::Deny, reason);
+                return Self::response(403, b"AccessDenied".to_vec());
+            }
+            audit(&mut self.audit, key_id, AuditDecision::Allow, reason);
+            entry.key_id.to_string()
+        };
 
         if path == "_logs" {
             return match request.method {
@@ -362,6 +1136,10 @@ where
             };
         }
 
+        if path.split('/').next() == Some(ADMIN_PREFIX) {
+            return self.handle_admin(&request.method, path, &request.body);
+        }
+
         if path.is_empty() {
             return match request.method {
                 Method::Get => self.handle_list_buckets(),
@@ -375,17 +1153,19 @@ where
             _ => return Self::response(400, b"MissingBucket".to_vec()),
         };
         let key = parts.next().unwrap_or("");
-        let params = QueryParams::parse(query.unwrap_or(""));
 
         if params.uploads && matches!(request.method, Method::Post) {
-            return self.handle_initiate_multipart(bucket, key);
+            return match Self::object_attributes(request) {
+                Ok(attributes) => self.handle_initiate_multipart(bucket, key, attributes),
+                Err(response) => response,
+            };
         }
 
         if let Some(ref upload_id) = params.upload_id {
             return match request.method {
                 Method::Put => {
                     if let Some(part_number) = params.part_number {
-                        self.handle_upload_part(upload_id, part_number, &request.body)
+                        self.handle_upload_part(bucket, upload_id, part_number, &request.body)
                     } else {
                         Self::response(400, b"MissingPartNumber".to_vec())
                     }
@@ -396,11 +1176,33 @@ where
             };
         }
 
+        if params.policy && key.is_empty() {
+            return match request.method {
+                Method::Put => self.handle_put_policy(bucket, &request.body),
+                Method::Get => self.handle_get_policy(bucket),
+                Method::Delete => self.handle_delete_policy(bucket),
+                _ => Self::response(405, b"MethodNotAllowed".to_vec()),
+            };
+        }
+
+        if params.tagging && !key.is_empty() {
+            return match request.method {
+                Method::Put => self.handle_put_tagging(bucket, key, &request.body),
+                Method::Get => self.handle_get_tagging(bucket, key),
+                Method::Delete => self.handle_delete_tagging(bucket, key),
+                _ => Self::response(405, b"MethodNotAllowed".to_vec()),
+            };
+        }
+
         match request.method {
-            Method::Put if key.is_empty() => self.handle_create_bucket(bucket),
-            Method::Put => self.handle_put(bucket, key, &request.body),
+            Method::Put if key.is_empty() => self.handle_create_bucket(bucket, &caller),
+            Method::Put => match Self::object_attributes(request) {
+                Ok(attributes) => self.handle_put(bucket, key, &request.body, attributes),
+                Err(response) => response,
+            },
             Method::Get if key.is_empty() => self.handle_list(bucket, query),
             Method::Get => self.handle_get(bucket, key),
+            Method::Head if !key.is_empty() => self.handle_head(bucket, key),
             Method::Delete if key.is_empty() => self.handle_delete_bucket(bucket),
             Method::Delete => self.handle_delete(bucket, key),
             _ => Self::response(405, b"MethodNotAllowed".to_vec()),
@@ -408,11 +1210,41 @@ where
     }
 }
 
+/// Quoted FNV-1a digest of the object body. Stable for identical content,
+/// but not the MD5 that AWS reports for single-part uploads.
+fn etag(data: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("\"{:016x}\"", hash)
+}
+
+/// Parses `key=value` tag pairs separated by `separator`, as used by the
+/// `x-amz-tagging` header (`&`) and tagging request bodies (newlines).
+fn parse_tags(text: &str, separator: char) -> Option<Vec<(String, String)>> {
+    text.split(separator)
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (!name.is_empty()).then(|| (name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
 struct QueryParams {
     prefix: Option<String>,
     delimiter: Option<char>,
     continuation_token: Option<String>,
+    start_after: Option<String>,
+    direction: ListDirection,
     max_keys: Option<usize>,
+    tag_key: Option<String>,
+    tag_value: Option<String>,
+    tagging: bool,
+    policy: bool,
     uploads: bool,
     upload_id: Option<String>,
     part_number: Option<u32>,
@@ -424,7 +1256,13 @@ impl QueryParams {
             prefix: None,
             delimiter: None,
             continuation_token: None,
+            start_after: None,
+            direction: ListDirection::Ascending,
             max_keys: None,
+            tag_key: None,
+            tag_value: None,
+            tagging: false,
+            policy: false,
             uploads: false,
             upload_id: None,
             part_number: None,
@@ -441,7 +1279,15 @@ impl QueryParams {
                 "prefix" => params.prefix = Some(value.replace('%', "")),
                 "delimiter" => params.delimiter = value.chars().next(),
                 "continuation-token" => params.continuation_token = Some(value.to_string()),
+                "start-after" => params.start_after = Some(value.to_string()),
+                "direction" if value == "descending" => {
+                    params.direction = ListDirection::Descending
+                }
                 "max-keys" => params.max_keys = value.parse().ok(),
+                "tag-key" => params.tag_key = Some(value.to_string()),
+                "tag-value" => params.tag_value = Some(value.to_string()),
+                "tagging" => params.tagging = true,
+                "policy" => params.policy = true,
                 "uploads" => params.uploads = true,
                 "uploadId" => params.upload_id = Some(value.to_string()),
                 "partNumber" => params.part_number = value.parse().ok(),
@@ -456,9 +1302,11 @@ impl QueryParams {
 mod tests {
     use super::*;
     use crate::http::{Header, Method};
+    use crate::multipart::InMemoryMultipart;
     use filesystem::catalog::InMemoryCatalog;
     use filesystem::index::InMemoryIndex;
-    use security::keystore::{ApiKeyEntry, InMemoryKeyStore};
+    use security::keystore::{ApiKeyEntry, InMemoryKeyStore, SALT_LEN};
+    use security::permissions::{Permissions, ANY_BUCKET};
     use storage::object::InMemoryObjectStore;
 
     fn make_request(method: Method, path: &str, key: Option<&str>, body: &[u8]) -> Request {
@@ -474,6 +1322,8 @@ mod tests {
             path: path.to_string(),
             headers,
             body: body.to_vec(),
+            remote_addr: None,
+            client_cert: None,
         }
     }
 
@@ -484,22 +1334,25 @@ mod tests {
         InMemoryIndex,
         InMemoryMultipart,
     > {
+        let master = MasterKey::from_bytes([7; 32]);
         let mut service = S3Service::new(
             InMemoryCatalog::new(),
             InMemoryObjectStore::new(),
-            InMemoryKeyStore::new(),
+            InMemoryKeyStore::new().with_master_key(&master),
             InMemoryIndex::new(),
             InMemoryMultipart::new(),
+            &master,
         );
         service.catalog_mut().create_bucket("photos").unwrap();
         service
             .keystore_mut()
-            .insert(ApiKeyEntry {
-                key_id: "abc123",
-                hash: [0; 32],
-                bucket: "photos",
-                permissions: 1,
-            })
+            .insert(ApiKeyEntry::with_secret(
+                "abc123",
+                "s3cret",
+                [0; SALT_LEN],
+                ANY_BUCKET,
+                Permissions::ALL,
+            ))
             .unwrap();
         service
     }
@@ -511,21 +1364,24 @@ mod tests {
         InMemoryIndex,
         InMemoryMultipart,
     > {
+        let master = MasterKey::from_bytes([7; 32]);
         let mut service = S3Service::new(
             InMemoryCatalog::new(),
             InMemoryObjectStore::new(),
-            InMemoryKeyStore::new(),
+            InMemoryKeyStore::new().with_master_key(&master),
             InMemoryIndex::new(),
             InMemoryMultipart::new(),
+            &master,
         );
         service
             .keystore_mut()
-            .insert(ApiKeyEntry {
-                key_id: "abc123",
-                hash: [0; 32],
-                bucket: "photos",
-                permissions: 1,
-            })
+            .insert(ApiKeyEntry::with_secret(
+                "abc123",
+                "s3cret",
+                [0; SALT_LEN],
+                ANY_BUCKET,
+                Permissions::ALL,
+            ))
             .unwrap();
         service
     }
@@ -533,12 +1389,23 @@ mod tests {
     #[test]
     fn multipart_flow() {
         let mut service = new_service();
-        let init_resp = service.handle(&make_request(
+        let mut init = make_request(
             Method::Post,
             "/photos/album.zip?uploads",
-            Some("abc123"),
+            Some("abc123:s3cret"),
             &[],
-        ));
+        );
+        for (name, value) in [
+            ("Content-Type", "application/zip"),
+            ("x-amz-tagging", "album=summer"),
+            ("X-Amz-Meta-Camera", "pinhole"),
+        ] {
+            init.headers.push(Header {
+                name: name.to_string(),
+                value: value.to_string(),
+            });
+        }
+        let init_resp = service.handle(&init);
         assert_eq!(init_resp.status, 200);
         let upload_id = core::str::from_utf8(&init_resp.body)
             .unwrap()
@@ -549,7 +1416,7 @@ mod tests {
         let part_resp = service.handle(&make_request(
             Method::Put,
             &format!("/photos/album.zip?partNumber=1&uploadId={}", upload_id),
-            Some("abc123"),
+            Some("abc123:s3cret"),
             b"chunk",
         ));
         assert_eq!(part_resp.status, 200);
@@ -557,7 +1424,7 @@ mod tests {
         let complete_resp = service.handle(&make_request(
             Method::Post,
             &format!("/photos/album.zip?uploadId={}", upload_id),
-            Some("abc123"),
+            Some("abc123:s3cret"),
             &[],
         ));
         assert_eq!(complete_resp.status, 200);
@@ -565,10 +1432,850 @@ mod tests {
         let get_resp = service.handle(&make_request(
             Method::Get,
             "/photos/album.zip",
-            Some("abc123"),
+            Some("abc123:s3cret"),
             &[],
         ));
         assert_eq!(get_resp.status, 200);
         assert_eq!(get_resp.body, b"chunk");
+
+        // Attributes given at initiate apply to the completed object.
+        let attributes = service
+            .catalog_mut()
+            .object_metadata("photos", "album.zip")
+            .unwrap()
+            .attributes;
+        assert_eq!(attributes.content_type, "application/zip");
+        assert_eq!(
+            attributes.tags,
+            [("album".to_string(), "summer".to_string())]
+        );
+        assert_eq!(
+            attributes.user_metadata,
+            [("camera".to_string(), "pinhole".to_string())]
+        );
+    }
+
+    #[test]
+    fn list_paginates_with_opaque_tokens() {
+        let mut service = new_service();
+        for key in ["a.txt", "b.txt", "c.txt"] {
+            let resp = service.handle(&make_request(
+                Method::Put,
+                &format!("/photos/{}", key),
+                Some("abc123:s3cret"),
+                b"x",
+            ));
+            assert_eq!(resp.status, 200);
+        }
+
+        let first = service.handle(&make_request(
+            Method::Get,
+            "/photos?max-keys=2",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        let body = String::from_utf8(first.body).unwrap();
+        assert!(body.contains("KeyCount:2\nIsTruncated:true"));
+        let token = body.rsplit("NextToken:").next().unwrap();
+
+        let rest = service.handle(&make_request(
+            Method::Get,
+            &format!("/photos?continuation-token={}", token),
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        let body = String::from_utf8(rest.body).unwrap();
+        assert_eq!(body, "Objects:\nc.txt\nKeyCount:1\nIsTruncated:false\n");
+
+        let bad = service.handle(&make_request(
+            Method::Get,
+            "/photos?continuation-token=zz",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert_eq!(bad.status, 400);
+    }
+
+    #[test]
+    fn head_and_get_return_object_attributes() {
+        let mut service = new_service();
+        service.set_clock(1700);
+        let mut put = make_request(
+            Method::Put,
+            "/photos/cat.png",
+            Some("abc123:s3cret"),
+            b"meow",
+        );
+        for (name, value) in [
+            ("Content-Type", "image/png"),
+            ("x-amz-storage-class", "STANDARD_IA"),
+            ("X-Amz-Meta-Camera", "pinhole"),
+        ] {
+            put.headers.push(Header {
+                name: name.to_string(),
+                value: value.to_string(),
+            });
+        }
+        assert_eq!(service.handle(&put).status, 200);
+
+        let head = service.handle(&make_request(
+            Method::Head,
+            "/photos/cat.png",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert_eq!(head.status, 200);
+        assert!(head.body.is_empty());
+        let header = |name: &str| {
+            head.headers
+                .iter()
+                .find(|h| h.name == name)
+                .map(|h| h.value.clone())
+        };
+        assert_eq!(header("Content-Length").as_deref(), Some("4"));
+        assert_eq!(header("Content-Type").as_deref(), Some("image/png"));
+        assert_eq!(header("Last-Modified").as_deref(), Some("1700"));
+        assert_eq!(
+            header("x-amz-storage-class").as_deref(),
+            Some("STANDARD_IA")
+        );
+        assert_eq!(header("x-amz-meta-camera").as_deref(), Some("pinhole"));
+
+        let get = service.handle(&make_request(
+            Method::Get,
+            "/photos/cat.png",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        let etag = get.headers.iter().find(|h| h.name == "ETag").unwrap();
+        assert_eq!(Some(etag.value.clone()), header("ETag"));
+
+        put.headers.push(Header {
+            name: "x-amz-storage-class".to_string(),
+            value: "SHELF".to_string(),
+        });
+        put.headers.retain(|h| h.value != "STANDARD_IA");
+        assert_eq!(service.handle(&put).status, 400);
+
+        // A rejected overwrite leaves the stored object intact.
+        let mut overwrite = make_request(
+            Method::Put,
+            "/photos/cat.png",
+            Some("abc123:s3cret"),
+            b"woof",
+        );
+        overwrite.headers.push(Header {
+            name: "x-amz-meta-notes".to_string(),
+            value: "x".repeat(ObjectAttributes::MAX_USER_METADATA_LEN),
+        });
+        let rejected = service.handle(&overwrite);
+        assert_eq!(rejected.body, b"MetadataTooLarge");
+        let get = service.handle(&make_request(
+            Method::Get,
+            "/photos/cat.png",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert_eq!((get.status, get.body), (200, b"meow".to_vec()));
+    }
+
+    #[test]
+    fn tagging_routes_and_list_filter() {
+        let mut service = new_service();
+        let mut put = make_request(Method::Put, "/photos/a.jpg", Some("abc123:s3cret"), b"a");
+        put.headers.push(Header {
+            name: "x-amz-tagging".to_string(),
+            value: "tier=hot&team=media".to_string(),
+        });
+        assert_eq!(service.handle(&put).status, 200);
+        for path in ["/photos/b.jpg", "/photos/c.jpg"] {
+            let req = make_request(Method::Put, path, Some("abc123:s3cret"), b"b");
+            assert_eq!(service.handle(&req).status, 200);
+        }
+        let tag = make_request(
+            Method::Put,
+            "/photos/c.jpg?tagging",
+            Some("abc123:s3cret"),
+            b"tier=hot\n",
+        );
+        assert_eq!(service.handle(&tag).status, 200);
+
+        let list = service.handle(&make_request(
+            Method::Get,
+            "/photos?tag-key=tier&tag-value=hot",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        let body = core::str::from_utf8(&list.body).unwrap();
+        assert!(body.starts_with("Objects:\na.jpg\nc.jpg\nKeyCount:2\n"));
+
+        let get = service.handle(&make_request(
+            Method::Get,
+            "/photos/a.jpg?tagging",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert_eq!(get.body, b"TagSet:\ntier=hot\nteam=media\n");
+
+        let delete = make_request(
+            Method::Delete,
+            "/photos/a.jpg?tagging",
+            Some("abc123:s3cret"),
+            &[],
+        );
+        assert_eq!(service.handle(&delete).status, 204);
+        let list = service.handle(&make_request(
+            Method::Get,
+            "/photos?tag-key=tier&tag-value=hot",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert!(list.body.starts_with(b"Objects:\nc.jpg\nKeyCount:1\n"));
+
+        let missing = make_request(
+            Method::Put,
+            "/photos/zzz?tagging",
+            Some("abc123:s3cret"),
+            b"k=v",
+        );
+        assert_eq!(service.handle(&missing).status, 404);
+    }
+
+    #[test]
+    fn lifecycle_expires_objects_and_aborts_uploads() {
+        use filesystem::lifecycle::LifecycleRule;
+
+        let mut service = new_service();
+        let put = make_request(
+            Method::Put,
+            "/photos/tmp/a.jpg",
+            Some("abc123:s3cret"),
+            b"a",
+        );
+        assert_eq!(service.handle(&put).status, 200);
+        let init = make_request(
+            Method::Post,
+            "/photos/big.zip?uploads",
+            Some("abc123:s3cret"),
+            &[],
+        );
+        assert_eq!(service.handle(&init).status, 200);
+        service
+            .lifecycle_mut()
+            .set_rules(
+                "photos",
+                vec![LifecycleRule {
+                    id: "cleanup".to_string(),
+                    enabled: true,
+                    prefix: String::new(),
+                    expire_after: Some(10),
+                    abort_incomplete_after: Some(10),
+                    ..LifecycleRule::default()
+                }],
+            )
+            .unwrap();
+
+        assert!(service.poll_lifecycle(0).is_none());
+        let report = service.poll_lifecycle(3600).unwrap();
+        assert_eq!(report.actions.len(), 2);
+        let list = service.handle(&make_request(
+            Method::Get,
+            "/photos",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert!(list.body.starts_with(b"Objects:\nKeyCount:0\n"));
+        assert!(service
+            .events()
+            .iter()
+            .any(|e| e == "LIFECYCLE_EXPIRE photos/tmp/a.jpg rule=cleanup"));
+        assert!(service.events().iter().any(
+            |e| e.starts_with("LIFECYCLE_ABORT") && e.ends_with("photos/big.zip rule=cleanup")
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_bucket_names_and_keys() {
+        let mut service = new_empty_service();
+        let create = service.handle(&make_request(
+            Method::Put,
+            "/My_Bucket",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert_eq!(create.status, 400);
+        assert_eq!(create.body, b"InvalidBucketName");
+
+        let mut service = new_service();
+        let path = format!("/photos/{}", "k".repeat(1025));
+        let put = service.handle(&make_request(
+            Method::Put,
+            &path,
+            Some("abc123:s3cret"),
+            b"x",
+        ));
+        assert_eq!(put.status, 400);
+        assert_eq!(put.body, b"KeyTooLongError");
+        assert_eq!(service.catalog_mut().usage("photos").unwrap().objects, 0);
+    }
+
+    #[test]
+    fn put_over_hard_quota_keeps_existing_object() {
+        use filesystem::catalog::{BucketQuota, Quota};
+
+        let mut service = new_service();
+        service
+            .catalog_mut()
+            .set_quota(
+                "photos",
+                BucketQuota {
+                    soft: Quota {
+                        max_bytes: Some(2),
+                        max_objects: None,
+                    },
+                    hard: Quota {
+                        max_bytes: Some(4),
+                        max_objects: None,
+                    },
+                },
+            )
+            .unwrap();
+        let put = |service: &mut S3Service<_, _, _, _, _>, body: &[u8]| {
+            service
+                .handle(&make_request(
+                    Method::Put,
+                    "/photos/a",
+                    Some("abc123:s3cret"),
+                    body,
+                ))
+                .status
+        };
+        assert_eq!(put(&mut service, b"abc"), 200);
+        assert!(service
+            .events()
+            .iter()
+            .any(|e| e.starts_with("QUOTA_SOFT photos")));
+        assert_eq!(put(&mut service, b"abcde"), 403);
+
+        let get = service.handle(&make_request(
+            Method::Get,
+            "/photos/a",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert_eq!(get.body, b"abc");
+    }
+
+    #[test]
+    fn rename_bucket_moves_data_and_listing() {
+        let mut service = new_empty_service();
+        service.set_clock(7);
+        let create = service.handle(&make_request(
+            Method::Put,
+            "/old",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert_eq!(create.status, 200);
+        service.handle(&make_request(
+            Method::Put,
+            "/old/a.txt",
+            Some("abc123:s3cret"),
+            b"data",
+        ));
+        service.handle(&make_request(
+            Method::Put,
+            "/old/secret/b.txt",
+            Some("abc123:s3cret"),
+            b"hidden",
+        ));
+        let policy = br#"{"Statement": {"Effect": "Deny", "Principal": "*",
+            "Action": "s3:GetObject", "Resource": "arn:aws:s3:::old/secret/*"}}"#;
+        let put_policy = service.handle(&make_request(
+            Method::Put,
+            "/old?policy",
+            Some("abc123:s3cret"),
+            policy,
+        ));
+        assert_eq!(put_policy.status, 204);
+
+        service.rename_bucket("old", "new").unwrap();
+
+        let get = service.handle(&make_request(
+            Method::Get,
+            "/new/a.txt",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert_eq!(get.body, b"data");
+        let list = service.handle(&make_request(
+            Method::Get,
+            "/new",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert!(String::from_utf8(list.body).unwrap().contains("a.txt"));
+        let described = service.catalog_mut().describe_bucket("new").unwrap();
+        assert_eq!(described.metadata.owner, "abc123");
+        assert_eq!(described.metadata.created_at, 7);
+        let old = service.handle(&make_request(
+            Method::Get,
+            "/old/a.txt",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert_eq!(old.status, 404);
+
+        // The policy moved with the bucket and still denies.
+        let secret = service.handle(&make_request(
+            Method::Get,
+            "/new/secret/b.txt",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert_eq!(secret.status, 403);
+        let moved = service.handle(&make_request(
+            Method::Get,
+            "/new?policy",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert!(String::from_utf8(moved.body)
+            .unwrap()
+            .contains("\"arn:aws:s3:::new/secret/*\""));
+        let stale = service.handle(&make_request(
+            Method::Get,
+            "/old?policy",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert_eq!(stale.status, 404);
+
+        // A policy that names buckets by wildcard cannot follow a rename.
+        let wildcard = br#"{"Statement": {"Effect": "Deny", "Principal": "*",
+            "Action": "s3:GetObject", "Resource": "arn:aws:s3:::ne*/secret/*"}}"#;
+        service.handle(&make_request(
+            Method::Put,
+            "/new?policy",
+            Some("abc123:s3cret"),
+            wildcard,
+        ));
+        assert_eq!(
+            service.rename_bucket("new", "newer"),
+            Err(RenameError::PolicyNotPortable)
+        );
+        assert!(service.catalog_mut().describe_bucket("new").is_ok());
+    }
+
+    #[test]
+    fn permissions_are_scoped_to_bucket_and_route() {
+        use security::permissions::Permission;
+
+        let mut service = new_service();
+        service.catalog_mut().create_bucket("docs").unwrap();
+        service
+            .keystore_mut()
+            .insert(ApiKeyEntry::with_secret(
+                "reader",
+                "r",
+                [1; SALT_LEN],
+                "photos",
+                Permissions::of(&[Permission::Read, Permission::List]),
+            ))
+            .unwrap();
+        let mut call = |method, path: &str| {
+            service
+                .handle(&make_request(method, path, Some("reader:r"), b"x"))
+                .status
+        };
+        // Authorized, so the lookup itself runs.
+        assert_eq!(call(Method::Get, "/photos/none.txt"), 404);
+        assert_eq!(call(Method::Put, "/photos/a.txt"), 403);
+        assert_eq!(call(Method::Get, "/docs"), 403);
+        assert_eq!(call(Method::Delete, "/photos"), 403);
+        assert_eq!(call(Method::Post, "/photos/big.bin?uploads"), 403);
+        assert_eq!(call(Method::Get, "/_logs"), 403);
+        assert_eq!(call(Method::Get, "/"), 403);
+
+        // A key scoped to another bucket cannot touch this bucket's upload
+        // by naming its ID.
+        service
+            .keystore_mut()
+            .insert(ApiKeyEntry::with_secret(
+                "docs-admin",
+                "d",
+                [2; SALT_LEN],
+                "docs",
+                Permissions::ALL,
+            ))
+            .unwrap();
+        let upload_id = service
+            .multipart_mut()
+            .initiate("photos", "secret.bin", ObjectAttributes::default(), 0)
+            .unwrap();
+        for method in [Method::Post, Method::Delete, Method::Put] {
+            let path = format!("/docs/secret.bin?partNumber=1&uploadId={}", upload_id);
+            let response = service.handle(&make_request(method, &path, Some("docs-admin:d"), b"x"));
+            assert_eq!(response.status, 404);
+        }
+        let mut pending = Vec::new();
+        service
+            .multipart_mut()
+            .pending("photos", &mut |id, _, _| pending.push(id.to_string()));
+        assert_eq!(pending, [upload_id]);
+    }
+
+    #[test]
+    fn admin_routes_manage_keys_and_report_usage() {
+        let mut service = new_service();
+        service.set_clock(1_000);
+        let mut call = |method, path: &str, key: &str, body: &[u8]| {
+            service.handle(&make_request(method, path, Some(key), body))
+        };
+        let spec = b"secret=pw1\nbucket=photos\npermissions=read,write,list";
+        assert_eq!(
+            call(Method::Put, "/_admin/keys/ops", "abc123:s3cret", spec).status,
+            200
+        );
+        assert_eq!(
+            call(Method::Put, "/_admin/keys/ops", "abc123:s3cret", spec).status,
+            409
+        );
+        assert_eq!(
+            call(Method::Put, "/photos/a.txt", "ops:pw1", b"hello").status,
+            200
+        );
+        // Scoped, non-admin keys cannot reach the admin API.
+        assert_eq!(
+            call(Method::Get, "/_admin/keys", "ops:pw1", &[]).status,
+            403
+        );
+
+        let listing = call(Method::Get, "/_admin/keys", "abc123:s3cret", &[]);
+        let listing = String::from_utf8(listing.body).unwrap();
+        assert!(listing.contains("ops bucket=photos permissions=read,write,list status=active\n"));
+        assert!(!listing.contains("pw1"));
+
+        let rotate = b"secret=pw2\ngrace=60";
+        assert_eq!(
+            call(
+                Method::Post,
+                "/_admin/keys/ops/rotate",
+                "abc123:s3cret",
+                rotate
+            )
+            .status,
+            200
+        );
+        assert_eq!(
+            call(Method::Get, "/photos/a.txt", "ops:pw1", &[]).status,
+            200
+        );
+        assert_eq!(
+            call(Method::Get, "/photos/a.txt", "ops:pw2", &[]).status,
+            200
+        );
+        service.set_clock(1_061);
+        let mut call = |method, path: &str, key: &str, body: &[u8]| {
+            service.handle(&make_request(method, path, Some(key), body))
+        };
+        assert_eq!(
+            call(Method::Get, "/photos/a.txt", "ops:pw1", &[]).status,
+            403
+        );
+
+        let usage = call(Method::Get, "/_admin/buckets/photos", "abc123:s3cret", &[]);
+        let usage = String::from_utf8(usage.body).unwrap();
+        assert!(usage.contains("Objects: 1\nBytes: 5\n"));
+
+        assert_eq!(
+            call(Method::Delete, "/_admin/keys/ops", "abc123:s3cret", &[]).status,
+            204
+        );
+        let revoked = call(Method::Get, "/photos/a.txt", "ops:pw2", &[]);
+        assert_eq!(
+            (revoked.status, revoked.body),
+            (403, b"KeyRevoked".to_vec())
+        );
+        assert_eq!(
+            call(Method::Get, "/_admin/keys/nobody", "abc123:s3cret", &[]).status,
+            404
+        );
+        assert_eq!(
+            call(Method::Put, "/_admin/keys/x", "abc123:s3cret", b"secret=").status,
+            400
+        );
+    }
+
+    #[test]
+    fn bucket_policy_grants_and_denies() {
+        let mut service = new_service();
+        service
+            .keystore_mut()
+            .insert(ApiKeyEntry::with_secret(
+                "guest",
+                "g",
+                [4; SALT_LEN],
+                "docs",
+                Permissions::ALL,
+            ))
+            .unwrap();
+        let policy = br#"{"Statement": [
+            {"Effect": "Allow", "Principal": {"AWS": "guest"}, "Action": "s3:GetObject",
+             "Resource": "arn:aws:s3:::photos/public/*",
+             "Condition": {"IpAddress": {"aws:SourceIp": "192.168.0.0/16"}}},
+            {"Effect": "Deny", "Principal": "*", "Action": "s3:DeleteObject",
+             "Resource": "arn:aws:s3:::photos/*"},
+            {"Effect": "Deny", "Principal": "*", "Action": "s3:PutObject",
+             "Resource": "arn:aws:s3:::photos/*",
+             "Condition": {"NotIpAddress": {"aws:SourceIp": "192.168.0.0/16"}}}
+        ]}"#;
+        let mut call = |method, path: &str, key: &str, body: &[u8]| {
+            let mut request = make_request(method, path, Some(key), body);
+            request.remote_addr = Some([192, 168, 1, 20]);
+            service.handle(&request).status
+        };
+        assert_eq!(
+            call(Method::Put, "/photos?policy", "abc123:s3cret", b"{}"),
+            400
+        );
+        assert_eq!(call(Method::Put, "/photos?policy", "guest:g", policy), 403);
+        assert_eq!(
+            call(Method::Put, "/photos?policy", "abc123:s3cret", policy),
+            204
+        );
+        assert_eq!(
+            call(Method::Put, "/photos/public/a.txt", "abc123:s3cret", b"a"),
+            200
+        );
+
+        // The policy reaches past the guest key's bucket scope...
+        assert_eq!(
+            call(Method::Get, "/photos/public/a.txt", "guest:g", &[]),
+            200
+        );
+        assert_eq!(
+            call(Method::Get, "/photos/private.txt", "guest:g", &[]),
+            403
+        );
+        // ...and its deny overrides even an all-powerful key.
+        assert_eq!(
+            call(Method::Delete, "/photos/public/a.txt", "abc123:s3cret", &[]),
+            403
+        );
+
+        let mut request = make_request(Method::Get, "/photos/public/a.txt", Some("guest:g"), &[]);
+        request.remote_addr = Some([10, 0, 0, 1]);
+        assert_eq!(service.handle(&request).status, 403);
+
+        // Uploads are denied outside the network, including from an
+        // unknown address.
+        let mut request = make_request(Method::Put, "/photos/b.txt", Some("abc123:s3cret"), b"b");
+        request.remote_addr = Some([10, 0, 0, 1]);
+        assert_eq!(service.handle(&request).status, 403);
+        request.remote_addr = None;
+        assert_eq!(service.handle(&request).status, 403);
+
+        let fetched = service.handle(&make_request(
+            Method::Get,
+            "/photos?policy",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert_eq!(fetched.body, policy.to_vec());
+    }
+
+    #[test]
+    fn rate_limits_keys_and_locks_out_guessers() {
+        use security::ratelimit::{RateLimitConfig, TokenBucket};
+
+        let mut service = new_service();
+        service.set_rate_limiter(RateLimiter::new(RateLimitConfig {
+            requests: TokenBucket {
+                burst: 2,
+                refill: 1,
+            },
+            interval: 10,
+            lockout_after: 3,
+            ..RateLimitConfig::default()
+        }));
+        let mut call = |key: &str, addr: u8| {
+            let mut request = make_request(Method::Get, "/photos/a.txt", Some(key), &[]);
+            request.remote_addr = Some([10, 0, 0, addr]);
+            let response = service.handle(&request);
+            (response.status, response.body)
+        };
+        assert_eq!(call("abc123:s3cret", 1).0, 404);
+        assert_eq!(call("abc123:s3cret", 1).0, 404);
+        assert_eq!(call("abc123:s3cret", 2), (503, b"SlowDown".to_vec()));
+
+        for _ in 0..3 {
+            assert_eq!(call("abc123:guess", 3).0, 403);
+        }
+        // Locked out even with the right secret; other clients are not.
+        assert_eq!(call("abc123:s3cret", 3), (503, b"SlowDown".to_vec()));
+        assert_eq!(call("nobody:x", 4).0, 403);
+
+        service.set_clock(10);
+        let mut request = make_request(Method::Get, "/photos/a.txt", Some("abc123:s3cret"), &[]);
+        request.remote_addr = Some([10, 0, 0, 4]);
+        assert_eq!(service.handle(&request).status, 404);
+    }
+
+    #[test]
+    fn client_certificates_authorize_as_their_key() {
+        use crate::auth::CertificateBinding;
+        use crate::http::ClientCertificate;
+
+        let mut service = new_service();
+        service.catalog_mut().create_bucket("docs").unwrap();
+        service
+            .keystore_mut()
+            .insert(ApiKeyEntry::with_secret(
+                "backup",
+                "unused",
+                [5; SALT_LEN],
+                "photos",
+                Permissions::of(&[Permission::Read, Permission::List]),
+            ))
+            .unwrap();
+        service
+            .certificates_mut()
+            .bind(CertificateBinding::Subject("CN=backup,O=Ops"), "backup");
+        let mut call = |method, path: &str, key: Option<&str>, subject: &str| {
+            let mut request = make_request(method, path, key, &[]);
+            request.client_cert = Some(ClientCertificate {
+                subject: subject.to_string(),
+                spki_sha256: [0; 32],
+            });
+            let response = service.handle(&request);
+            (response.status, response.body)
+        };
+        assert_eq!(
+            call(Method::Get, "/photos/a.txt", None, "CN=backup,O=Ops").0,
+            404
+        );
+        // The bound key's bucket scope and permissions apply.
+        assert_eq!(
+            call(Method::Delete, "/photos/a.txt", None, "CN=backup,O=Ops").0,
+            403
+        );
+        assert_eq!(call(Method::Get, "/docs", None, "CN=backup,O=Ops").0, 403);
+        assert_eq!(
+            call(Method::Get, "/photos/a.txt", None, "CN=intruder"),
+            (403, b"UnknownClientCertificate".to_vec())
+        );
+        // Explicit credentials take precedence over the certificate: the
+        // delete gets past authorization to the missing object.
+        assert_eq!(
+            call(
+                Method::Delete,
+                "/photos/a.txt",
+                Some("abc123:s3cret"),
+                "CN=backup,O=Ops"
+            )
+            .0,
+            404
+        );
+
+        let audit = service.audit_log();
+        assert_eq!(
+            audit.entries().next().unwrap().key_id.as_deref(),
+            Some("backup")
+        );
+    }
+
+    #[test]
+    fn audit_log_records_every_decision() {
+        use security::audit::AuditDecision;
+
+        let mut service = new_service();
+        service.set_clock(7);
+        let mut call = |method, path: &str, key: Option<&str>| {
+            service.handle(&make_request(method, path, key, &[]))
+        };
+        call(Method::Get, "/photos/a.txt", Some("abc123:s3cret"));
+        call(Method::Get, "/photos/a.txt", Some("abc123:nope"));
+        call(Method::Delete, "/docs", None);
+        let audit = call(Method::Get, "/_admin/audit", Some("abc123:s3cret"));
+        let audit = String::from_utf8(audit.body).unwrap();
+        assert!(audit
+            .contains("0 7 abc123 s3:GetObject arn:aws:s3:::photos/a.txt allow KeyPermission\n"));
+        assert!(audit.contains("1 7 - s3:GetObject arn:aws:s3:::photos/a.txt deny InvalidApiKey\n"));
+        assert!(audit.contains("2 7 - s3:DeleteBucket arn:aws:s3:::docs deny MissingApiKey\n"));
+        assert!(audit.contains("3 7 abc123 admin:GetAudit /_admin/audit allow KeyPermission\n"));
+        assert!(audit.contains("Chain: intact\n"));
+
+        let log = service.audit_log();
+        assert_eq!(log.entries().count(), 4);
+        assert_eq!(
+            log.entries()
+                .filter(|e| e.decision == AuditDecision::Deny)
+                .count(),
+            2
+        );
+        assert_eq!(log.verify(Some(&log.head())), Ok(()));
+    }
+
+    #[test]
+    fn presigned_put_and_get() {
+        use crate::auth::{presign, PresignRequest};
+
+        let mut service = new_service();
+        service
+            .keystore_mut()
+            .insert(ApiKeyEntry::with_signing_secret(
+                "signer",
+                "topsecret",
+                [3; SALT_LEN],
+                "photos",
+                Permissions::ALL,
+            ))
+            .unwrap();
+        service.set_clock(1_700_000_000);
+        let url = |method| {
+            presign(PresignRequest {
+                method,
+                host: "s3.local",
+                bucket: "photos",
+                key: "my cats/a b+c&d.jpg",
+                key_id: "signer",
+                secret: "topsecret",
+                region: "us-east-1",
+                now: 1_700_000_000,
+                expires: 300,
+            })
+        };
+        let with_host = |method, path: &str, body: &[u8]| {
+            let mut request = make_request(method, path, None, body);
+            request.headers.push(Header {
+                name: "Host".to_string(),
+                value: "s3.local".to_string(),
+            });
+            request
+        };
+
+        let put = service.handle(&with_host(Method::Put, &url(Method::Put), b"meow"));
+        assert_eq!(put.status, 200);
+        let get_url = url(Method::Get);
+        let get = service.handle(&with_host(Method::Get, &get_url, &[]));
+        assert_eq!(get.body, b"meow");
+        // Stored under the decoded key, not its escaped form.
+        assert!(service
+            .catalog_mut()
+            .object_metadata("photos", "my cats/a b+c&d.jpg")
+            .is_ok());
+        let malformed = service.handle(&make_request(
+            Method::Get,
+            "/photos/a%zz",
+            Some("abc123:s3cret"),
+            &[],
+        ));
+        assert_eq!(malformed.status, 400);
+
+        service.set_clock(1_700_000_301);
+        let expired = service.handle(&with_host(Method::Get, &get_url, &[]));
+        assert_eq!(expired.status, 403);
+        assert_eq!(expired.body, b"RequestExpired");
     }
 }
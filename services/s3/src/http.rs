@@ -17,6 +17,20 @@ pub struct Request {
     pub path: String,
     pub headers: Vec<Header>,
     pub body: Vec<u8>,
+    /// Client IPv4 address, when the transport knows it.
+    pub remote_addr: Option<[u8; 4]>,
+    /// Certificate the client presented over mutual TLS.
+    pub client_cert: Option<ClientCertificate>,
+}
+
+/// Identity of a client certificate. Only set by the TLS layer once it has
+/// verified the chain and the client's handshake signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCertificate {
+    /// Subject distinguished name, e.g. `CN=backup,O=Ops`.
+    pub subject: String,
+    /// SHA-256 of the DER-encoded SubjectPublicKeyInfo.
+    pub spki_sha256: [u8; 32],
 }
 
 impl Request {
@@ -85,6 +99,8 @@ pub fn parse_request(raw: &[u8]) -> Option<Request> {
         path,
         headers,
         body,
+        remote_addr: None,
+        client_cert: None,
     })
 }
 
@@ -96,10 +112,10 @@ pub fn build_response(response: &Response) -> Vec<u8> {
         status_line,
         "HTTP/1.1 {}\r\n",
         match response.status {
-            200 => "200 OK",
-            403 => "403 Forbidden",
-            404 => "404 Not Found",
-            500 => "500 Internal Server Error",
+            200 => "200 OK".to_string(),
+            403 => "403 Forbidden".to_string(),
+            404 => "404 Not Found".to_string(),
+            500 => "500 Internal Server Error".to_string(),
             other => {
                 let mut code = alloc::string::String::new();
                 let _ = write!(code, "{}", other);
@@ -122,6 +138,7 @@ pub fn build_response(response: &Response) -> Vec<u8> {
 #[cfg(test)]
 mod tests {
     use super::{build_response, parse_request, Method, Response};
+    use alloc::string::ToString;
 
     #[test]
     fn parse_basic_request() {
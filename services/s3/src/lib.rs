@@ -2,8 +2,10 @@
 
 extern crate alloc;
 
+pub mod admin;
 pub mod auth;
 pub mod error;
+pub mod fsck;
 pub mod handlers;
 pub mod http;
 pub mod log;
@@ -1,9 +1,13 @@
 #![allow(dead_code)]
 
 use alloc::collections::BTreeMap;
-use alloc::string::String;
+use alloc::format;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 
+use filesystem::catalog::ObjectAttributes;
+use filesystem::lifecycle::LifecycleHooks;
+
 pub struct MultipartUpload<'a> {
     pub upload_id: &'a str,
     pub bucket: &'a str,
@@ -16,11 +20,50 @@ pub struct MultipartPart<'a> {
     pub data: &'a [u8],
 }
 
+/// Parts of a completed upload joined in order, with the attributes given
+/// when it was initiated.
+pub struct CompletedUpload {
+    pub data: Vec<u8>,
+    pub attributes: ObjectAttributes,
+}
+
 pub trait MultipartManager {
-    fn initiate(&mut self, bucket: &str, key: &str) -> Result<String, MultipartError>;
+    /// Starts an upload, keeping the attributes the finished object is
+    /// stored with and recording `initiated_at` for lifecycle rules.
+    fn initiate(
+        &mut self,
+        bucket: &str,
+        key: &str,
+        attributes: ObjectAttributes,
+        initiated_at: u64,
+    ) -> Result<String, MultipartError>;
     fn put_part(&mut self, part: MultipartPart<'_>) -> Result<(), MultipartError>;
-    fn complete(&mut self, request: &MultipartUpload<'_>) -> Result<Vec<u8>, MultipartError>;
+    fn complete(
+        &mut self,
+        request: &MultipartUpload<'_>,
+    ) -> Result<CompletedUpload, MultipartError>;
     fn abort(&mut self, request: &MultipartUpload<'_>) -> Result<(), MultipartError>;
+    /// Visits `(upload id, key, initiated at)` for each upload in `bucket`.
+    fn pending(&self, bucket: &str, visit: &mut dyn FnMut(&str, &str, u64));
+}
+
+/// Exposes a multipart manager's incomplete uploads to lifecycle rules.
+pub struct MultipartHooks<'a, M: MultipartManager>(pub &'a mut M);
+
+impl<M: MultipartManager> LifecycleHooks for MultipartHooks<'_, M> {
+    fn incomplete_uploads(&self, bucket: &str, visit: &mut dyn FnMut(&str, &str, u64)) {
+        self.0.pending(bucket, visit);
+    }
+
+    fn abort_upload(&mut self, bucket: &str, key: &str, upload_id: &str) -> bool {
+        self.0
+            .abort(&MultipartUpload {
+                upload_id,
+                bucket,
+                key,
+            })
+            .is_ok()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -36,6 +79,8 @@ pub struct InMemoryMultipart {
 struct UploadState {
     bucket: String,
     key: String,
+    attributes: ObjectAttributes,
+    initiated_at: u64,
     parts: BTreeMap<u32, Vec<u8>>,
 }
 
@@ -45,6 +90,21 @@ impl InMemoryMultipart {
             uploads: BTreeMap::new(),
         }
     }
+
+    /// Removes the upload, but only if it belongs to the requested bucket
+    /// and key; a mismatch leaves it in place.
+    fn take(&mut self, request: &MultipartUpload<'_>) -> Result<UploadState, MultipartError> {
+        let state = self
+            .uploads
+            .get(request.upload_id)
+            .ok_or(MultipartError::NotFound)?;
+        if state.bucket != request.bucket || state.key != request.key {
+            return Err(MultipartError::InvalidState);
+        }
+        self.uploads
+            .remove(request.upload_id)
+            .ok_or(MultipartError::NotFound)
+    }
 }
 
 impl Default for InMemoryMultipart {
@@ -54,11 +114,19 @@ impl Default for InMemoryMultipart {
 }
 
 impl MultipartManager for InMemoryMultipart {
-    fn initiate(&mut self, bucket: &str, key: &str) -> Result<String, MultipartError> {
+    fn initiate(
+        &mut self,
+        bucket: &str,
+        key: &str,
+        attributes: ObjectAttributes,
+        initiated_at: u64,
+    ) -> Result<String, MultipartError> {
         let upload_id = format!("{}:{}:{}", bucket, key, self.uploads.len() + 1);
         let state = UploadState {
             bucket: bucket.to_string(),
             key: key.to_string(),
+            attributes,
+            initiated_at,
             parts: BTreeMap::new(),
         };
         self.uploads.insert(upload_id.clone(), state);
@@ -74,30 +142,31 @@ impl MultipartManager for InMemoryMultipart {
         Ok(())
     }
 
-    fn complete(&mut self, request: &MultipartUpload<'_>) -> Result<Vec<u8>, MultipartError> {
-        let state = self
-            .uploads
-            .remove(request.upload_id)
-            .ok_or(MultipartError::NotFound)?;
-        if state.bucket != request.bucket || state.key != request.key {
-            return Err(MultipartError::InvalidState);
-        }
+    fn complete(
+        &mut self,
+        request: &MultipartUpload<'_>,
+    ) -> Result<CompletedUpload, MultipartError> {
+        let state = self.take(request)?;
         let mut combined = Vec::new();
         for (_number, data) in state.parts {
             combined.extend_from_slice(&data);
         }
-        Ok(combined)
+        Ok(CompletedUpload {
+            data: combined,
+            attributes: state.attributes,
+        })
     }
 
     fn abort(&mut self, request: &MultipartUpload<'_>) -> Result<(), MultipartError> {
-        let state = self
-            .uploads
-            .remove(request.upload_id)
-            .ok_or(MultipartError::NotFound)?;
-        if state.bucket != request.bucket || state.key != request.key {
-            return Err(MultipartError::InvalidState);
+        self.take(request).map(drop)
+    }
+
+    fn pending(&self, bucket: &str, visit: &mut dyn FnMut(&str, &str, u64)) {
+        for (upload_id, state) in &self.uploads {
+            if state.bucket == bucket {
+                visit(upload_id, &state.key, state.initiated_at);
+            }
         }
-        Ok(())
     }
 }
 
@@ -108,7 +177,13 @@ mod tests {
     #[test]
     fn initiate_put_complete() {
         let mut manager = InMemoryMultipart::new();
-        let upload_id = manager.initiate("photos", "album.zip").unwrap();
+        let attributes = ObjectAttributes {
+            content_type: "application/zip".to_string(),
+            ..ObjectAttributes::default()
+        };
+        let upload_id = manager
+            .initiate("photos", "album.zip", attributes, 0)
+            .unwrap();
         manager
             .put_part(MultipartPart {
                 upload_id: &upload_id,
@@ -123,20 +198,23 @@ mod tests {
                 data: b"part2",
             })
             .unwrap();
-        let combined = manager
+        let completed = manager
             .complete(&MultipartUpload {
                 upload_id: &upload_id,
                 bucket: "photos",
                 key: "album.zip",
             })
             .unwrap();
-        assert_eq!(combined, b"part1part2");
+        assert_eq!(completed.data, b"part1part2");
+        assert_eq!(completed.attributes.content_type, "application/zip");
     }
 
     #[test]
     fn abort_discard_upload() {
         let mut manager = InMemoryMultipart::new();
-        let upload_id = manager.initiate("docs", "report.bin").unwrap();
+        let upload_id = manager
+            .initiate("docs", "report.bin", ObjectAttributes::default(), 0)
+            .unwrap();
         manager
             .put_part(MultipartPart {
                 upload_id: &upload_id,
@@ -159,5 +237,23 @@ mod tests {
             }),
             Err(MultipartError::NotFound)
         ));
+
+        // A mismatched bucket leaves the upload in place.
+        let upload_id = manager
+            .initiate("docs", "keep.bin", ObjectAttributes::default(), 0)
+            .unwrap();
+        let elsewhere = MultipartUpload {
+            upload_id: &upload_id,
+            bucket: "photos",
+            key: "keep.bin",
+        };
+        assert_eq!(manager.abort(&elsewhere), Err(MultipartError::InvalidState));
+        assert!(matches!(
+            manager.complete(&elsewhere),
+            Err(MultipartError::InvalidState)
+        ));
+        let mut pending = 0;
+        manager.pending("docs", &mut |_, _, _| pending += 1);
+        assert_eq!(pending, 1);
     }
 }
@@ -22,6 +22,11 @@ pub trait ObjectStore {
     fn put(&mut self, key: &str, data: &[u8]) -> Result<ObjectMetadata, ObjectError>;
     fn get(&self, key: &str, buffer: &mut [u8]) -> Result<ObjectMetadata, ObjectError>;
     fn delete(&mut self, key: &str) -> Result<(), ObjectError>;
+    /// Moves the data stored under `from` to `to`, replacing any object
+    /// already stored there.
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), ObjectError>;
+    /// Visits every stored key together with its size in bytes.
+    fn list(&self, visit: &mut dyn FnMut(&str, u64));
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -94,4 +99,19 @@ impl ObjectStore for InMemoryObjectStore {
             .map(|_| ())
             .ok_or(ObjectError::NotFound)
     }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), ObjectError> {
+        if to.is_empty() {
+            return Err(ObjectError::InvalidKey);
+        }
+        let data = self.objects.remove(from).ok_or(ObjectError::NotFound)?;
+        self.objects.insert(to.to_string(), data);
+        Ok(())
+    }
+
+    fn list(&self, visit: &mut dyn FnMut(&str, u64)) {
+        for (key, data) in &self.objects {
+            visit(key, data.len() as u64);
+        }
+    }
 }